@@ -4,6 +4,8 @@ use dada_ir::{
     span::LineColumn,
 };
 
+use crate::breakpoint::Breakpoint;
+
 /// Salsa input: the set of breakpoint locations.
 ///
 /// Defaults to empty set if not explicitly set.
@@ -26,3 +28,29 @@ pub fn breakpoints_in_code(db: &dyn crate::Db, code: Code) -> Vec<syntax::Expr>
         .map(|bp| bp.expr)
         .collect()
 }
+
+/// Finds the breakpoint at the start of each line of code in `filename`,
+/// skipping blank lines, comments, and lines whose breakpoint is the same
+/// as that of an earlier line. Debuggers set these to step line by line.
+pub fn line_start_breakpoints(
+    db: &dyn crate::Db,
+    filename: Filename,
+) -> Vec<(LineColumn, Breakpoint)> {
+    let source_text = dada_ir::manifest::source_text(db, filename);
+    let mut result: Vec<(LineColumn, Breakpoint)> = vec![];
+    for (line0, line) in source_text.lines().enumerate() {
+        let code = line.trim_start();
+        if code.is_empty() || code.starts_with('#') {
+            continue;
+        }
+
+        let position = LineColumn::new0(line0, line.len() - code.len());
+        if let Some(breakpoint) = crate::breakpoint::find(db, filename, position) {
+            let span = breakpoint.span(db);
+            if result.iter().all(|(_, b)| b.span(db) != span) {
+                result.push((position, breakpoint));
+            }
+        }
+    }
+    result
+}
@@ -68,6 +68,7 @@ impl Cursor {
                 let loop_context = brewery.loop_context(*from_expr);
                 self.brew_expr_and_assign_to(brewery, loop_context.loop_value, *with_value);
                 self.push_breakpoint_end(brewery, Some(loop_context.loop_value), origin);
+                self.end_atomic_sections(brewery, loop_context.atomic_depth, origin);
                 self.terminate_and_goto(brewery, loop_context.break_block, origin);
             }
 
@@ -75,6 +76,7 @@ impl Cursor {
                 self.push_breakpoint_start(brewery, origin);
                 let loop_context = brewery.loop_context(*from_expr);
                 self.push_breakpoint_end(brewery, None, origin);
+                self.end_atomic_sections(brewery, loop_context.atomic_depth, origin);
                 self.terminate_and_goto(brewery, loop_context.continue_block, origin);
             }
 
@@ -82,6 +84,7 @@ impl Cursor {
                 self.push_breakpoint_start(brewery, origin);
                 if let Some(value_place) = self.brew_expr_to_temporary(brewery, *value_expr) {
                     self.push_breakpoint_end(brewery, Some(value_place), origin);
+                    self.end_atomic_sections(brewery, 0, origin);
                     self.terminate_and_diverge(
                         brewery,
                         bir::TerminatorData::Return(value_place),
@@ -99,6 +102,12 @@ impl Cursor {
             validated::ExprData::Assign(place, value_expr) => {
                 self.push_breakpoint_start(brewery, origin);
                 let (place, origins) = self.brew_place(brewery, *place);
+
+                // Breakpoints on the parts of the place end along with the
+                // assignment (below), so they must start here too.
+                for &place_origin in &origins {
+                    self.push_breakpoint_start(brewery, place_origin);
+                }
                 self.brew_expr_and_assign_to(brewery, place, *value_expr);
                 self.push_breakpoint_ends(brewery, None, origins, origin)
             }
@@ -203,12 +212,13 @@ impl Cursor {
                         continue_block: body_block,
                         break_block,
                         loop_value: target,
+                        atomic_depth: body_brewery.atomic_depth(),
                     },
                 );
                 let mut body_cursor = self.with_end_block(body_block);
-                body_cursor.brew_expr_for_side_effects(brewery, *body);
+                body_cursor.brew_expr_for_side_effects(&mut body_brewery, *body);
                 body_cursor.terminate_and_diverge(
-                    brewery,
+                    &mut body_brewery,
                     bir::TerminatorData::Goto(body_block),
                     origin,
                 );
@@ -357,9 +367,9 @@ impl Cursor {
                 self.push_breakpoint_start(brewery, origin);
                 self.terminate_and_continue(brewery, bir::TerminatorData::StartAtomic, origin);
 
-                // FIXME what if a break exits through an atomic?
-
-                self.brew_expr_and_assign_to(brewery, target, *subexpr);
+                let mut atomic_brewery = brewery.subbrewery();
+                atomic_brewery.enter_atomic();
+                self.brew_expr_and_assign_to(&mut atomic_brewery, target, *subexpr);
 
                 self.terminate_and_continue(brewery, bir::TerminatorData::EndAtomic, origin);
                 self.push_breakpoint_end(brewery, Some(target), origin);
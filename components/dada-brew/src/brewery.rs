@@ -22,6 +22,9 @@ pub struct Brewery<'me> {
     loop_contexts: Map<validated::Expr, LoopContext>,
     variables: Rc<Map<validated::LocalVariable, bir::LocalVariable>>,
     dummy_terminator: bir::Terminator,
+
+    /// Number of atomic sections enclosing the code being brewed.
+    atomic_depth: usize,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Hash)]
@@ -29,6 +32,10 @@ pub struct LoopContext {
     pub continue_block: bir::BasicBlock,
     pub break_block: bir::BasicBlock,
     pub loop_value: bir::Place,
+
+    /// Number of atomic sections enclosing the loop; a `break` or `continue`
+    /// must end any atomic sections started since.
+    pub atomic_depth: usize,
 }
 
 impl<'me> Brewery<'me> {
@@ -60,6 +67,7 @@ impl<'me> Brewery<'me> {
             loop_contexts: Default::default(),
             variables,
             dummy_terminator,
+            atomic_depth: 0,
         }
     }
 
@@ -90,6 +98,7 @@ impl<'me> Brewery<'me> {
             loop_contexts: self.loop_contexts.clone(),
             variables: self.variables.clone(),
             dummy_terminator: self.dummy_terminator,
+            atomic_depth: self.atomic_depth,
         }
     }
 
@@ -132,6 +141,16 @@ impl<'me> Brewery<'me> {
         self.loop_contexts[&loop_expr]
     }
 
+    /// Number of atomic sections enclosing the code being brewed.
+    pub fn atomic_depth(&self) -> usize {
+        self.atomic_depth
+    }
+
+    /// Record that we are brewing the body of an atomic section; typically this is done in a "subbrewery".
+    pub fn enter_atomic(&mut self) {
+        self.atomic_depth += 1;
+    }
+
     /// Push a new loop context into the brewery; typically this is done in a "subbrewery".
     pub fn push_loop_context(&mut self, loop_expr: validated::Expr, loop_context: LoopContext) {
         let old_value = self.loop_contexts.insert(loop_expr, loop_context);
@@ -15,7 +15,10 @@ pub(crate) struct Cursor {
 
     /// The basic block we are currently appending to.
     ///
-    /// If `None`, we are in a section of dead code.
+    /// If `None`, we have just diverged (e.g., after a `return`).
+    /// Any code that follows is still brewed, but into a fresh block
+    /// that nothing jumps to, so that later passes can report it as
+    /// unreachable.
     end_block: Option<bir::BasicBlock>,
 }
 
@@ -44,10 +47,22 @@ impl Cursor {
         self.end_block.is_none()
     }
 
-    pub(crate) fn push_statement(&mut self, brewery: &mut Brewery<'_>, statement: bir::Statement) {
-        if let Some(end_block) = self.end_block {
-            brewery[end_block].statements.push(statement);
-        }
+    /// Returns the block we are appending to, starting a fresh
+    /// (unreachable) block if we are in dead code.
+    fn end_block(&mut self, brewery: &mut Brewery<'_>, origin: ExprOrigin) -> bir::BasicBlock {
+        *self
+            .end_block
+            .get_or_insert_with(|| brewery.dummy_block(origin))
+    }
+
+    pub(crate) fn push_statement(
+        &mut self,
+        brewery: &mut Brewery<'_>,
+        statement: bir::Statement,
+        origin: ExprOrigin,
+    ) {
+        let end_block = self.end_block(brewery, origin);
+        brewery[end_block].statements.push(statement);
     }
 
     fn terminate(
@@ -57,12 +72,11 @@ impl Cursor {
         origin: ExprOrigin,
         next_block: Option<bir::BasicBlock>,
     ) {
-        if let Some(end_block) = self.end_block {
-            let terminator = brewery.add(terminator_data, origin);
-            brewery[end_block].terminator = terminator;
-            brewery[end_block].statements.shrink_to_fit();
-            self.end_block = next_block;
-        }
+        let end_block = self.end_block(brewery, origin);
+        let terminator = brewery.add(terminator_data, origin);
+        brewery[end_block].terminator = terminator;
+        brewery[end_block].statements.shrink_to_fit();
+        self.end_block = next_block;
     }
 
     pub(crate) fn terminate_and_diverge(
@@ -93,11 +107,9 @@ impl Cursor {
         value: bir::ExprData,
         origin: ExprOrigin,
     ) {
-        if self.end_block.is_some() {
-            let value = brewery.add(value, origin);
-            let statement = brewery.add(bir::StatementData::Assign(target, value), origin);
-            self.push_statement(brewery, statement);
-        }
+        let value = brewery.add(value, origin);
+        let statement = brewery.add(bir::StatementData::Assign(target, value), origin);
+        self.push_statement(brewery, statement, origin);
     }
 
     /// If any of the origins in `origins`, or `origin`, is a breakpoint expression,
@@ -116,14 +128,14 @@ impl Cursor {
     /// If `origin` is a breakpoint expression, push a "breakpoint-start"
     /// statement onto the current basic block.
     pub(crate) fn push_breakpoint_start(&mut self, brewery: &mut Brewery<'_>, origin: ExprOrigin) {
-        if !origin.synthesized && self.end_block.is_some() {
+        if !origin.synthesized {
             if let Some(breakpoint_index) = brewery.expr_is_breakpoint(origin.syntax_expr) {
                 let filename = brewery.code().filename(brewery.db());
                 let statement = brewery.add(
                     bir::StatementData::BreakpointStart(filename, breakpoint_index),
                     origin,
                 );
-                self.push_statement(brewery, statement);
+                self.push_statement(brewery, statement, origin);
             }
         }
     }
@@ -163,18 +175,32 @@ impl Cursor {
         place: Option<bir::Place>,
         origin: ExprOrigin,
     ) {
-        if !origin.synthesized && self.end_block.is_some() {
+        if !origin.synthesized {
             if let Some(breakpoint_index) = brewery.expr_is_breakpoint(origin.syntax_expr) {
                 let filename = brewery.code().filename(brewery.db());
                 let statement = brewery.add(
                     bir::StatementData::BreakpointEnd(filename, breakpoint_index, expr, place),
                     origin,
                 );
-                self.push_statement(brewery, statement);
+                self.push_statement(brewery, statement, origin);
             }
         }
     }
 
+    /// Ends each atomic section enclosing the current code, innermost first,
+    /// until only `atomic_depth` remain. Used when control leaves the sections
+    /// early, e.g. via `break`.
+    pub(crate) fn end_atomic_sections(
+        &mut self,
+        brewery: &mut Brewery<'_>,
+        atomic_depth: usize,
+        origin: ExprOrigin,
+    ) {
+        for _ in atomic_depth..brewery.atomic_depth() {
+            self.terminate_and_continue(brewery, bir::TerminatorData::EndAtomic, origin);
+        }
+    }
+
     pub(crate) fn terminate_and_goto(
         &mut self,
         brewery: &mut Brewery<'_>,
@@ -5,7 +5,13 @@
 #![allow(incomplete_features)]
 
 #[salsa::jar(Db)]
-pub struct Jar(brew::brew);
+pub struct Jar(
+    brew::brew,
+    optimize::optimize,
+    optimize::optimizations_enabled,
+    verify::verification_enabled,
+    verify::verify,
+);
 
 pub trait Db:
     salsa::DbWithJar<Jar> + dada_breakpoint::Db + dada_ir::Db + dada_parse::Db + dada_validate::Db
@@ -24,4 +30,6 @@ impl<T> Db for T where
 mod brew;
 mod brewery;
 mod cursor;
+pub mod optimize;
 pub mod prelude;
+pub mod verify;
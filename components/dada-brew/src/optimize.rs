@@ -0,0 +1,549 @@
+//! Simplifications applied to the bir before it is executed. Brewing
+//! introduces a temporary for nearly every subexpression, along with
+//! chains of `Goto` blocks; these passes clean some of that up without
+//! changing what the program does.
+//!
+//! Breakpoint statements are never removed or reordered, and everything
+//! that survives keeps its origin, so the debugger and diagnostics work
+//! the same on optimized bir.
+
+use dada_collections::{Map, Set};
+use dada_id::prelude::*;
+use dada_ir::{
+    code::{
+        bir::{self, BirData},
+        validated,
+    },
+    filename::Filename,
+    origin_table::PushOriginIn,
+};
+
+/// Salsa input: whether the interpreter executes optimized bir for
+/// the functions in a given file.
+///
+/// Defaults to false if not explicitly set.
+#[salsa::memoized(in crate::Jar)]
+pub fn optimizations_enabled(_db: &dyn crate::Db, _filename: Filename) -> bool {
+    false // default: off
+}
+
+/// Returns an optimized copy of `bir`.
+#[salsa::memoized(in crate::Jar)]
+pub fn optimize(db: &dyn crate::Db, bir: bir::Bir) -> bir::Bir {
+    let bir_data = bir.data(db);
+    let mut optimizer = Optimizer {
+        tables: bir_data.tables.clone(),
+        origins: bir.origins(db).clone(),
+        start_basic_block: bir_data.start_basic_block,
+    };
+    optimizer.thread_jumps();
+    optimizer.merge_straight_line_blocks();
+    optimizer.eliminate_temporaries();
+    let (tables, origins, start_basic_block) = optimizer.compact();
+
+    bir::Bir::new(
+        db,
+        bir.origin(db),
+        BirData::new(tables, bir_data.num_parameters, start_basic_block),
+        origins,
+    )
+}
+
+/// The number of times each local variable appears.
+type Counts = Map<bir::LocalVariable, usize>;
+
+struct Optimizer {
+    tables: bir::Tables,
+    origins: bir::Origins,
+    start_basic_block: bir::BasicBlock,
+}
+
+impl Optimizer {
+    fn add<V, O>(&mut self, data: V, origin: O) -> V::Key
+    where
+        V: dada_id::InternValue<Table = bir::Tables>,
+        V::Key: PushOriginIn<bir::Origins, Origin = O>,
+    {
+        add(&mut self.tables, &mut self.origins, data, origin)
+    }
+
+    /// Returns the blocks reachable from the start block, in depth-first
+    /// order (so the start block comes first).
+    fn reachable_blocks(&self) -> Vec<bir::BasicBlock> {
+        let mut visited = Set::default();
+        let mut result = vec![];
+        let mut stack = vec![self.start_basic_block];
+        while let Some(block) = stack.pop() {
+            if visited.insert(block) {
+                result.push(block);
+                let terminator = block.data(&self.tables).terminator;
+                let mut successors = terminator.data(&self.tables).successors();
+                successors.reverse();
+                stack.extend(successors);
+            }
+        }
+        result
+    }
+
+    /// Counts, for each reachable block, how many edges lead into it.
+    fn predecessor_counts(&self) -> Map<bir::BasicBlock, usize> {
+        let mut counts: Map<bir::BasicBlock, usize> = Map::default();
+        for block in self.reachable_blocks() {
+            let terminator = block.data(&self.tables).terminator;
+            for successor in terminator.data(&self.tables).successors() {
+                *counts.entry(successor).or_default() += 1;
+            }
+        }
+        counts
+    }
+
+    /// Follows `block` through any empty blocks that just `Goto` another block.
+    fn forward(&self, mut block: bir::BasicBlock) -> bir::BasicBlock {
+        let mut visited = Set::default();
+        while visited.insert(block) {
+            let block_data = block.data(&self.tables);
+            if !block_data.statements.is_empty() {
+                break;
+            }
+            match block_data.terminator.data(&self.tables) {
+                bir::TerminatorData::Goto(next) => block = *next,
+                _ => break,
+            }
+        }
+        block
+    }
+
+    /// Redirects jumps to empty blocks that just `Goto` somewhere else.
+    fn thread_jumps(&mut self) {
+        self.start_basic_block = self.forward(self.start_basic_block);
+        for block in self.reachable_blocks() {
+            let terminator = block.data(&self.tables).terminator;
+            let terminator_data = terminator.data(&self.tables);
+            let threaded_data = map_successors(terminator_data, |target| self.forward(target));
+            if threaded_data != *terminator_data {
+                let origin = self.origins[terminator];
+                let threaded = self.add(threaded_data, origin);
+                block.data_mut(&mut self.tables).terminator = threaded;
+            }
+        }
+    }
+
+    /// Appends a block onto its predecessor when it has only the one
+    /// predecessor and that predecessor always jumps to it.
+    fn merge_straight_line_blocks(&mut self) {
+        let predecessor_counts = self.predecessor_counts();
+        for block in self.reachable_blocks() {
+            loop {
+                let terminator = block.data(&self.tables).terminator;
+                let next = match terminator.data(&self.tables) {
+                    bir::TerminatorData::Goto(next) => *next,
+                    _ => break,
+                };
+                if next == block
+                    || next == self.start_basic_block
+                    || predecessor_counts.get(&next) != Some(&1)
+                {
+                    break;
+                }
+
+                let next_data = next.data(&self.tables).clone();
+                let block_data = block.data_mut(&mut self.tables);
+                block_data.statements.extend(next_data.statements);
+                block_data.terminator = next_data.terminator;
+            }
+        }
+    }
+
+    /// Removes temporaries whose value is immediately given to another place:
+    /// `t = E; x = give t` becomes `x = E`, provided that `t` is not used
+    /// anywhere else.
+    fn eliminate_temporaries(&mut self) {
+        let (definitions, uses) = self.count_local_variables();
+        let eliminable = |optimizer: &Optimizer, lv: bir::LocalVariable| {
+            matches!(
+                optimizer.origins[lv],
+                validated::LocalVariableOrigin::Temporary(_)
+            ) && definitions.get(&lv) == Some(&1)
+                && uses.get(&lv) == Some(&1)
+        };
+
+        let predecessor_counts = self.predecessor_counts();
+        for block in self.reachable_blocks() {
+            // Within the block: `t = E; x = give t`.
+            let mut statements = block.data(&self.tables).statements.clone();
+            let mut index = 0;
+            while index + 1 < statements.len() {
+                let (first, second) = (statements[index], statements[index + 1]);
+                let replacement = match (first.data(&self.tables), second.data(&self.tables)) {
+                    (
+                        bir::StatementData::Assign(temporary, expr),
+                        bir::StatementData::Assign(target, given_expr),
+                    ) => match given_expr.data(&self.tables) {
+                        bir::ExprData::Give(given) => {
+                            match self.temporary_given(*temporary, *given) {
+                                Some(lv) if eliminable(self, lv) => Some((*target, *expr)),
+                                _ => None,
+                            }
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                };
+
+                match replacement {
+                    Some((target, expr)) => {
+                        let origin = self.origins[first];
+                        let statement = self.add(bir::StatementData::Assign(target, expr), origin);
+                        statements[index] = statement;
+                        statements.remove(index + 1);
+                    }
+                    None => index += 1,
+                }
+            }
+            block.data_mut(&mut self.tables).statements = statements;
+
+            // Across blocks: `t = call(...)` as the terminator, followed
+            // by `x = give t` at the start of the (only) successor.
+            let terminator = block.data(&self.tables).terminator;
+            let (temporary, terminator_expr, next) = match terminator.data(&self.tables) {
+                bir::TerminatorData::Assign(temporary, terminator_expr, next) => {
+                    (*temporary, terminator_expr.clone(), *next)
+                }
+                _ => continue,
+            };
+            if next == self.start_basic_block || predecessor_counts.get(&next) != Some(&1) {
+                continue;
+            }
+            let first_statement = match next.data(&self.tables).statements.first() {
+                Some(statement) => *statement,
+                None => continue,
+            };
+            let target = match first_statement.data(&self.tables) {
+                bir::StatementData::Assign(target, given_expr) => match given_expr
+                    .data(&self.tables)
+                {
+                    bir::ExprData::Give(given) => match self.temporary_given(temporary, *given) {
+                        Some(lv) if eliminable(self, lv) => *target,
+                        _ => continue,
+                    },
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            let origin = self.origins[terminator];
+            let terminator = self.add(
+                bir::TerminatorData::Assign(target, terminator_expr, next),
+                origin,
+            );
+            block.data_mut(&mut self.tables).terminator = terminator;
+            next.data_mut(&mut self.tables).statements.remove(0);
+        }
+    }
+
+    /// If `temporary` and `given` both name the same local variable, returns it.
+    fn temporary_given(
+        &self,
+        temporary: bir::Place,
+        given: bir::Place,
+    ) -> Option<bir::LocalVariable> {
+        match (temporary.data(&self.tables), given.data(&self.tables)) {
+            (bir::PlaceData::LocalVariable(a), bir::PlaceData::LocalVariable(b)) if a == b => {
+                Some(*a)
+            }
+            _ => None,
+        }
+    }
+
+    /// Counts how many times each local variable is assigned (as a whole)
+    /// and how many times it is otherwise referenced, in reachable code.
+    fn count_local_variables(&self) -> (Counts, Counts) {
+        let mut definitions = Counts::default();
+        let mut uses = Counts::default();
+
+        let mut count_target = |place: bir::Place, uses: &mut Counts| match place.data(&self.tables)
+        {
+            bir::PlaceData::LocalVariable(lv) => *definitions.entry(*lv).or_default() += 1,
+            _ => self.count_uses(place, uses),
+        };
+
+        for block in self.reachable_blocks() {
+            let block_data = block.data(&self.tables);
+            for statement in &block_data.statements {
+                match statement.data(&self.tables) {
+                    bir::StatementData::Assign(target, expr) => {
+                        count_target(*target, &mut uses);
+                        for place in expr_places(expr.data(&self.tables)) {
+                            self.count_uses(place, &mut uses);
+                        }
+                    }
+                    bir::StatementData::BreakpointStart(..) => {}
+                    bir::StatementData::BreakpointEnd(_, _, _, place) => {
+                        if let Some(place) = place {
+                            self.count_uses(*place, &mut uses);
+                        }
+                    }
+                }
+            }
+
+            match block_data.terminator.data(&self.tables) {
+                bir::TerminatorData::If(place, _, _) | bir::TerminatorData::Return(place) => {
+                    self.count_uses(*place, &mut uses);
+                }
+                bir::TerminatorData::Assign(target, terminator_expr, _) => {
+                    count_target(*target, &mut uses);
+                    match terminator_expr {
+                        bir::TerminatorExpr::Await(place) => self.count_uses(*place, &mut uses),
+                        bir::TerminatorExpr::Call {
+                            function,
+                            arguments,
+                            labels: _,
+                        } => {
+                            self.count_uses(*function, &mut uses);
+                            for argument in arguments {
+                                self.count_uses(*argument, &mut uses);
+                            }
+                        }
+                    }
+                }
+                bir::TerminatorData::Goto(_)
+                | bir::TerminatorData::StartAtomic(_)
+                | bir::TerminatorData::EndAtomic(_)
+                | bir::TerminatorData::Error
+                | bir::TerminatorData::Panic => {}
+            }
+        }
+
+        (definitions, uses)
+    }
+
+    fn count_uses(&self, place: bir::Place, uses: &mut Counts) {
+        match place.data(&self.tables) {
+            bir::PlaceData::LocalVariable(lv) => *uses.entry(*lv).or_default() += 1,
+            bir::PlaceData::Dot(owner, _) => self.count_uses(*owner, uses),
+            bir::PlaceData::Function(_)
+            | bir::PlaceData::Class(_)
+            | bir::PlaceData::Intrinsic(_) => {}
+        }
+    }
+
+    /// Copies the reachable blocks (and everything they reference) into
+    /// fresh tables. Local variables keep their indices.
+    fn compact(&self) -> (bir::Tables, bir::Origins, bir::BasicBlock) {
+        let mut copier = Copier {
+            from: self,
+            tables: Default::default(),
+            origins: Default::default(),
+            blocks: Default::default(),
+        };
+
+        for lv in bir::LocalVariable::max_key(&self.tables).iter() {
+            let data = lv.data(&self.tables).clone();
+            let copied = add(
+                &mut copier.tables,
+                &mut copier.origins,
+                data,
+                self.origins[lv],
+            );
+            assert_eq!(copied, lv);
+        }
+
+        // Blocks are added in order, so we know in advance what each will be numbered.
+        let reachable = self.reachable_blocks();
+        for (index, &block) in reachable.iter().enumerate() {
+            copier.blocks.insert(block, bir::BasicBlock::from(index));
+        }
+        for &block in &reachable {
+            let block_data = block.data(&self.tables);
+            let statements = block_data
+                .statements
+                .iter()
+                .map(|&statement| copier.copy_statement(statement))
+                .collect();
+            let terminator = copier.copy_terminator(block_data.terminator);
+            let copied = add(
+                &mut copier.tables,
+                &mut copier.origins,
+                bir::BasicBlockData {
+                    statements,
+                    terminator,
+                },
+                self.origins[block],
+            );
+            assert_eq!(copied, copier.blocks[&block]);
+        }
+
+        let start_basic_block = copier.blocks[&self.start_basic_block];
+        (copier.tables, copier.origins, start_basic_block)
+    }
+}
+
+struct Copier<'me> {
+    from: &'me Optimizer,
+    tables: bir::Tables,
+    origins: bir::Origins,
+    blocks: Map<bir::BasicBlock, bir::BasicBlock>,
+}
+
+impl Copier<'_> {
+    fn copy_statement(&mut self, statement: bir::Statement) -> bir::Statement {
+        let data = match statement.data(&self.from.tables) {
+            bir::StatementData::Assign(target, expr) => {
+                bir::StatementData::Assign(self.copy_place(*target), self.copy_expr(*expr))
+            }
+            bir::StatementData::BreakpointStart(filename, index) => {
+                bir::StatementData::BreakpointStart(*filename, *index)
+            }
+            bir::StatementData::BreakpointEnd(filename, index, expr, place) => {
+                bir::StatementData::BreakpointEnd(
+                    *filename,
+                    *index,
+                    *expr,
+                    place.map(|place| self.copy_place(place)),
+                )
+            }
+        };
+        add(
+            &mut self.tables,
+            &mut self.origins,
+            data,
+            self.from.origins[statement],
+        )
+    }
+
+    fn copy_terminator(&mut self, terminator: bir::Terminator) -> bir::Terminator {
+        let data = map_successors(terminator.data(&self.from.tables), |block| {
+            self.blocks[&block]
+        });
+        let data = match data {
+            bir::TerminatorData::If(place, if_true, if_false) => {
+                bir::TerminatorData::If(self.copy_place(place), if_true, if_false)
+            }
+            bir::TerminatorData::Return(place) => {
+                bir::TerminatorData::Return(self.copy_place(place))
+            }
+            bir::TerminatorData::Assign(target, terminator_expr, next) => {
+                let target = self.copy_place(target);
+                let terminator_expr = match terminator_expr {
+                    bir::TerminatorExpr::Await(place) => {
+                        bir::TerminatorExpr::Await(self.copy_place(place))
+                    }
+                    bir::TerminatorExpr::Call {
+                        function,
+                        arguments,
+                        labels,
+                    } => bir::TerminatorExpr::Call {
+                        function: self.copy_place(function),
+                        arguments: arguments
+                            .into_iter()
+                            .map(|argument| self.copy_place(argument))
+                            .collect(),
+                        labels,
+                    },
+                };
+                bir::TerminatorData::Assign(target, terminator_expr, next)
+            }
+            data @ (bir::TerminatorData::Goto(_)
+            | bir::TerminatorData::StartAtomic(_)
+            | bir::TerminatorData::EndAtomic(_)
+            | bir::TerminatorData::Error
+            | bir::TerminatorData::Panic) => data,
+        };
+        add(
+            &mut self.tables,
+            &mut self.origins,
+            data,
+            self.from.origins[terminator],
+        )
+    }
+
+    fn copy_expr(&mut self, expr: bir::Expr) -> bir::Expr {
+        let data = match expr.data(&self.from.tables).clone() {
+            bir::ExprData::GiveShare(place) => bir::ExprData::GiveShare(self.copy_place(place)),
+            bir::ExprData::Lease(place) => bir::ExprData::Lease(self.copy_place(place)),
+            bir::ExprData::Give(place) => bir::ExprData::Give(self.copy_place(place)),
+            bir::ExprData::Tuple(places) => bir::ExprData::Tuple(
+                places
+                    .into_iter()
+                    .map(|place| self.copy_place(place))
+                    .collect(),
+            ),
+            bir::ExprData::Op(lhs, op, rhs) => {
+                bir::ExprData::Op(self.copy_place(lhs), op, self.copy_place(rhs))
+            }
+            data @ (bir::ExprData::BooleanLiteral(_)
+            | bir::ExprData::IntegerLiteral(_)
+            | bir::ExprData::StringLiteral(_)
+            | bir::ExprData::Unit
+            | bir::ExprData::Error) => data,
+        };
+        add(
+            &mut self.tables,
+            &mut self.origins,
+            data,
+            self.from.origins[expr],
+        )
+    }
+
+    fn copy_place(&mut self, place: bir::Place) -> bir::Place {
+        let data = match place.data(&self.from.tables).clone() {
+            bir::PlaceData::Dot(owner, field) => bir::PlaceData::Dot(self.copy_place(owner), field),
+            data @ (bir::PlaceData::LocalVariable(_)
+            | bir::PlaceData::Function(_)
+            | bir::PlaceData::Class(_)
+            | bir::PlaceData::Intrinsic(_)) => data,
+        };
+        add(
+            &mut self.tables,
+            &mut self.origins,
+            data,
+            self.from.origins[place],
+        )
+    }
+}
+
+/// Returns a copy of `data` with each successor block replaced by `f(block)`.
+fn map_successors(
+    data: &bir::TerminatorData,
+    mut f: impl FnMut(bir::BasicBlock) -> bir::BasicBlock,
+) -> bir::TerminatorData {
+    match data.clone() {
+        bir::TerminatorData::Goto(next) => bir::TerminatorData::Goto(f(next)),
+        bir::TerminatorData::If(place, if_true, if_false) => {
+            bir::TerminatorData::If(place, f(if_true), f(if_false))
+        }
+        bir::TerminatorData::StartAtomic(next) => bir::TerminatorData::StartAtomic(f(next)),
+        bir::TerminatorData::EndAtomic(next) => bir::TerminatorData::EndAtomic(f(next)),
+        bir::TerminatorData::Assign(place, terminator_expr, next) => {
+            bir::TerminatorData::Assign(place, terminator_expr, f(next))
+        }
+        data @ (bir::TerminatorData::Return(_)
+        | bir::TerminatorData::Error
+        | bir::TerminatorData::Panic) => data,
+    }
+}
+
+/// Returns the places that `expr` reads from.
+fn expr_places(expr: &bir::ExprData) -> Vec<bir::Place> {
+    match expr {
+        bir::ExprData::GiveShare(place)
+        | bir::ExprData::Lease(place)
+        | bir::ExprData::Give(place) => vec![*place],
+        bir::ExprData::Tuple(places) => places.clone(),
+        bir::ExprData::Op(lhs, _, rhs) => vec![*lhs, *rhs],
+        bir::ExprData::BooleanLiteral(_)
+        | bir::ExprData::IntegerLiteral(_)
+        | bir::ExprData::StringLiteral(_)
+        | bir::ExprData::Unit
+        | bir::ExprData::Error => vec![],
+    }
+}
+
+fn add<V, O>(tables: &mut bir::Tables, origins: &mut bir::Origins, data: V, origin: O) -> V::Key
+where
+    V: dada_id::InternValue<Table = bir::Tables>,
+    V::Key: PushOriginIn<bir::Origins, Origin = O>,
+{
+    let key = tables.add(data);
+    origins.push(key, origin);
+    key
+}
@@ -3,6 +3,10 @@ use dada_validate::prelude::*;
 
 pub trait BrewExt {
     fn brew(self, db: &dyn crate::Db) -> bir::Bir;
+
+    /// The bir that the interpreter executes: the result of `brew`,
+    /// optimized if optimizations are enabled for this function's file.
+    fn brew_for_execution(self, db: &dyn crate::Db) -> bir::Bir;
 }
 
 impl BrewExt for Function {
@@ -10,10 +14,21 @@ impl BrewExt for Function {
         let tree = self.validated_tree(db);
         crate::brew::brew(db, tree)
     }
+
+    fn brew_for_execution(self, db: &dyn crate::Db) -> bir::Bir {
+        let bir = self.brew(db);
+        if crate::optimize::optimizations_enabled(db, self.filename(db)) {
+            crate::optimize::optimize(db, bir)
+        } else {
+            bir
+        }
+    }
 }
 
 pub trait MaybeBrewExt {
     fn maybe_brew(self, db: &dyn crate::Db) -> Option<bir::Bir>;
+
+    fn maybe_brew_optimized(self, db: &dyn crate::Db) -> Option<bir::Bir>;
 }
 
 impl MaybeBrewExt for Item {
@@ -21,4 +36,9 @@ impl MaybeBrewExt for Item {
         self.validated_tree(db)
             .map(|tree| crate::brew::brew(db, tree))
     }
+
+    fn maybe_brew_optimized(self, db: &dyn crate::Db) -> Option<bir::Bir> {
+        self.maybe_brew(db)
+            .map(|bir| crate::optimize::optimize(db, bir))
+    }
 }
@@ -0,0 +1,527 @@
+//! Checks that bir is internally consistent. Violations are bugs in
+//! the compiler (in brewing or in an optimization), not in the user's
+//! program, so they are reported as internal compiler errors.
+
+use dada_collections::{Map, Set};
+use dada_id::prelude::*;
+use dada_ir::{
+    code::{
+        bir::{self, BirData},
+        syntax, validated,
+    },
+    filename::Filename,
+    span::FileSpan,
+};
+use dada_parse::prelude::*;
+
+/// Salsa input: whether the bir for the functions in a given file
+/// is verified when the file is checked.
+///
+/// Defaults to true in debug builds and false otherwise.
+#[salsa::memoized(in crate::Jar)]
+pub fn verification_enabled(_db: &dyn crate::Db, _filename: Filename) -> bool {
+    cfg!(debug_assertions)
+}
+
+/// Verifies `bir`, reporting an internal compiler error for each problem found:
+///
+/// * every basic block referenced by a terminator exists;
+/// * every place refers to an allocated local variable;
+/// * the parameters occupy the first `num_parameters` local variables;
+/// * `StartAtomic` and `EndAtomic` are balanced on all paths;
+/// * every `BreakpointEnd` is preceded by its `BreakpointStart` on all paths,
+///   and no breakpoint starts again before it has ended.
+///
+/// A breakpoint that starts but never ends is fine: control may leave the
+/// breakpoint expression early (e.g., via `break`).
+///
+/// The errors are accumulated in [`dada_ir::diagnostic::Diagnostics`].
+#[salsa::memoized(in crate::Jar)]
+pub fn verify(db: &dyn crate::Db, bir: bir::Bir) {
+    let verifier = Verifier {
+        db,
+        bir,
+        bir_data: bir.data(db),
+        origins: bir.origins(db),
+    };
+    verifier.verify();
+}
+
+/// What we know on entry to a basic block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct State {
+    /// Number of atomic sections that have been started but not ended.
+    atomic_depth: usize,
+
+    /// Breakpoints that have started but not ended on every path to this point.
+    open_breakpoints: Set<(Filename, usize)>,
+}
+
+struct Verifier<'me> {
+    db: &'me dyn crate::Db,
+    bir: bir::Bir,
+    bir_data: &'me BirData,
+    origins: &'me bir::Origins,
+}
+
+impl Verifier<'_> {
+    fn tables(&self) -> &bir::Tables {
+        self.bir_data.tables()
+    }
+
+    fn verify(&self) {
+        let well_formed = self.verify_references();
+        self.verify_parameters();
+        if well_formed {
+            // The flow checks walk the control-flow graph, which is only
+            // safe if all the blocks it refers to exist.
+            self.verify_flow();
+        }
+    }
+
+    /// Checks that the blocks and local variables referenced from each
+    /// block exist. Returns false if some block does not.
+    fn verify_references(&self) -> bool {
+        let mut well_formed = true;
+        let max_basic_block = self.bir_data.max_basic_block();
+
+        if self.bir_data.start_basic_block >= max_basic_block {
+            self.report(
+                self.bir
+                    .origin(self.db)
+                    .syntax_tree(self.db)
+                    .data(self.db)
+                    .root_expr,
+                format!(
+                    "start block {:?} does not exist",
+                    self.bir_data.start_basic_block
+                ),
+            );
+            well_formed = false;
+        }
+
+        for block in self.bir_data.all_basic_blocks() {
+            let block_data = block.data(self.tables());
+            for &statement in &block_data.statements {
+                let origin = self.origins[statement];
+                match statement.data(self.tables()) {
+                    bir::StatementData::Assign(place, expr) => {
+                        self.verify_place(*place, origin);
+                        for place in expr_places(expr.data(self.tables())) {
+                            self.verify_place(place, origin);
+                        }
+                    }
+                    bir::StatementData::BreakpointStart(..) => {}
+                    bir::StatementData::BreakpointEnd(_, _, _, place) => {
+                        if let Some(place) = place {
+                            self.verify_place(*place, origin);
+                        }
+                    }
+                }
+            }
+
+            let terminator = block_data.terminator;
+            let origin = self.origins[terminator];
+            let terminator_data = terminator.data(self.tables());
+            for place in terminator_places(terminator_data) {
+                self.verify_place(place, origin);
+            }
+            for successor in terminator_data.successors() {
+                if successor >= max_basic_block {
+                    self.report(
+                        origin,
+                        format!("{block:?} jumps to {successor:?}, which does not exist"),
+                    );
+                    well_formed = false;
+                }
+            }
+        }
+
+        well_formed
+    }
+
+    fn verify_place(&self, place: bir::Place, origin: syntax::Expr) {
+        match place.data(self.tables()) {
+            bir::PlaceData::LocalVariable(lv) => {
+                if *lv >= self.bir_data.max_local_variable() {
+                    self.report(origin, format!("local variable {lv:?} was never allocated"));
+                }
+            }
+            bir::PlaceData::Dot(owner, _) => self.verify_place(*owner, origin),
+            bir::PlaceData::Function(_)
+            | bir::PlaceData::Class(_)
+            | bir::PlaceData::Intrinsic(_) => {}
+        }
+    }
+
+    /// Checks that the parameters are the first `num_parameters` local variables.
+    fn verify_parameters(&self) {
+        let num_parameters = self.bir_data.num_parameters();
+        let root_expr = self
+            .bir
+            .origin(self.db)
+            .syntax_tree(self.db)
+            .data(self.db)
+            .root_expr;
+        let num_local_variables = usize::from(self.bir_data.max_local_variable());
+        if num_parameters > num_local_variables {
+            self.report(
+                root_expr,
+                format!(
+                    "{num_parameters} parameters, but only {num_local_variables} local variables"
+                ),
+            );
+            return;
+        }
+
+        for lv in self.bir_data.max_local_variable().iter() {
+            let is_parameter = matches!(
+                self.origins[lv],
+                validated::LocalVariableOrigin::Parameter(_)
+            );
+            let in_parameter_slot = usize::from(lv) < num_parameters;
+            if is_parameter != in_parameter_slot {
+                let slots = if in_parameter_slot {
+                    "a parameter slot"
+                } else {
+                    "a non-parameter slot"
+                };
+                let kind = if is_parameter {
+                    "a parameter"
+                } else {
+                    "not a parameter"
+                };
+                self.report(
+                    root_expr,
+                    format!("local variable {lv:?} is in {slots} but is {kind}"),
+                );
+            }
+        }
+    }
+
+    /// Checks the atomic sections and breakpoints along every path
+    /// through the reachable blocks.
+    fn verify_flow(&self) {
+        // Compute the state on entry to each block by iterating to a fixed point...
+        let start_block = self.bir_data.start_basic_block;
+        let mut entry_states: Map<bir::BasicBlock, State> = Map::default();
+        entry_states.insert(
+            start_block,
+            State {
+                atomic_depth: 0,
+                open_breakpoints: Set::default(),
+            },
+        );
+        let mut inconsistent_blocks = Set::default();
+        let mut worklist = vec![start_block];
+        while let Some(block) = worklist.pop() {
+            let mut state = entry_states[&block].clone();
+            self.verify_block(block, &mut state, false);
+
+            let terminator = block.data(self.tables()).terminator;
+            for successor in terminator.data(self.tables()).successors() {
+                let changed = match entry_states.get_mut(&successor) {
+                    Some(successor_state) => {
+                        if successor_state.atomic_depth != state.atomic_depth {
+                            inconsistent_blocks.insert(successor);
+                        }
+                        let old_len = successor_state.open_breakpoints.len();
+                        successor_state
+                            .open_breakpoints
+                            .retain(|breakpoint| state.open_breakpoints.contains(breakpoint));
+                        successor_state.open_breakpoints.len() != old_len
+                    }
+                    None => {
+                        entry_states.insert(successor, state.clone());
+                        true
+                    }
+                };
+                if changed {
+                    worklist.push(successor);
+                }
+            }
+        }
+
+        // ...and then walk each block once more, this time reporting errors.
+        let mut blocks: Vec<_> = entry_states.into_iter().collect();
+        blocks.sort_by_key(|(block, _)| *block);
+        for (block, mut state) in blocks {
+            if inconsistent_blocks.contains(&block) {
+                self.report(
+                    self.origins[block],
+                    format!("{block:?} is reached with different numbers of atomic sections open"),
+                );
+            }
+            self.verify_block(block, &mut state, true);
+        }
+    }
+
+    fn verify_block(&self, block: bir::BasicBlock, state: &mut State, report: bool) {
+        let block_data = block.data(self.tables());
+        for &statement in &block_data.statements {
+            match statement.data(self.tables()) {
+                bir::StatementData::Assign(..) => {}
+                bir::StatementData::BreakpointStart(filename, index) => {
+                    if !state.open_breakpoints.insert((*filename, *index)) && report {
+                        self.report(
+                            self.origins[statement],
+                            format!("breakpoint {index} started again before it ended"),
+                        );
+                    }
+                }
+                bir::StatementData::BreakpointEnd(filename, index, _, _) => {
+                    if !state.open_breakpoints.remove(&(*filename, *index)) && report {
+                        self.report(
+                            self.origins[statement],
+                            format!("end of breakpoint {index} without a matching start"),
+                        );
+                    }
+                }
+            }
+        }
+
+        let terminator = block_data.terminator;
+        let origin = self.origins[terminator];
+        match terminator.data(self.tables()) {
+            bir::TerminatorData::StartAtomic(_) => {
+                state.atomic_depth += 1;
+            }
+            bir::TerminatorData::EndAtomic(_) => {
+                if state.atomic_depth == 0 {
+                    if report {
+                        self.report(origin, "end of atomic section that was never started");
+                    }
+                } else {
+                    state.atomic_depth -= 1;
+                }
+            }
+            bir::TerminatorData::Return(_) => {
+                if report && state.atomic_depth > 0 {
+                    self.report(origin, "return from within an atomic section");
+                }
+            }
+            bir::TerminatorData::Goto(_)
+            | bir::TerminatorData::If(..)
+            | bir::TerminatorData::Assign(..)
+            | bir::TerminatorData::Error
+            | bir::TerminatorData::Panic => {}
+        }
+    }
+
+    fn report(&self, origin: syntax::Expr, message: impl std::fmt::Display) {
+        dada_ir::error!(self.span(origin), "internal compiler error: {}", message).emit(self.db);
+    }
+
+    fn span(&self, expr: syntax::Expr) -> FileSpan {
+        let code = self.bir.origin(self.db);
+        code.syntax_tree(self.db).spans(self.db)[expr].in_file(code.filename(self.db))
+    }
+}
+
+fn expr_places(expr: &bir::ExprData) -> Vec<bir::Place> {
+    match expr {
+        bir::ExprData::GiveShare(place)
+        | bir::ExprData::Lease(place)
+        | bir::ExprData::Give(place) => {
+            vec![*place]
+        }
+        bir::ExprData::Tuple(places) => places.clone(),
+        bir::ExprData::Op(lhs, _, rhs) => vec![*lhs, *rhs],
+        bir::ExprData::BooleanLiteral(_)
+        | bir::ExprData::IntegerLiteral(_)
+        | bir::ExprData::StringLiteral(_)
+        | bir::ExprData::Unit
+        | bir::ExprData::Error => vec![],
+    }
+}
+
+fn terminator_places(terminator: &bir::TerminatorData) -> Vec<bir::Place> {
+    match terminator {
+        bir::TerminatorData::If(place, _, _) | bir::TerminatorData::Return(place) => vec![*place],
+        bir::TerminatorData::Assign(target, bir::TerminatorExpr::Await(thunk), _) => {
+            vec![*target, *thunk]
+        }
+        bir::TerminatorData::Assign(
+            target,
+            bir::TerminatorExpr::Call {
+                function,
+                arguments,
+                labels: _,
+            },
+            _,
+        ) => std::iter::once(*target)
+            .chain(Some(*function))
+            .chain(arguments.iter().copied())
+            .collect(),
+        bir::TerminatorData::Goto(_)
+        | bir::TerminatorData::StartAtomic(_)
+        | bir::TerminatorData::EndAtomic(_)
+        | bir::TerminatorData::Error
+        | bir::TerminatorData::Panic => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dada_id::prelude::*;
+    use dada_ir::{
+        code::{
+            bir::{self, BirData},
+            syntax,
+        },
+        diagnostic::{Diagnostic, Diagnostics},
+        filename::Filename,
+        item::Item,
+        storage_mode::StorageMode,
+    };
+    use dada_parse::prelude::*;
+
+    #[salsa::db(
+        dada_breakpoint::Jar,
+        crate::Jar,
+        dada_ir::Jar,
+        dada_lex::Jar,
+        dada_parse::Jar,
+        dada_validate::Jar
+    )]
+    #[derive(Default)]
+    struct Db {
+        storage: salsa::Storage<Self>,
+    }
+
+    impl salsa::Database for Db {
+        fn salsa_runtime(&self) -> &salsa::Runtime {
+            self.storage.runtime()
+        }
+    }
+
+    /// Builds bir for the function `f` in a one-line file, attributing
+    /// everything to its root expression, and returns the diagnostics
+    /// that verifying it reports.
+    fn verify_bir(
+        build: impl FnOnce(&mut bir::Tables, &mut bir::Origins, syntax::Expr) -> BirData,
+    ) -> Vec<Diagnostic> {
+        let mut db = Db::default();
+        let filename = Filename::from(&db, "verify.dada");
+        dada_ir::manifest::source_text::set(&mut db, filename, "fn f() {}".to_string());
+        let function = match filename.items(&db).as_slice() {
+            [Item::Function(function)] => *function,
+            _ => unreachable!(),
+        };
+        let root_expr = function.syntax_tree(&db).data(&db).root_expr;
+
+        let mut tables = bir::Tables::default();
+        let mut origins = bir::Origins::default();
+        let data = build(&mut tables, &mut origins, root_expr);
+        let bir = bir::Bir::new(&db, function.code(&db), data, origins);
+        super::verify::accumulated::<Diagnostics>(&db, bir)
+    }
+
+    /// Adds a block that contains `statements` and ends in `terminator`.
+    fn add_block(
+        tables: &mut bir::Tables,
+        origins: &mut bir::Origins,
+        origin: syntax::Expr,
+        statements: Vec<bir::StatementData>,
+        terminator: bir::TerminatorData,
+    ) -> bir::BasicBlock {
+        let statements = statements
+            .into_iter()
+            .map(|data| {
+                let statement = tables.add(data);
+                origins.push(statement, origin);
+                statement
+            })
+            .collect();
+        let terminator = tables.add(terminator);
+        origins.push(terminator, origin);
+        let block = tables.add(bir::BasicBlockData {
+            statements,
+            terminator,
+        });
+        origins.push(block, origin);
+        block
+    }
+
+    /// Adds a local variable and a place that refers to it.
+    fn add_local(
+        tables: &mut bir::Tables,
+        origins: &mut bir::Origins,
+        origin: syntax::Expr,
+    ) -> bir::Place {
+        let local_variable = tables.add(bir::LocalVariableData {
+            name: None,
+            storage_mode: StorageMode::Shared,
+        });
+        origins.push(
+            local_variable,
+            dada_ir::code::validated::LocalVariableOrigin::Temporary(origin),
+        );
+        let place = tables.add(bir::PlaceData::LocalVariable(local_variable));
+        origins.push(place, origin);
+        place
+    }
+
+    fn messages(diagnostics: &[Diagnostic]) -> Vec<&str> {
+        diagnostics.iter().map(|d| &d.message[..]).collect()
+    }
+
+    #[test]
+    fn well_formed() {
+        let diagnostics = verify_bir(|tables, origins, origin| {
+            let place = add_local(tables, origins, origin);
+            let unit = tables.add(bir::ExprData::Unit);
+            origins.push(unit, origin);
+            let block = add_block(
+                tables,
+                origins,
+                origin,
+                vec![bir::StatementData::Assign(place, unit)],
+                bir::TerminatorData::Return(place),
+            );
+            BirData::new(std::mem::take(tables), 0, block)
+        });
+        assert_eq!(messages(&diagnostics), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn jump_to_missing_block() {
+        let diagnostics = verify_bir(|tables, origins, origin| {
+            let missing = bir::BasicBlock::from(1_usize);
+            let block = add_block(
+                tables,
+                origins,
+                origin,
+                vec![],
+                bir::TerminatorData::Goto(missing),
+            );
+            BirData::new(std::mem::take(tables), 0, block)
+        });
+        assert_eq!(
+            messages(&diagnostics),
+            vec!["internal compiler error: BasicBlock(0) jumps to BasicBlock(1), which does not exist"],
+        );
+    }
+
+    #[test]
+    fn local_variable_never_allocated() {
+        let diagnostics = verify_bir(|tables, origins, origin| {
+            let unallocated = tables.add(bir::PlaceData::LocalVariable(bir::LocalVariable::from(
+                0_usize,
+            )));
+            origins.push(unallocated, origin);
+            let block = add_block(
+                tables,
+                origins,
+                origin,
+                vec![],
+                bir::TerminatorData::Return(unallocated),
+            );
+            BirData::new(std::mem::take(tables), 0, block)
+        });
+        assert_eq!(
+            messages(&diagnostics),
+            vec!["internal compiler error: local variable LocalVariable(0) was never allocated"],
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use dada_brew::prelude::*;
 use dada_ir::{filename::Filename, item::Item};
 use dada_parse::prelude::*;
 use dada_validate::prelude::*;
@@ -14,10 +15,21 @@ pub fn check_filename(db: &dyn crate::Db, filename: Filename) {
                 function.parameters(db);
                 function.syntax_tree(db);
                 function.validated_tree(db);
+                crate::permission_check::check_permissions(db, function);
+
+                if dada_brew::verify::verification_enabled(db, filename) {
+                    // Verify the optimized bir too, even if it won't be executed,
+                    // so that bugs in the optimizations show up.
+                    let bir = function.brew(db);
+                    dada_brew::verify::verify(db, bir);
+                    dada_brew::verify::verify(db, dada_brew::optimize::optimize(db, bir));
+                }
             }
             Item::Class(class) => {
                 class.fields(db);
             }
         }
     }
+
+    crate::lint::lint_filename(db, filename);
 }
@@ -2,9 +2,12 @@
 #![feature(trait_upcasting)]
 
 mod check;
+mod lint;
+mod permission_check;
+pub mod warnings;
 
 #[salsa::jar(Db)]
-pub struct Jar(check::check_filename);
+pub struct Jar(check::check_filename, warnings::warn_unused);
 
 pub trait Db:
     salsa::DbWithJar<Jar>
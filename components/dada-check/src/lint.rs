@@ -0,0 +1,238 @@
+//! Lints that run after validation. These report code that is legal
+//! but probably not what the user intended (e.g., unused variables
+//! or code that can never execute).
+
+use dada_brew::prelude::*;
+use dada_collections::Set;
+use dada_id::prelude::*;
+use dada_ir::{
+    code::{bir, syntax, validated},
+    filename::Filename,
+    function::Function,
+    item::Item,
+    word::Word,
+};
+use dada_parse::prelude::*;
+use dada_validate::prelude::*;
+
+/// Reports unused local variables and parameters in each function,
+/// along with functions and classes that are never referenced
+/// (unless [`crate::warnings::warn_unused`] is off), and unreachable code.
+pub(crate) fn lint_filename(db: &dyn crate::Db, filename: Filename) {
+    let items = filename.items(db);
+    let warn_unused = crate::warnings::warn_unused(db, filename);
+
+    let mut used_items: Set<Item> = Set::default();
+    for &item in items {
+        if let Item::Function(function) = item {
+            let tree = function.validated_tree(db);
+            if warn_unused {
+                lint_unused_local_variables(db, function, tree);
+            }
+            lint_unreachable_code(db, function);
+
+            // References from within an item to itself (e.g., recursion)
+            // don't count as uses.
+            used_items.extend(
+                referenced_items(db, tree)
+                    .into_iter()
+                    .filter(|&referenced| referenced != item),
+            );
+        }
+    }
+
+    if !warn_unused {
+        return;
+    }
+
+    for &item in items {
+        if used_items.contains(&item) || is_opted_out(db, item.name(db)) {
+            continue;
+        }
+
+        if let Item::Function(function) = item {
+            if function.name(db).as_str(db) == "main" {
+                continue;
+            }
+        }
+
+        dada_ir::warning!(
+            item.name_span(db),
+            "the {} `{}` is never used",
+            item.kind_str(),
+            item.name(db).as_str(db)
+        )
+        .emit(db);
+    }
+}
+
+fn lint_unused_local_variables(db: &dyn crate::Db, function: Function, tree: validated::Tree) {
+    let tables = &tree.data(db).tables;
+    let origins = tree.origins(db);
+    let syntax_tree = function.syntax_tree(db);
+    let filename = function.code(db).filename(db);
+
+    // Assigning to a variable (e.g., `x := 22` or `var x = 22`) does
+    // not count as a use, but assigning to one of its fields does.
+    let mut used_local_variables: Set<validated::LocalVariable> = Set::default();
+    for expr in validated::Expr::max_key(tables).iter() {
+        match expr.data(tables) {
+            validated::ExprData::Give(place)
+            | validated::ExprData::Share(place)
+            | validated::ExprData::Lease(place) => {
+                mark_used(tables, *place, &mut used_local_variables);
+            }
+            validated::ExprData::Assign(place, _) => {
+                if let validated::PlaceData::Dot(owner, _) = place.data(tables) {
+                    mark_used(tables, *owner, &mut used_local_variables);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for lv in validated::LocalVariable::max_key(tables).iter() {
+        if used_local_variables.contains(&lv) {
+            continue;
+        }
+
+        // Temporaries introduced by the compiler have no name.
+        let name = match lv.data(tables).name {
+            Some(name) => name,
+            None => continue,
+        };
+
+        if is_opted_out(db, name) {
+            continue;
+        }
+
+        let (kind, decl) = match origins[lv] {
+            validated::LocalVariableOrigin::Temporary(_) => continue,
+            validated::LocalVariableOrigin::LocalVariable(decl) => ("variable", decl),
+            validated::LocalVariableOrigin::Parameter(decl) => ("parameter", decl),
+        };
+        let span = syntax_tree.spans(db)[decl].name_span.in_file(filename);
+
+        dada_ir::warning!(span, "unused {} `{}`", kind, name.as_str(db))
+            .child(
+                dada_ir::help!(
+                    span,
+                    "if this is intentional, prefix it with an underscore: `_{}`",
+                    name.as_str(db)
+                )
+                .skip_primary_label()
+                .finish(),
+            )
+            .emit(db);
+    }
+}
+
+/// Reports code that can never execute, such as code that follows an
+/// infinite `loop`. Dead code is still brewed into the BIR, it just lives
+/// in basic blocks that cannot be reached from the start block.
+fn lint_unreachable_code(db: &dyn crate::Db, function: Function) {
+    let bir = function.brew(db);
+    let bir_data = bir.data(db);
+    let tables = bir_data.tables();
+    let origins = bir.origins(db);
+    let reachable = bir_data.reachable_basic_blocks();
+
+    let unreachable: Vec<bir::BasicBlock> = bir_data
+        .all_basic_blocks()
+        .filter(|block| !reachable.contains(block))
+        .collect();
+
+    // Each chunk of dead code begins with an unreachable block that is not
+    // the target of any other unreachable block; report one warning per chunk.
+    let mut successors_of_unreachable: Set<bir::BasicBlock> = Set::default();
+    for &block in &unreachable {
+        let terminator = block.data(tables).terminator;
+        successors_of_unreachable.extend(
+            terminator
+                .data(tables)
+                .successors()
+                .into_iter()
+                .filter(|&successor| successor != block),
+        );
+    }
+
+    let spans = function.syntax_tree(db).spans(db);
+    let filename = function.code(db).filename(db);
+    for &entry_block in &unreachable {
+        if successors_of_unreachable.contains(&entry_block) {
+            continue;
+        }
+
+        // Find the outermost expression that executes in this chunk of
+        // dead code: that is, the one that starts first (and, amongst
+        // those, is the longest).
+        let mut visited: Set<bir::BasicBlock> = Set::default();
+        let mut stack = vec![entry_block];
+        let mut dead_exprs: Vec<syntax::Expr> = vec![];
+        while let Some(block) = stack.pop() {
+            if reachable.contains(&block) || !visited.insert(block) {
+                continue;
+            }
+
+            let block_data = block.data(tables);
+            for &statement in &block_data.statements {
+                if let bir::StatementData::Assign(..) = statement.data(tables) {
+                    dead_exprs.push(origins[statement]);
+                }
+            }
+
+            let terminator_data = block_data.terminator.data(tables);
+            if let bir::TerminatorData::Assign(..) = terminator_data {
+                dead_exprs.push(origins[block_data.terminator]);
+            }
+            stack.extend(terminator_data.successors());
+        }
+
+        if let Some(span) = dead_exprs
+            .into_iter()
+            .map(|expr| spans[expr])
+            .min_by_key(|span| (span.start, std::cmp::Reverse(span.len())))
+        {
+            dada_ir::warning!(span.in_file(filename), "unreachable code")
+                .primary_label("this code can never execute")
+                .emit(db);
+        }
+    }
+}
+
+/// Records the local variable that `place` is rooted in as used.
+fn mark_used(
+    tables: &validated::Tables,
+    place: validated::Place,
+    used_local_variables: &mut Set<validated::LocalVariable>,
+) {
+    match place.data(tables) {
+        validated::PlaceData::LocalVariable(lv) => {
+            used_local_variables.insert(*lv);
+        }
+        validated::PlaceData::Dot(owner, _) => mark_used(tables, *owner, used_local_variables),
+        validated::PlaceData::Function(_)
+        | validated::PlaceData::Intrinsic(_)
+        | validated::PlaceData::Class(_) => {}
+    }
+}
+
+/// Returns the set of functions and classes referenced from `tree`.
+fn referenced_items(db: &dyn crate::Db, tree: validated::Tree) -> Set<Item> {
+    let tables = &tree.data(db).tables;
+    validated::Place::max_key(tables)
+        .iter()
+        .filter_map(|place| match place.data(tables) {
+            validated::PlaceData::Function(f) => Some(Item::Function(*f)),
+            validated::PlaceData::Class(c) => Some(Item::Class(*c)),
+            validated::PlaceData::LocalVariable(_)
+            | validated::PlaceData::Intrinsic(_)
+            | validated::PlaceData::Dot(..) => None,
+        })
+        .collect()
+}
+
+/// Names beginning with `_` are exempt from the "unused" lints.
+fn is_opted_out(db: &dyn crate::Db, name: Word) -> bool {
+    name.as_str(db).starts_with('_')
+}
@@ -0,0 +1,377 @@
+//! A flow-sensitive, static approximation of the permission checks that
+//! the interpreter performs at runtime (see `dada-execute/src/permission`).
+//!
+//! We track, for each local variable in the BIR, what we know about the
+//! permission it holds. When that is enough to know that an operation will
+//! definitely fail at runtime (e.g., reading a variable after its value
+//! was given away), we report an error using the same wording as the
+//! runtime error. Anything we are not sure about is left to the interpreter.
+
+use dada_brew::prelude::*;
+use dada_collections::{IndexVec, Map};
+use dada_id::prelude::*;
+use dada_ir::{
+    code::{bir, syntax},
+    function::Function,
+    span::FileSpan,
+};
+use dada_parse::prelude::*;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum PermissionState {
+    /// We don't know anything about this permission.
+    Unknown,
+
+    /// A uniquely owned (`my`) permission.
+    My,
+
+    /// A leased permission.
+    Leased,
+
+    /// A jointly owned or shared (`our` or `shared`) permission,
+    /// granted by the given expression.
+    Shared(syntax::Expr),
+
+    /// A class; calling it creates a uniquely owned instance.
+    Class,
+
+    /// The permission was given away by the given expression.
+    Given(syntax::Expr),
+}
+
+impl PermissionState {
+    /// Combines the states from two paths in the control-flow graph.
+    /// The result only retains what is true on both paths.
+    fn join(self, other: PermissionState) -> PermissionState {
+        match (self, other) {
+            (PermissionState::Shared(a), PermissionState::Shared(b)) => {
+                PermissionState::Shared(a.min(b))
+            }
+            (PermissionState::Given(a), PermissionState::Given(b)) => {
+                PermissionState::Given(a.min(b))
+            }
+            (a, b) if a == b => a,
+            _ => PermissionState::Unknown,
+        }
+    }
+}
+
+type State = IndexVec<bir::LocalVariable, PermissionState>;
+
+pub(crate) fn check_permissions(db: &dyn crate::Db, function: Function) {
+    let bir = function.brew(db);
+    let checker = PermissionChecker {
+        db,
+        function,
+        bir_data: bir.data(db),
+        origins: bir.origins(db),
+    };
+    checker.check();
+}
+
+struct PermissionChecker<'me> {
+    db: &'me dyn crate::Db,
+    function: Function,
+    bir_data: &'me bir::BirData,
+    origins: &'me bir::Origins,
+}
+
+impl PermissionChecker<'_> {
+    fn tables(&self) -> &bir::Tables {
+        self.bir_data.tables()
+    }
+
+    fn check(&self) {
+        // Compute the state on entry to each block by iterating to a fixed point...
+        let start_block = self.bir_data.start_basic_block;
+        let initial_state: State = self
+            .bir_data
+            .max_local_variable()
+            .iter()
+            .map(|_| PermissionState::Unknown)
+            .collect();
+        let mut entry_states: Map<bir::BasicBlock, State> = Map::default();
+        entry_states.insert(start_block, initial_state);
+        let mut worklist = vec![start_block];
+        while let Some(block) = worklist.pop() {
+            let mut state = entry_states[&block].clone();
+            self.check_block(block, &mut state, false);
+
+            let terminator = block.data(self.tables()).terminator;
+            for successor in terminator.data(self.tables()).successors() {
+                let changed = match entry_states.get_mut(&successor) {
+                    Some(successor_state) => {
+                        let mut changed = false;
+                        for (old, new) in successor_state.iter_mut().zip(&state) {
+                            let joined = old.join(*new);
+                            changed |= joined != *old;
+                            *old = joined;
+                        }
+                        changed
+                    }
+                    None => {
+                        entry_states.insert(successor, state.clone());
+                        true
+                    }
+                };
+                if changed {
+                    worklist.push(successor);
+                }
+            }
+        }
+
+        // ...and then walk each block once more, this time reporting errors.
+        let mut blocks: Vec<_> = entry_states.into_iter().collect();
+        blocks.sort_by_key(|(block, _)| *block);
+        for (block, mut state) in blocks {
+            self.check_block(block, &mut state, true);
+        }
+    }
+
+    fn check_block(&self, block: bir::BasicBlock, state: &mut State, report: bool) {
+        let block_data = block.data(self.tables());
+        for &statement in &block_data.statements {
+            let origin = self.origins[statement];
+            match statement.data(self.tables()) {
+                bir::StatementData::Assign(target, expr) => {
+                    let value = self.evaluate(*expr, state, origin, report);
+                    self.assign(*target, value, state, origin, report);
+                }
+                bir::StatementData::BreakpointStart(..) | bir::StatementData::BreakpointEnd(..) => {
+                }
+            }
+        }
+
+        let terminator = block_data.terminator;
+        let origin = self.origins[terminator];
+        match terminator.data(self.tables()) {
+            bir::TerminatorData::If(condition, _, _) => {
+                self.read(*condition, state, origin, report);
+            }
+            bir::TerminatorData::Return(value) => {
+                self.give(*value, state, origin, report);
+            }
+            bir::TerminatorData::Assign(target, bir::TerminatorExpr::Await(thunk), _) => {
+                self.give(*thunk, state, origin, report);
+                self.assign(*target, PermissionState::Unknown, state, origin, report);
+            }
+            bir::TerminatorData::Assign(
+                target,
+                bir::TerminatorExpr::Call {
+                    function,
+                    arguments,
+                    labels: _,
+                },
+                _,
+            ) => {
+                let function = self.give(*function, state, origin, report);
+                for &argument in arguments {
+                    self.give(argument, state, origin, report);
+                }
+                let value = match function {
+                    PermissionState::Class => PermissionState::My,
+                    _ => PermissionState::Unknown,
+                };
+                self.assign(*target, value, state, origin, report);
+            }
+            bir::TerminatorData::Goto(_)
+            | bir::TerminatorData::StartAtomic(_)
+            | bir::TerminatorData::EndAtomic(_)
+            | bir::TerminatorData::Error
+            | bir::TerminatorData::Panic => {}
+        }
+    }
+
+    /// Returns the permission that results from evaluating `expr`.
+    fn evaluate(
+        &self,
+        expr: bir::Expr,
+        state: &mut State,
+        origin: syntax::Expr,
+        report: bool,
+    ) -> PermissionState {
+        match expr.data(self.tables()) {
+            bir::ExprData::BooleanLiteral(_) | bir::ExprData::IntegerLiteral(_) => {
+                PermissionState::Shared(origin)
+            }
+            bir::ExprData::StringLiteral(_) | bir::ExprData::Unit => PermissionState::My,
+            bir::ExprData::Tuple(places) => {
+                for &place in places {
+                    self.give(place, state, origin, report);
+                }
+                PermissionState::My
+            }
+            bir::ExprData::Op(lhs, _, rhs) => {
+                self.read(*lhs, state, origin, report);
+                self.read(*rhs, state, origin, report);
+                PermissionState::Unknown
+            }
+            bir::ExprData::Give(place) => self.give(*place, state, origin, report),
+            bir::ExprData::GiveShare(place) => self.give_share(*place, state, origin, report),
+            bir::ExprData::Lease(place) => self.lease(*place, state, origin, report),
+            bir::ExprData::Error => PermissionState::Unknown,
+        }
+    }
+
+    /// Equivalent of `Permission::give`.
+    fn give(
+        &self,
+        place: bir::Place,
+        state: &mut State,
+        origin: syntax::Expr,
+        report: bool,
+    ) -> PermissionState {
+        match place.data(self.tables()) {
+            bir::PlaceData::LocalVariable(lv) => {
+                self.check_not_given(*lv, state, origin, report);
+                match state[*lv] {
+                    PermissionState::My => {
+                        state[*lv] = PermissionState::Given(origin);
+                        PermissionState::My
+                    }
+                    permission => permission,
+                }
+            }
+            _ => self.access_other(place, state, origin, report),
+        }
+    }
+
+    /// Equivalent of `Permission::give_share`.
+    fn give_share(
+        &self,
+        place: bir::Place,
+        state: &mut State,
+        origin: syntax::Expr,
+        report: bool,
+    ) -> PermissionState {
+        match place.data(self.tables()) {
+            bir::PlaceData::LocalVariable(lv) => {
+                self.check_not_given(*lv, state, origin, report);
+                match state[*lv] {
+                    PermissionState::My => {
+                        state[*lv] = PermissionState::Given(origin);
+                        PermissionState::Shared(origin)
+                    }
+                    PermissionState::Leased => PermissionState::Shared(origin),
+                    permission => permission,
+                }
+            }
+            _ => self.access_other(place, state, origin, report),
+        }
+    }
+
+    /// Equivalent of `Permission::lease`.
+    fn lease(
+        &self,
+        place: bir::Place,
+        state: &mut State,
+        origin: syntax::Expr,
+        report: bool,
+    ) -> PermissionState {
+        match place.data(self.tables()) {
+            bir::PlaceData::LocalVariable(lv) => {
+                self.check_not_given(*lv, state, origin, report);
+                match state[*lv] {
+                    PermissionState::My | PermissionState::Leased => PermissionState::Leased,
+                    permission => permission,
+                }
+            }
+            _ => self.access_other(place, state, origin, report),
+        }
+    }
+
+    /// Reads the value in `place` without taking any permission from it.
+    fn read(&self, place: bir::Place, state: &mut State, origin: syntax::Expr, report: bool) {
+        match place.data(self.tables()) {
+            bir::PlaceData::LocalVariable(lv) => {
+                self.check_not_given(*lv, state, origin, report);
+            }
+            _ => {
+                self.access_other(place, state, origin, report);
+            }
+        }
+    }
+
+    /// Accesses a place that is not a local variable, returning the
+    /// permission of the value found there.
+    fn access_other(
+        &self,
+        place: bir::Place,
+        state: &mut State,
+        origin: syntax::Expr,
+        report: bool,
+    ) -> PermissionState {
+        match place.data(self.tables()) {
+            bir::PlaceData::LocalVariable(_) => {
+                unreachable!("local variables are handled by callers")
+            }
+            bir::PlaceData::Function(_) | bir::PlaceData::Intrinsic(_) => {
+                PermissionState::Shared(origin)
+            }
+            bir::PlaceData::Class(_) => PermissionState::Class,
+            bir::PlaceData::Dot(owner, _) => {
+                // We don't track the permissions of fields, but the owner must be readable.
+                self.read(*owner, state, origin, report);
+                PermissionState::Unknown
+            }
+        }
+    }
+
+    /// Stores a value with the given permission into `target`.
+    fn assign(
+        &self,
+        target: bir::Place,
+        value: PermissionState,
+        state: &mut State,
+        origin: syntax::Expr,
+        report: bool,
+    ) {
+        match target.data(self.tables()) {
+            bir::PlaceData::LocalVariable(lv) => state[*lv] = value,
+            bir::PlaceData::Dot(owner, _) => {
+                // Writing to a field requires write permission on the owner.
+                self.read(*owner, state, origin, report);
+                if let bir::PlaceData::LocalVariable(owner_lv) = owner.data(self.tables()) {
+                    if let PermissionState::Shared(granted) = state[*owner_lv] {
+                        if report {
+                            dada_ir::error!(
+                                self.span(origin),
+                                "shared permission does not permit writes"
+                            )
+                            .secondary_label(self.span(granted), "permission granted here")
+                            .emit(self.db);
+                        }
+                    }
+                }
+            }
+            bir::PlaceData::Function(_)
+            | bir::PlaceData::Class(_)
+            | bir::PlaceData::Intrinsic(_) => {}
+        }
+    }
+
+    /// Reports an error if the permission in `lv` was given away.
+    /// Afterwards, we treat the permission as unknown, so that each
+    /// mistake is only reported once.
+    fn check_not_given(
+        &self,
+        lv: bir::LocalVariable,
+        state: &mut State,
+        origin: syntax::Expr,
+        report: bool,
+    ) {
+        if let PermissionState::Given(given) = state[lv] {
+            if report {
+                dada_ir::error!(self.span(origin), "permission already given")
+                    .secondary_label(self.span(given), "permission given here")
+                    .emit(self.db);
+            }
+            state[lv] = PermissionState::Unknown;
+        }
+    }
+
+    fn span(&self, expr: syntax::Expr) -> FileSpan {
+        let filename = self.function.code(self.db).filename(self.db);
+        self.function.syntax_tree(self.db).spans(self.db)[expr].in_file(filename)
+    }
+}
@@ -0,0 +1,10 @@
+use dada_ir::filename::Filename;
+
+/// Salsa input: whether to warn about local variables, parameters, functions,
+/// and classes that are never used.
+///
+/// Defaults to true if not explicitly set.
+#[salsa::memoized(in crate::Jar)]
+pub fn warn_unused(_db: &dyn crate::Db, _filename: Filename) -> bool {
+    true // default: on
+}
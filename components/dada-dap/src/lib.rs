@@ -0,0 +1,194 @@
+//! A server for the [Debug Adapter Protocol](https://microsoft.github.io/debug-adapter-protocol/),
+//! so that editors can debug Dada programs.
+//!
+//! Besides the standard requests, the server answers a `heapGraph` request
+//! while the program is stopped, whose body has the heap graph in graphviz format.
+
+#![feature(trait_upcasting)]
+#![allow(incomplete_features)]
+
+use std::{collections::BTreeSet, io::BufRead, path::PathBuf, sync::Arc};
+
+use parking_lot::Mutex;
+use protocol::{Client, Request};
+use serde_json::json;
+use session::{BreakpointLines, Command, Session};
+
+mod protocol;
+mod session;
+
+pub struct DapServer {
+    input: Box<dyn BufRead + Send>,
+    client: Arc<Client>,
+
+    /// The arguments of the `launch` request, once received.
+    launch: Option<Launch>,
+
+    /// True once the client has sent `configurationDone`.
+    configured: bool,
+
+    breakpoint_lines: BreakpointLines,
+
+    /// The running program, once it has been launched and configured.
+    session: Option<Session>,
+}
+
+struct Launch {
+    program: PathBuf,
+    stop_on_entry: bool,
+}
+
+impl DapServer {
+    /// Creates a server that talks to the client over stdin and stdout.
+    pub fn stdio() -> Self {
+        Self {
+            input: Box::new(std::io::BufReader::new(std::io::stdin())),
+            client: Arc::new(Client::new(Box::new(std::io::stdout()))),
+            launch: None,
+            configured: false,
+            breakpoint_lines: Arc::new(Mutex::new(BTreeSet::new())),
+            session: None,
+        }
+    }
+
+    /// Handles requests until the client disconnects.
+    pub fn main_loop(&mut self) -> eyre::Result<()> {
+        while let Some(message) = protocol::read_message(&mut *self.input)? {
+            let request = match Request::from_message(&message) {
+                Some(request) => request,
+                None => continue,
+            };
+            tracing::debug!("request: {:?}", request);
+            if !self.handle(request)? {
+                break;
+            }
+        }
+
+        if let Some(session) = &self.session {
+            session.send(Command::Disconnect);
+        }
+        Ok(())
+    }
+
+    /// Handles a request; returns false if the client disconnected.
+    fn handle(&mut self, request: Request) -> eyre::Result<bool> {
+        match request.command.as_str() {
+            "initialize" => {
+                self.client.respond(
+                    &request,
+                    json!({
+                        "supportsConfigurationDoneRequest": true,
+                    }),
+                )?;
+                self.client.event("initialized", json!({}))?;
+            }
+            "launch" => {
+                let program = match request.arguments["program"].as_str() {
+                    Some(program) => PathBuf::from(program),
+                    None => {
+                        self.client
+                            .respond_error(&request, "`launch` requires a `program`")?;
+                        return Ok(true);
+                    }
+                };
+                let stop_on_entry = request.arguments["stopOnEntry"].as_bool().unwrap_or(false);
+                self.launch = Some(Launch {
+                    program,
+                    stop_on_entry,
+                });
+                self.client.respond(&request, json!({}))?;
+                self.start_if_ready();
+            }
+            "setBreakpoints" => self.set_breakpoints(&request)?,
+            "configurationDone" => {
+                self.configured = true;
+                self.client.respond(&request, json!({}))?;
+                self.start_if_ready();
+            }
+            "threads" => self.client.respond(
+                &request,
+                json!({ "threads": [{ "id": session::THREAD_ID, "name": "main" }] }),
+            )?,
+            "stackTrace" | "scopes" | "variables" | "heapGraph" => match &self.session {
+                Some(session) if session.is_stopped() => session.send(Command::Inspect(request)),
+                _ => self
+                    .client
+                    .respond_error(&request, "the program is not stopped")?,
+            },
+            "continue" => {
+                self.client
+                    .respond(&request, json!({ "allThreadsContinued": true }))?;
+                self.resume(Command::Continue);
+            }
+            "next" | "stepIn" => {
+                self.client.respond(&request, json!({}))?;
+                self.resume(Command::Step);
+            }
+            "disconnect" | "terminate" => {
+                self.client.respond(&request, json!({}))?;
+                return Ok(false);
+            }
+            command => self
+                .client
+                .respond_error(&request, &format!("unsupported request `{command}`"))?,
+        }
+        Ok(true)
+    }
+
+    /// Replaces the breakpoints. Execution can only stop at the start of a line
+    /// of code, so breakpoints on other lines are reported as unverified.
+    fn set_breakpoints(&mut self, request: &Request) -> eyre::Result<()> {
+        let path = match request.arguments["source"]["path"].as_str() {
+            Some(path) => PathBuf::from(path),
+            None => {
+                return self
+                    .client
+                    .respond_error(request, "`setBreakpoints` requires a source path")
+            }
+        };
+        let stopping_lines = match session::stopping_lines(&path) {
+            Ok(lines) => lines,
+            Err(e) => return self.client.respond_error(request, &e.to_string()),
+        };
+
+        let mut breakpoint_lines = BTreeSet::new();
+        let breakpoints = request.arguments["breakpoints"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|breakpoint| {
+                let line = breakpoint["line"].as_u64().unwrap_or(0) as u32;
+                let verified = stopping_lines.contains(&line);
+                if verified {
+                    breakpoint_lines.insert(line);
+                }
+                json!({ "verified": verified, "line": line })
+            })
+            .collect::<Vec<_>>();
+        *self.breakpoint_lines.lock() = breakpoint_lines;
+
+        self.client
+            .respond(request, json!({ "breakpoints": breakpoints }))
+    }
+
+    /// Starts the program once it has been both launched and configured.
+    fn start_if_ready(&mut self) {
+        if self.session.is_some() || !self.configured {
+            return;
+        }
+        if let Some(launch) = &self.launch {
+            self.session = Some(Session::start(
+                self.client.clone(),
+                launch.program.clone(),
+                self.breakpoint_lines.clone(),
+                launch.stop_on_entry,
+            ));
+        }
+    }
+
+    fn resume(&self, command: Command) {
+        if let Some(session) = &self.session {
+            session.send(command);
+        }
+    }
+}
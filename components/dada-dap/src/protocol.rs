@@ -0,0 +1,115 @@
+//! The base protocol of the Debug Adapter Protocol: JSON messages, each
+//! preceded by a `Content-Length` header (the same framing as LSP).
+
+use std::{
+    io::{BufRead, Write},
+    sync::atomic::{AtomicI64, Ordering},
+};
+
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+
+/// Reads the next message, or returns `None` at the end of the input.
+pub(crate) fn read_message(input: &mut dyn BufRead) -> eyre::Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            content_length = Some(value.trim().parse::<usize>()?);
+        }
+    }
+
+    let content_length = match content_length {
+        Some(content_length) => content_length,
+        None => eyre::bail!("message without a `Content-Length` header"),
+    };
+    let mut buffer = vec![0; content_length];
+    input.read_exact(&mut buffer)?;
+    Ok(Some(serde_json::from_slice(&buffer)?))
+}
+
+/// A request from the client.
+#[derive(Clone, Debug)]
+pub(crate) struct Request {
+    pub(crate) seq: i64,
+    pub(crate) command: String,
+    pub(crate) arguments: Value,
+}
+
+impl Request {
+    /// Returns the request in `message`, or `None` if it is some other kind of message.
+    pub(crate) fn from_message(message: &Value) -> Option<Request> {
+        if message["type"] != "request" {
+            return None;
+        }
+        Some(Request {
+            seq: message["seq"].as_i64()?,
+            command: message["command"].as_str()?.to_string(),
+            arguments: message["arguments"].clone(),
+        })
+    }
+}
+
+/// Sends messages to the client. It is shared by the thread that handles
+/// requests and the thread that executes the program.
+pub(crate) struct Client {
+    output: Mutex<Box<dyn Write + Send>>,
+
+    /// Sequence number of the last message sent.
+    seq: AtomicI64,
+}
+
+impl Client {
+    pub(crate) fn new(output: Box<dyn Write + Send>) -> Self {
+        Self {
+            output: Mutex::new(output),
+            seq: AtomicI64::new(0),
+        }
+    }
+
+    /// Responds to `request` successfully, with the given body.
+    pub(crate) fn respond(&self, request: &Request, body: Value) -> eyre::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request.seq,
+            "success": true,
+            "command": request.command,
+            "body": body,
+        }))
+    }
+
+    /// Responds to `request` with an error.
+    pub(crate) fn respond_error(&self, request: &Request, message: &str) -> eyre::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request.seq,
+            "success": false,
+            "command": request.command,
+            "message": message,
+        }))
+    }
+
+    pub(crate) fn event(&self, event: &str, body: Value) -> eyre::Result<()> {
+        self.send(json!({
+            "type": "event",
+            "event": event,
+            "body": body,
+        }))
+    }
+
+    fn send(&self, mut message: Value) -> eyre::Result<()> {
+        message["seq"] = json!(self.seq.fetch_add(1, Ordering::SeqCst) + 1);
+        let text = message.to_string();
+        let mut output = self.output.lock();
+        write!(output, "Content-Length: {}\r\n\r\n{}", text.len(), text)?;
+        output.flush()?;
+        Ok(())
+    }
+}
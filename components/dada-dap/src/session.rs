@@ -0,0 +1,335 @@
+//! Executes the program on its own thread, so that the server can keep
+//! handling requests while it runs. When execution stops (at a breakpoint
+//! or after a step), the executing thread answers the requests that inspect
+//! the program until it is told to resume.
+
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use crossbeam_channel::{Receiver, Sender};
+use dada_execute::heap_graph::HeapGraph;
+use dada_ir::{filename::Filename, span::FileSpan};
+use eyre::Context;
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+
+use crate::protocol::{Client, Request};
+
+/// The lines (1-based) where the user has set breakpoints.
+pub(crate) type BreakpointLines = Arc<Mutex<BTreeSet<u32>>>;
+
+/// The only thread that we report to the client; Dada tasks are not (yet) reported as threads.
+pub(crate) const THREAD_ID: i64 = 1;
+
+pub(crate) enum Command {
+    /// Answer a request that inspects the stopped program.
+    Inspect(Request),
+
+    /// Continue to the next breakpoint.
+    Continue,
+
+    /// Continue to the next line.
+    Step,
+
+    /// Stop executing the program.
+    Disconnect,
+}
+
+pub(crate) struct Session {
+    commands: Sender<Command>,
+
+    /// True while execution is stopped and waiting for commands.
+    stopped: Arc<AtomicBool>,
+}
+
+impl Session {
+    /// Starts executing `main` from `program` on a new thread.
+    /// If `stop_on_entry` is true, execution stops at the first line.
+    pub(crate) fn start(
+        client: Arc<Client>,
+        program: PathBuf,
+        breakpoint_lines: BreakpointLines,
+        stop_on_entry: bool,
+    ) -> Self {
+        let (commands, receiver) = crossbeam_channel::unbounded();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let kernel = DapKernel {
+            client: client.clone(),
+            commands: receiver,
+            stopped: stopped.clone(),
+            breakpoint_lines,
+            stepping: AtomicBool::new(stop_on_entry),
+        };
+        std::thread::spawn(move || {
+            let exit_code = match execute(&program, &kernel) {
+                Ok(()) => 0,
+                Err(e) if e.downcast_ref::<Disconnected>().is_some() => return,
+                Err(e) => {
+                    let _ = client.event(
+                        "output",
+                        json!({"category": "stderr", "output": format!("{e}\n")}),
+                    );
+                    1
+                }
+            };
+            let _ = client.event("exited", json!({ "exitCode": exit_code }));
+            let _ = client.event("terminated", json!({}));
+        });
+        Self { commands, stopped }
+    }
+
+    pub(crate) fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+
+    /// Sends `command` to the executing thread. It is ignored if
+    /// the program has already finished.
+    pub(crate) fn send(&self, command: Command) {
+        if !matches!(command, Command::Inspect(_)) {
+            // Mark execution as resumed right away, so that inspection requests
+            // that arrive before the executing thread sees `command` are refused.
+            self.stopped.store(false, Ordering::SeqCst);
+        }
+        let _ = self.commands.send(command);
+    }
+}
+
+/// Returns the lines of `program` where execution can stop.
+pub(crate) fn stopping_lines(program: &Path) -> eyre::Result<BTreeSet<u32>> {
+    let mut db = dada_db::Db::default();
+    let filename = load(&mut db, program)?;
+    Ok(
+        dada_breakpoint::locations::line_start_breakpoints(&db, filename)
+            .into_iter()
+            .map(|(position, _)| position.line1())
+            .collect(),
+    )
+}
+
+fn load(db: &mut dada_db::Db, program: &Path) -> eyre::Result<Filename> {
+    let contents = std::fs::read_to_string(program)
+        .with_context(|| format!("reading `{}`", program.display()))?;
+    let filename = Filename::from(db, program);
+    db.update_file(filename, contents);
+    Ok(filename)
+}
+
+fn execute(program: &Path, kernel: &DapKernel) -> eyre::Result<()> {
+    let mut db = dada_db::Db::default();
+    let filename = load(&mut db, program)?;
+
+    let diagnostics = db.diagnostics(filename);
+    if !diagnostics.is_empty() {
+        let output = dada_error_format::format_diagnostics(&db, &diagnostics)?;
+        kernel
+            .client
+            .event("output", json!({"category": "stderr", "output": output}))?;
+    }
+
+    // Execution can only stop at expressions that were compiled as breakpoints,
+    // so we compile one at the start of every line; breakpoints can then be set
+    // on any line, even while the program is running.
+    let locations = dada_breakpoint::locations::line_start_breakpoints(&db, filename)
+        .into_iter()
+        .map(|(position, _)| position)
+        .collect();
+    db.set_breakpoints(filename, locations);
+
+    let function = match db.function_named(filename, "main") {
+        Some(function) => function,
+        None => eyre::bail!(
+            "could not find a function named `main` in `{}`",
+            program.display()
+        ),
+    };
+
+    let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+    runtime.block_on(dada_execute::interpret(function, &db, kernel, vec![]))
+}
+
+/// Returned from the kernel to stop execution when the client disconnects.
+#[derive(Debug)]
+struct Disconnected;
+
+impl std::fmt::Display for Disconnected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the debugger disconnected")
+    }
+}
+
+impl std::error::Error for Disconnected {}
+
+struct DapKernel {
+    client: Arc<Client>,
+    commands: Receiver<Command>,
+    stopped: Arc<AtomicBool>,
+    breakpoint_lines: BreakpointLines,
+
+    /// If true, stop at the next line, whether or not there is a breakpoint.
+    stepping: AtomicBool,
+}
+
+impl DapKernel {
+    /// Answers commands until told to resume (`Ok`) or to disconnect (`Err`).
+    fn stop(
+        &self,
+        db: &dyn dada_execute::Db,
+        reason: &str,
+        heap_graph: &HeapGraph,
+    ) -> eyre::Result<()> {
+        self.stopped.store(true, Ordering::SeqCst);
+        self.client.event(
+            "stopped",
+            json!({"reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true}),
+        )?;
+
+        let stepping = loop {
+            match self.commands.recv() {
+                Ok(Command::Inspect(request)) => match inspect(db, heap_graph, &request) {
+                    Ok(body) => self.client.respond(&request, body)?,
+                    Err(e) => self.client.respond_error(&request, &e.to_string())?,
+                },
+                Ok(Command::Continue) => break false,
+                Ok(Command::Step) => break true,
+                Ok(Command::Disconnect) | Err(_) => {
+                    self.stopped.store(false, Ordering::SeqCst);
+                    return Err(Disconnected.into());
+                }
+            }
+        };
+        self.stepping.store(stepping, Ordering::SeqCst);
+        self.stopped.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+/// Answers a request that inspects the program stopped with `heap_graph`.
+///
+/// Frames are numbered from 1, topmost first. The locals of each frame have the
+/// frame's number as their variables reference; the fields of object `i` (see
+/// [`dada_execute::heap_graph::VariableText::object`]) have `num_frames + 1 + i`.
+fn inspect(
+    db: &dyn dada_execute::Db,
+    heap_graph: &HeapGraph,
+    request: &Request,
+) -> eyre::Result<Value> {
+    let frames = heap_graph.frames_text(db);
+    let variables_json = |variables: Vec<dada_execute::heap_graph::VariableText>| {
+        variables
+            .into_iter()
+            .map(|variable| {
+                json!({
+                    "name": variable.name,
+                    "value": variable.value,
+                    "variablesReference": variable.object.map_or(0, |o| frames.len() + 1 + o),
+                })
+            })
+            .collect::<Vec<_>>()
+    };
+
+    match request.command.as_str() {
+        "stackTrace" => {
+            let stack_frames = frames
+                .iter()
+                .zip(1..)
+                .map(|(frame, id)| {
+                    let (path, line, column) = position(db, frame.span);
+                    json!({
+                        "id": id,
+                        "name": frame.function_name,
+                        "source": { "path": path },
+                        "line": line,
+                        "column": column,
+                    })
+                })
+                .collect::<Vec<_>>();
+            Ok(json!({ "stackFrames": stack_frames, "totalFrames": frames.len() }))
+        }
+        "scopes" => {
+            let frame_id = request.arguments["frameId"].as_u64().unwrap_or(0) as usize;
+            if frame_id == 0 || frame_id > frames.len() {
+                eyre::bail!("no frame with id {frame_id}");
+            }
+            Ok(json!({
+                "scopes": [{
+                    "name": "Locals",
+                    "variablesReference": frame_id,
+                    "expensive": false,
+                }]
+            }))
+        }
+        "variables" => {
+            let reference = request.arguments["variablesReference"]
+                .as_u64()
+                .unwrap_or(0) as usize;
+            let variables = if reference == 0 {
+                vec![]
+            } else if reference <= frames.len() {
+                variables_json(
+                    heap_graph
+                        .frames_text(db)
+                        .swap_remove(reference - 1)
+                        .variables,
+                )
+            } else {
+                variables_json(heap_graph.fields_text(db, reference - frames.len() - 1))
+            };
+            Ok(json!({ "variables": variables }))
+        }
+        "heapGraph" => Ok(json!({ "graphviz": heap_graph.graphviz_alone(db, false) })),
+        command => eyre::bail!("cannot answer `{command}`"),
+    }
+}
+
+/// Returns the path, line, and column (both 1-based) where `span` starts.
+fn position(db: &dyn dada_execute::Db, span: FileSpan) -> (String, u32, u32) {
+    let start = dada_ir::lines::line_column(db, span.filename, span.start);
+    (
+        span.filename.as_str(db).to_string(),
+        start.line1(),
+        start.column1(),
+    )
+}
+
+#[async_trait::async_trait]
+impl dada_execute::kernel::Kernel for DapKernel {
+    async fn print(&self, text: &str) -> eyre::Result<()> {
+        self.client
+            .event("output", json!({"category": "stdout", "output": text}))
+    }
+
+    fn breakpoint_start(
+        &self,
+        _db: &dyn dada_execute::Db,
+        _breakpoint_filename: Filename,
+        _breakpoint_index: usize,
+        _generate_heap_graph: &dyn Fn() -> HeapGraph,
+    ) -> eyre::Result<()> {
+        Ok(())
+    }
+
+    fn breakpoint_end(
+        &self,
+        db: &dyn dada_execute::Db,
+        _breakpoint_filename: Filename,
+        _breakpoint_index: usize,
+        breakpoint_span: FileSpan,
+        generate_heap_graph: &dyn Fn() -> HeapGraph,
+    ) -> eyre::Result<()> {
+        let (_, line, _) = position(db, breakpoint_span);
+        let reason = if self.stepping.load(Ordering::SeqCst) {
+            "step"
+        } else if self.breakpoint_lines.lock().contains(&line) {
+            "breakpoint"
+        } else {
+            return Ok(());
+        };
+        self.stop(db, reason, &generate_heap_graph())
+    }
+}
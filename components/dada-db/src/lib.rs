@@ -1,5 +1,6 @@
 use dada_brew::prelude::MaybeBrewExt;
 use dada_ir::{
+    code::bir::{text::ParseError, Bir},
     diagnostic::Diagnostic,
     filename::Filename,
     function::Function,
@@ -55,6 +56,33 @@ impl Db {
         dada_breakpoint::locations::breakpoint_locations::set(self, filename, locations);
     }
 
+    /// Controls whether we warn about local variables that shadow other definitions within the given file.
+    pub fn set_warn_shadowing(&mut self, filename: Filename, enabled: bool) {
+        dada_validate::warnings::warn_shadowing::set(self, filename, enabled);
+    }
+
+    /// Controls whether we warn about unused variables, parameters, functions, and classes
+    /// within the given file. This is enabled by default.
+    pub fn set_warn_unused(&mut self, filename: Filename, enabled: bool) {
+        dada_check::warnings::warn_unused::set(self, filename, enabled);
+    }
+
+    /// Controls whether the interpreter executes optimized bir for the functions in the given file.
+    pub fn set_optimize_bir(&mut self, filename: Filename, enabled: bool) {
+        dada_brew::optimize::optimizations_enabled::set(self, filename, enabled);
+    }
+
+    /// Returns whether the interpreter executes optimized bir for the functions in the given file.
+    pub fn optimize_bir(&self, filename: Filename) -> bool {
+        dada_brew::optimize::optimizations_enabled(self, filename)
+    }
+
+    /// Controls whether checking the given file also verifies the internal consistency of its bir.
+    /// This is enabled by default in debug builds.
+    pub fn set_verify_bir(&mut self, filename: Filename, enabled: bool) {
+        dada_brew::verify::verification_enabled::set(self, filename, enabled);
+    }
+
     /// Checks `filename` for compilation errors and returns all relevant diagnostics.
     pub fn diagnostics(&self, filename: Filename) -> Vec<Diagnostic> {
         dada_check::check_filename::accumulated::<dada_ir::diagnostic::Diagnostics>(self, filename)
@@ -94,6 +122,36 @@ impl Db {
         Some(item.maybe_brew(self)?.into_debug(self))
     }
 
+    /// Returns the optimized bir for `item`.
+    pub fn debug_optimized_bir(&self, item: Item) -> Option<impl std::fmt::Debug + '_> {
+        Some(item.maybe_brew_optimized(self)?.into_debug(self))
+    }
+
+    /// Returns the bir for `item`, if it is a function.
+    pub fn bir(&self, item: Item) -> Option<Bir> {
+        item.maybe_brew(self)
+    }
+
+    /// Returns the bir for `item` in the textual format from [`dada_ir::code::bir::text`].
+    pub fn bir_text(&self, item: Item) -> Option<String> {
+        Some(dada_ir::code::bir::text::print(self, self.bir(item)?))
+    }
+
+    /// Parses bir in the textual format from [`dada_ir::code::bir::text`] to use as the body of `function`.
+    /// The parsed bir can be executed with [`dada_execute::interpret_bir`].
+    pub fn parse_bir(&self, function: Function, text: &str) -> Result<Bir, ParseError> {
+        let filename = function.filename(self);
+        let root_expr = function.syntax_tree(self).data(self).root_expr;
+        dada_ir::code::bir::text::parse(
+            self,
+            function.code(self),
+            filename.items(self),
+            root_expr,
+            function.name_span(self),
+            text,
+        )
+    }
+
     /// Converts a given offset in a given file into line/column information.
     pub fn line_column(&self, filename: Filename, offset: Offset) -> LineColumn {
         dada_ir::lines::line_column(self, filename, offset)
@@ -1,7 +1,7 @@
 use std::io::Cursor;
 
 use ariadne::{Label, Report, ReportKind, Source};
-use dada_ir::filename::Filename;
+use dada_ir::{diagnostic::Severity, filename::Filename};
 
 pub fn print_diagnostic(
     db: &dyn crate::Db,
@@ -28,17 +28,39 @@ fn ariadne_diagnostic(
     _db: &dyn crate::Db,
     diagnostic: &dada_ir::diagnostic::Diagnostic,
 ) -> eyre::Result<ariadne::Report<ASpan>> {
-    let mut builder = Report::<ASpan>::build(
-        ReportKind::Error,
-        diagnostic.span.filename,
-        diagnostic.span.start.into(),
-    )
-    .with_message(&diagnostic.message);
+    let kind = match diagnostic.severity {
+        Severity::Error => ReportKind::Error,
+        Severity::Warning => ReportKind::Warning,
+        Severity::Note | Severity::Help => ReportKind::Advice,
+    };
+    let mut builder =
+        Report::<ASpan>::build(kind, diagnostic.span.filename, diagnostic.span.start.into())
+            .with_message(&diagnostic.message);
 
     for label in &diagnostic.labels {
         builder = builder.with_label(Label::new(ASpan(label.span())).with_message(&label.message));
     }
 
+    // Ariadne only supports a single note and a single help message per
+    // report, so we merge the messages from all children of each kind.
+    let (helps, notes): (Vec<_>, Vec<_>) = diagnostic
+        .children
+        .iter()
+        .partition(|child| child.severity == Severity::Help);
+    let join = |children: Vec<&dada_ir::diagnostic::Diagnostic>| -> String {
+        children
+            .iter()
+            .map(|child| &child.message[..])
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    if !helps.is_empty() {
+        builder = builder.with_help(join(helps));
+    }
+    if !notes.is_empty() {
+        builder = builder.with_note(join(notes));
+    }
+
     Ok(builder.finish())
 }
 
@@ -1,10 +1,11 @@
 use std::{future::Future, pin::Pin};
 
-use crate::error::DiagnosticBuilderExt;
+use crate::error::{DiagnosticBuilderExt, ErrorKind};
 use crate::execute::StackFrame;
 use crate::ext::*;
 use crate::intrinsic::IntrinsicDefinition;
-use crate::{interpreter::Interpreter, thunk::Thunk, value::Value};
+use crate::{interpreter::Interpreter, scheduler::Channel, thunk::Thunk, value::Value};
+use dada_ir::edit_distance::{did_you_mean, similar_words};
 use dada_ir::parameter::Parameter;
 use dada_ir::word::SpannedOptionalWord;
 use dada_ir::{class::Class, error, function::Function, intrinsic::Intrinsic, word::Word};
@@ -12,6 +13,12 @@ use dada_parse::prelude::*;
 
 pub(crate) type DadaFuture<'i> = Pin<Box<dyn Future<Output = eyre::Result<Value>> + 'i>>;
 
+/// A future that is already complete, with the given value.
+pub(crate) fn ready<'i>(value: Value) -> DadaFuture<'i> {
+    let result: eyre::Result<Value> = Ok(value);
+    Box::pin(std::future::ready(result))
+}
+
 #[derive(Debug)]
 pub(crate) enum Data {
     Instance(Instance),
@@ -19,6 +26,7 @@ pub(crate) enum Data {
     Function(Function),
     Intrinsic(Intrinsic),
     Thunk(Thunk),
+    Channel(Channel),
     Tuple(Tuple),
     Bool(bool),
     Uint(u64),
@@ -46,6 +54,7 @@ data_from_impl! {
     Function(Function),
     Intrinsic(Intrinsic),
     Thunk(Thunk),
+    Channel(Channel),
     Tuple(Tuple),
     Bool(bool),
     Uint(u64),
@@ -64,6 +73,7 @@ impl Data {
             Data::Function(_) => "a function".to_string(),
             Data::Intrinsic(_) => "a function".to_string(),
             Data::Thunk(_) => "a thunk".to_string(),
+            Data::Channel(_) => "a channel".to_string(),
             Data::Tuple(_) => "a tuple".to_string(),
             Data::Bool(_) => "a boolean".to_string(),
             Data::Uint(_) => "an unsigned integer".to_string(),
@@ -74,7 +84,7 @@ impl Data {
         }
     }
 
-    fn expected(&self, interpreter: &Interpreter<'_>, what: &str) -> eyre::Report {
+    pub(crate) fn expected(&self, interpreter: &Interpreter<'_>, what: &str) -> eyre::Report {
         let span = interpreter.span_now();
         error!(
             span,
@@ -82,7 +92,7 @@ impl Data {
             what,
             self.kind_str(interpreter)
         )
-        .eyre(interpreter.db())
+        .eyre(interpreter.db(), ErrorKind::TypeMismatch)
     }
 
     fn no_such_field(interpreter: &Interpreter<'_>, class: Class, name: Word) -> eyre::Report {
@@ -99,7 +109,16 @@ impl Data {
             class_span,
             &format!("the class `{}` is declared here", class_name),
         )
-        .eyre(interpreter.db())
+        .maybe_child(did_you_mean(
+            interpreter.db(),
+            span,
+            &similar_words(
+                interpreter.db(),
+                name,
+                class.field_names(interpreter.db()).iter().copied(),
+            ),
+        ))
+        .eyre(interpreter.db(), ErrorKind::TypeMismatch)
     }
 
     pub(crate) fn field(&self, interpreter: &Interpreter<'_>, name: Word) -> eyre::Result<&Value> {
@@ -138,6 +157,28 @@ impl Data {
         Ok(())
     }
 
+    /// Replaces the value of the field `name`, returning the class,
+    /// the index of the field, and the value that was replaced.
+    pub(crate) fn replace_field(
+        &mut self,
+        interpreter: &Interpreter<'_>,
+        name: Word,
+        value: Value,
+    ) -> eyre::Result<(Class, usize, Value)> {
+        let db = interpreter.db();
+        match self {
+            Data::Instance(i) => match i.class.field_index(db, name) {
+                Some(index) => Ok((
+                    i.class,
+                    index,
+                    std::mem::replace(&mut i.fields[index], value),
+                )),
+                None => Err(Self::no_such_field(interpreter, i.class, name)),
+            },
+            _ => Err(self.expected(interpreter, "something with fields")),
+        }
+    }
+
     pub(crate) fn to_bool(&self, interpreter: &Interpreter<'_>) -> eyre::Result<bool> {
         match self {
             Data::Bool(b) => Ok(*b),
@@ -159,6 +200,13 @@ impl Data {
         }
     }
 
+    pub(crate) fn to_channel(&self, interpreter: &Interpreter<'_>) -> eyre::Result<Channel> {
+        match self {
+            Data::Channel(c) => Ok(*c),
+            _ => Err(self.expected(interpreter, "a channel")),
+        }
+    }
+
     pub(crate) fn into_thunk(self, interpreter: &Interpreter<'_>) -> eyre::Result<Thunk> {
         match self {
             Data::Thunk(v) => Ok(v),
@@ -171,13 +219,13 @@ impl Data {
     /// which (when awaited) will perform the call. This is because `call` is
     /// invoked inside of a closure which can't await (which itself maybe should
     /// change!).
-    pub(crate) fn call(
+    pub(crate) fn call<'i>(
         &self,
-        interpreter: &Interpreter<'_>,
+        interpreter: &'i Interpreter<'_>,
         arguments: Vec<Value>,
         labels: &[SpannedOptionalWord],
-        parent_stack_frame: Option<&StackFrame<'_>>,
-    ) -> eyre::Result<Value> {
+        parent_stack_frame: Option<&'i StackFrame<'_>>,
+    ) -> eyre::Result<DadaFuture<'i>> {
         assert_eq!(arguments.len(), labels.len());
         let db = interpreter.db();
         match self {
@@ -188,17 +236,17 @@ impl Data {
                     class: *c,
                     fields: arguments,
                 };
-                Ok(Value::new(interpreter, instance))
+                Ok(ready(Value::new(interpreter, instance)))
             }
             Data::Function(function) => {
                 let parameters = function.parameters(db);
                 match_labels(interpreter, labels, parameters)?;
-                interpreter.execute_function(*function, arguments, parent_stack_frame)
+                Ok(interpreter.execute_function(*function, arguments, parent_stack_frame))
             }
             Data::Intrinsic(intrinsic) => {
                 let definition = IntrinsicDefinition::for_intrinsic(interpreter.db(), *intrinsic);
                 match_labels(interpreter, labels, &definition.argument_names)?;
-                (definition.function)(interpreter, arguments)
+                Ok(ready((definition.function)(interpreter, arguments)?))
             }
             _ => {
                 let span = interpreter.span_now();
@@ -207,7 +255,7 @@ impl Data {
                     "expected something callable, found {}",
                     self.kind_str(interpreter)
                 )
-                .eyre(interpreter.db()))
+                .eyre(interpreter.db(), ErrorKind::TypeMismatch))
             }
         }
     }
@@ -230,7 +278,7 @@ fn match_labels(
                     expected_name.as_str(db),
                     actual_word.as_str(db),
                 )
-                .eyre(db));
+                .eyre(db, ErrorKind::TypeMismatch));
             }
         }
     }
@@ -242,7 +290,7 @@ fn match_labels(
             expected_names.len(),
             actual_labels.len(),
         )
-        .eyre(db));
+        .eyre(db, ErrorKind::TypeMismatch));
     }
 
     Ok(())
@@ -1,24 +1,87 @@
-use dada_ir::diagnostic::DiagnosticBuilder;
+use dada_ir::diagnostic::{Diagnostic, DiagnosticBuilder};
 
 #[extension_trait::extension_trait]
 pub impl DiagnosticBuilderExt for DiagnosticBuilder {
-    fn eyre(self, db: &dyn crate::Db) -> eyre::Report {
-        let diagnostic = self.finish();
-        match dada_error_format::format_diagnostics(db, &[diagnostic]) {
-            Ok(string) => eyre::Report::new(DiagnosticError { string }),
-            Err(report) => {
-                // FIXME: should give causal information
-                report
-            }
-        }
+    fn eyre(self, db: &dyn crate::Db, kind: ErrorKind) -> eyre::Report {
+        eyre::Report::new(DiagnosticError::new(db, self.finish(), kind))
     }
 }
 
+/// What went wrong when execution failed with a [`DiagnosticError`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A permission did not allow the access (e.g., it was already given away).
+    Permission,
+
+    /// A value was not of the expected kind (e.g., a condition that is not a boolean).
+    TypeMismatch,
+
+    /// An arithmetic operation failed (e.g., division by zero or overflow).
+    Arithmetic,
+
+    /// Execution reached code that failed to compile.
+    Compilation,
+
+    /// Tasks deadlocked, or an atomic section conflicted with another task.
+    Concurrency,
+
+    /// Execution took too many steps or too long, or the heap grew too large.
+    ResourceLimit,
+
+    /// Execution reached a `panic`.
+    Panic,
+
+    /// Anything else (e.g., printing failed).
+    Other,
+}
+
+/// The error that execution fails with when the Dada program did something wrong.
+/// Embedders can get it with `eyre::Report::downcast_ref` to inspect the diagnostic,
+/// e.g. to render it themselves.
 #[derive(Debug)]
 pub struct DiagnosticError {
+    diagnostic: Diagnostic,
+    kind: ErrorKind,
+
+    /// The diagnostic, formatted for display.
     string: String,
 }
 
+impl DiagnosticError {
+    fn new(db: &dyn crate::Db, diagnostic: Diagnostic, kind: ErrorKind) -> Self {
+        let string = format_diagnostic(db, &diagnostic);
+        Self {
+            diagnostic,
+            kind,
+            string,
+        }
+    }
+
+    /// The diagnostic describing the error.
+    pub fn diagnostic(&self) -> &Diagnostic {
+        &self.diagnostic
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Adds `note` to the diagnostic (e.g., to record a stack frame that the error
+    /// propagated through).
+    pub(crate) fn add_note(&mut self, db: &dyn crate::Db, note: Diagnostic) {
+        self.diagnostic.children.push(note);
+        self.string = format_diagnostic(db, &self.diagnostic);
+    }
+}
+
+/// Formats `diagnostic` for display, falling back to just its message
+/// if it cannot be rendered with its source.
+fn format_diagnostic(db: &dyn crate::Db, diagnostic: &Diagnostic) -> String {
+    dada_error_format::format_diagnostics(db, std::slice::from_ref(diagnostic))
+        .unwrap_or_else(|_| diagnostic.message.clone())
+}
+
 impl std::error::Error for DiagnosticError {}
 
 impl std::fmt::Display for DiagnosticError {
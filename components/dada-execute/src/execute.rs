@@ -1,4 +1,4 @@
-use std::{future::Future, pin::Pin};
+use std::{future::Future, pin::Pin, sync::Arc};
 
 use dada_brew::prelude::*;
 use dada_collections::IndexVec;
@@ -12,16 +12,21 @@ use dada_ir::{
     code::{bir, syntax},
     error,
     origin_table::HasOriginIn,
-    span::FileSpan,
+    span::{FileSpan, Span},
+    storage_mode::{Atomic, Joint, StorageMode},
+    word::Word,
 };
 use dada_parse::prelude::*;
 
+use crate::execution::{Control, Pause, PauseReason};
+use crate::ext::DadaExecuteClassExt;
 use crate::heap_graph::HeapGraph;
+use crate::history::Snapshot;
 use crate::kernel::Kernel;
 use crate::thunk::Thunk;
 use crate::{
-    data::{Data, Tuple},
-    error::DiagnosticBuilderExt,
+    data::{DadaFuture, Data, Tuple},
+    error::{DiagnosticBuilderExt, DiagnosticError, ErrorKind},
     interpreter::Interpreter,
     value::Value,
 };
@@ -32,13 +37,46 @@ pub async fn interpret(
     db: &dyn crate::Db,
     kernel: &dyn Kernel,
     arguments: Vec<Value>,
+) -> eyre::Result<()> {
+    interpret_bir(
+        function,
+        function.brew_for_execution(db),
+        db,
+        kernel,
+        arguments,
+    )
+    .await
+}
+
+/// Interprets the given bir for `function` with the given kernel, rather than the
+/// bir brewed from its source. Calls to other functions execute their brewed bir as usual.
+/// Assumes this is the top stack frame.
+pub async fn interpret_bir(
+    function: Function,
+    bir: bir::Bir,
+    db: &dyn crate::Db,
+    kernel: &dyn Kernel,
+    arguments: Vec<Value>,
+) -> eyre::Result<()> {
+    interpret_with_control(function, bir, db, kernel, arguments, None).await
+}
+
+/// Interprets the given bir for `function`; if `control` is given, execution pauses
+/// as it requests (see [`crate::execution::Execution`]).
+pub(crate) async fn interpret_with_control(
+    function: Function,
+    bir: bir::Bir,
+    db: &dyn crate::Db,
+    kernel: &dyn Kernel,
+    arguments: Vec<Value>,
+    control: Option<Arc<Control>>,
 ) -> eyre::Result<()> {
     let initial_span = function.name_span(db);
-    let interpreter = &Interpreter::new(db, kernel, initial_span);
-    let bir = function.brew(db);
-    let value = interpreter
-        .execute_bir(function, bir, arguments, None)
-        .await?;
+    let interpreter = &Interpreter::new(db, kernel, initial_span, control);
+    let main = interpreter.execute_bir(function, bir, arguments, None);
+    let result = crate::scheduler::run(interpreter, main).await;
+    kernel.report_heap_stats(interpreter.heap().stats());
+    let value = result?;
     value.read(interpreter, |data| data.to_unit(interpreter))
 }
 
@@ -51,6 +89,37 @@ pub struct StackFrame<'me> {
     origins: &'me bir::Origins,
     basic_block: bir::BasicBlock,
     location: StackFrameLocation,
+
+    /// The interpreter's atomic depth when this frame began executing;
+    /// the frame must end any atomic sections it starts before returning.
+    atomic_depth_on_entry: usize,
+}
+
+/// Properties of the storage reached by a path like `a.b.c`, accumulated
+/// over each step of the path.
+#[derive(Copy, Clone, Debug)]
+struct PlaceStorage {
+    /// Some step of the path was declared `atomic`.
+    atomic: Atomic,
+
+    /// Some step of the path may be accessed through an alias: it was declared
+    /// `shared` or it was reached through a value with a joint permission.
+    joint: Joint,
+}
+
+impl PlaceStorage {
+    /// Storage for values that are always jointly owned, like functions and classes.
+    const OUR: PlaceStorage = PlaceStorage {
+        atomic: Atomic::No,
+        joint: Joint::Yes,
+    };
+
+    fn for_mode(mode: StorageMode) -> PlaceStorage {
+        PlaceStorage {
+            atomic: mode.atomic(),
+            joint: mode.joint(),
+        }
+    }
 }
 
 pub(crate) enum StackFrameLocation {
@@ -61,27 +130,23 @@ pub(crate) enum StackFrameLocation {
 }
 
 impl Interpreter<'_> {
-    /// Executes the function on the given arguments. If function is an async
-    /// function, this will simply return a thunk that, when awaited, runs the code.
-    /// Otherwise it runs the function.
-    pub(crate) fn execute_function(
-        &self,
+    /// Returns a future that executes the function on the given arguments. If function
+    /// is an async function, the future simply returns a thunk that, when awaited, runs
+    /// the code. Otherwise it runs the function.
+    pub(crate) fn execute_function<'i>(
+        &'i self,
         function: Function,
         arguments: Vec<Value>,
-        parent_stack_frame: Option<&StackFrame<'_>>,
-    ) -> eyre::Result<Value> {
+        parent_stack_frame: Option<&'i StackFrame<'_>>,
+    ) -> DadaFuture<'i> {
         if let Effect::Async = function.code(self.db()).effect {
             let thunk = Thunk::for_function(function, arguments);
-            Ok(Value::new(self, thunk))
+            crate::data::ready(Value::new(self, thunk))
         } else {
-            let bir = function.brew(self.db());
-            let future = self.execute_bir(function, bir, arguments, parent_stack_frame);
-
-            // This is interesting. `execute_bir` is async in *Rust* because it is
-            // for both `fn` and `async fn` in Dada -- but in the case that we are
-            // executing a Dada `fn`, we know that it will never await anything.
-            // Therefore we can just poll it a single time.
-            crate::poll_once::poll_once(future)
+            // A Dada `fn` never awaits anything, but its future can still be
+            // pending: execution may pause within it (see [`crate::execution`]).
+            let bir = function.brew_for_execution(self.db());
+            self.execute_bir(function, bir, arguments, parent_stack_frame)
         }
     }
 
@@ -125,6 +190,7 @@ impl Interpreter<'_> {
             parent_stack_frame,
             basic_block: bir_data.start_basic_block,
             location: StackFrameLocation::Block(bir_data.start_basic_block),
+            atomic_depth_on_entry: 0,
         };
         Box::pin(stack_frame.execute(self))
     }
@@ -147,11 +213,55 @@ impl StackFrame<'_> {
     }
 
     async fn execute(mut self, interpreter: &Interpreter<'_>) -> eyre::Result<Value> {
+        self.atomic_depth_on_entry = interpreter.atomic_depth();
+        let mut result = self.execute_blocks(interpreter).await;
+        if let Err(report) = &mut result {
+            if interpreter.atomic_depth() > self.atomic_depth_on_entry {
+                // Leave the atomic sections this frame started; if they were
+                // the outermost, this rolls back their writes.
+                interpreter.abort_atomic(self.atomic_depth_on_entry);
+            }
+            self.add_backtrace_note(interpreter.db(), report);
+        }
+        result
+    }
+
+    /// If this frame was called from another, adds a note to the diagnostic
+    /// in `report` saying where. As the error propagates up the stack, each
+    /// frame adds its note, so that together they form a backtrace.
+    ///
+    /// The notes are children of the diagnostic, which `dada-lsp` already
+    /// turns into related information; but the language server only checks
+    /// programs and never runs them, so editors do not see backtraces yet.
+    fn add_backtrace_note(&self, db: &dyn crate::Db, report: &mut eyre::Report) {
+        let (parent, diagnostic_error) = match (
+            self.parent_stack_frame,
+            report.downcast_mut::<DiagnosticError>(),
+        ) {
+            (Some(parent), Some(diagnostic_error)) => (parent, diagnostic_error),
+            _ => return,
+        };
+        let call_span = parent.current_span(db);
+        let call_position = dada_ir::lines::line_column(db, call_span.filename, call_span.start);
+        let note = dada_ir::note!(
+            call_span,
+            "in `{}`, called from `{}` at {}:{}:{}",
+            self.function.name(db).as_str(db),
+            parent.function.name(db).as_str(db),
+            call_span.filename.as_str(db),
+            call_position.line1(),
+            call_position.column1(),
+        )
+        .finish();
+        diagnostic_error.add_note(db, note);
+    }
+
+    async fn execute_blocks(&mut self, interpreter: &Interpreter<'_>) -> eyre::Result<Value> {
         loop {
             let basic_block_data = self.basic_block.data(self.tables);
             for statement in &basic_block_data.statements {
                 self.location = StackFrameLocation::Statement(*statement);
-                self.tick_clock(interpreter, *statement);
+                self.tick_clock(interpreter, *statement).await?;
                 match statement.data(self.tables) {
                     dada_ir::code::bir::StatementData::Assign(place, expr) => {
                         let expr_value = self.evaluate_bir_expr(interpreter, *expr)?;
@@ -162,8 +272,10 @@ impl StackFrame<'_> {
                             interpreter.db(),
                             *filename,
                             *index,
-                            &|| HeapGraph::new(interpreter, &self, None),
+                            &|| HeapGraph::new(interpreter, &*self, None),
                         )?;
+                        self.pause(interpreter, PauseReason::BreakpointStart, None)
+                            .await;
                     }
                     dada_ir::code::bir::StatementData::BreakpointEnd(
                         filename,
@@ -180,14 +292,17 @@ impl StackFrame<'_> {
                             *filename,
                             *index,
                             span.in_file(*filename),
-                            &|| HeapGraph::new(interpreter, &self, *place),
+                            &|| HeapGraph::new(interpreter, &*self, *place),
                         )?;
+                        self.pause(interpreter, PauseReason::BreakpointEnd, *place)
+                            .await;
                     }
                 }
             }
 
-            self.tick_clock(interpreter, basic_block_data.terminator);
             self.location = StackFrameLocation::Terminator(basic_block_data.terminator);
+            self.tick_clock(interpreter, basic_block_data.terminator)
+                .await?;
             match basic_block_data.terminator.data(self.tables) {
                 dada_ir::code::bir::TerminatorData::Goto(next_block) => {
                     self.basic_block = *next_block;
@@ -200,12 +315,37 @@ impl StackFrame<'_> {
                     }
                 }
                 dada_ir::code::bir::TerminatorData::StartAtomic(next_block) => {
+                    interpreter.start_atomic();
                     self.basic_block = *next_block;
                 }
                 dada_ir::code::bir::TerminatorData::EndAtomic(next_block) => {
+                    if interpreter.atomic_depth() == self.atomic_depth_on_entry {
+                        let span =
+                            self.span_from_bir(interpreter.db(), basic_block_data.terminator);
+                        return Err(error!(
+                            span,
+                            "end of an atomic section that was never started"
+                        )
+                        .eyre(interpreter.db(), ErrorKind::Compilation));
+                    }
+                    if interpreter.end_atomic().is_err() {
+                        let span =
+                            self.span_from_bir(interpreter.db(), basic_block_data.terminator);
+                        return Err(error!(
+                            span,
+                            "atomic section conflicted with another task and was rolled back"
+                        )
+                        .eyre(interpreter.db(), ErrorKind::Concurrency));
+                    }
                     self.basic_block = *next_block;
                 }
                 dada_ir::code::bir::TerminatorData::Return(place) => {
+                    if interpreter.atomic_depth() != self.atomic_depth_on_entry {
+                        let span =
+                            self.span_from_bir(interpreter.db(), basic_block_data.terminator);
+                        return Err(error!(span, "return from within an atomic section")
+                            .eyre(interpreter.db(), ErrorKind::Compilation));
+                    }
                     return self.give_place(interpreter, *place);
                 }
                 dada_ir::code::bir::TerminatorData::Assign(place, expr, next) => {
@@ -215,11 +355,12 @@ impl StackFrame<'_> {
                 }
                 dada_ir::code::bir::TerminatorData::Error => {
                     let span = self.span_from_bir(interpreter.db(), basic_block_data.terminator);
-                    return Err(error!(span, "compilation error").eyre(interpreter.db()));
+                    return Err(error!(span, "compilation error")
+                        .eyre(interpreter.db(), ErrorKind::Compilation));
                 }
                 dada_ir::code::bir::TerminatorData::Panic => {
                     let span = self.span_from_bir(interpreter.db(), basic_block_data.terminator);
-                    return Err(error!(span, "panic").eyre(interpreter.db()));
+                    return Err(error!(span, "panic").eyre(interpreter.db(), ErrorKind::Panic));
                 }
             }
         }
@@ -258,7 +399,8 @@ impl StackFrame<'_> {
             }
             bir::ExprData::Error => {
                 let span = self.span_from_bir(interpreter.db(), expr);
-                Err(error!(span, "compilation error").eyre(interpreter.db()))
+                Err(error!(span, "compilation error")
+                    .eyre(interpreter.db(), ErrorKind::Compilation))
             }
             bir::ExprData::Unit => Ok(Value::new(interpreter, ())),
         }
@@ -272,12 +414,47 @@ impl StackFrame<'_> {
         self.with_place_mut(interpreter, place, Value::give)
     }
 
-    fn tick_clock(
+    async fn tick_clock(
         &self,
         interpreter: &Interpreter<'_>,
         expr: impl HasOriginIn<bir::Origins, Origin = syntax::Expr>,
+    ) -> eyre::Result<()> {
+        let span = self.span_from_bir(interpreter.db(), expr);
+        interpreter.tick_clock(span)?;
+        if interpreter.records_history() {
+            interpreter.kernel().record_step(
+                interpreter.db(),
+                Snapshot {
+                    clock: interpreter.clock_now(),
+                    span,
+                    heap_graph: HeapGraph::new(interpreter, self, None),
+                },
+            );
+        }
+        self.pause(interpreter, PauseReason::Step, None).await;
+        Ok(())
+    }
+
+    /// If execution is driven by an [`Execution`](crate::execution::Execution) that
+    /// wants to pause for `reason`, captures the state of the program (with `place`
+    /// as the in-flight value) and suspends until execution is resumed.
+    async fn pause(
+        &self,
+        interpreter: &Interpreter<'_>,
+        reason: PauseReason,
+        place: Option<bir::Place>,
     ) {
-        interpreter.tick_clock(self.span_from_bir(interpreter.db(), expr));
+        if let Some(control) = interpreter.control() {
+            if control.pauses_at(reason) {
+                let pause = Pause {
+                    reason,
+                    clock: interpreter.clock_now(),
+                    span: interpreter.span_now(),
+                    heap_graph: HeapGraph::new(interpreter, self, place),
+                };
+                control.pause(pause).await;
+            }
+        }
     }
 
     fn span_from_bir(
@@ -292,15 +469,23 @@ impl StackFrame<'_> {
         syntax_tree.spans(db)[syntax_expr].in_file(filename)
     }
 
+    /// Records that `place` is being accessed, so that permission errors
+    /// can explain which access canceled a permission.
+    fn access_place(&self, interpreter: &Interpreter<'_>, place: bir::Place) {
+        interpreter.access_place(self.span_from_bir(interpreter.db(), place));
+    }
+
     fn assign_place(
         &mut self,
         interpreter: &Interpreter<'_>,
         place: bir::Place,
         value: Value,
     ) -> eyre::Result<()> {
+        self.access_place(interpreter, place);
         match place.data(self.tables) {
             bir::PlaceData::LocalVariable(local_variable) => {
-                // FIXME: Presently infallible, but think about atomic etc eventually. =)
+                // A local variable is only reachable from its own stack frame, so it is
+                // never joint and can be assigned even if it is `atomic`.
                 let slot = &mut self.local_variables[*local_variable];
                 *slot = value;
                 Ok(())
@@ -314,7 +499,7 @@ impl StackFrame<'_> {
                         name_span,
                         &format!("`{}` is a function, declared here", name),
                     )
-                    .eyre(interpreter.db()))
+                    .eyre(interpreter.db(), ErrorKind::TypeMismatch))
             }
             bir::PlaceData::Class(class) => {
                 let span_now = interpreter.span_now();
@@ -322,19 +507,39 @@ impl StackFrame<'_> {
                 let name_span = class.name_span(interpreter.db());
                 Err(error!(span_now, "cannot assign to `{}`", name)
                     .secondary_label(name_span, &format!("`{}` is a class, declared here", name))
-                    .eyre(interpreter.db()))
+                    .eyre(interpreter.db(), ErrorKind::TypeMismatch))
             }
             bir::PlaceData::Intrinsic(intrinsic) => {
                 let span_now = interpreter.span_now();
                 let name = intrinsic.as_str(interpreter.db());
-                Err(error!(span_now, "cannot assign to `{}`", name).eyre(interpreter.db()))
+                Err(error!(span_now, "cannot assign to `{}`", name)
+                    .eyre(interpreter.db(), ErrorKind::TypeMismatch))
             }
             bir::PlaceData::Dot(owner_place, field_name) => {
-                self.with_place(interpreter, *owner_place, |owner_value, interpreter| {
-                    owner_value.write(interpreter, |data| {
-                        data.assign_field(interpreter, *field_name, value)
-                    })
-                })
+                let code = self.code(interpreter.db());
+                let origin = self.origins[place];
+                self.with_place_storage(
+                    interpreter,
+                    *owner_place,
+                    |owner_value, owner_storage, interpreter| {
+                        let storage = field_storage(
+                            interpreter,
+                            code,
+                            origin,
+                            owner_value,
+                            owner_storage,
+                            *field_name,
+                        )?;
+                        match storage.atomic {
+                            Atomic::Yes if interpreter.atomic_depth() > 0 => {
+                                owner_value.assign_atomic_field(interpreter, *field_name, value)
+                            }
+                            _ => owner_value.write(interpreter, |data| {
+                                data.assign_field(interpreter, *field_name, value)
+                            }),
+                        }
+                    },
+                )
             }
         }
     }
@@ -344,36 +549,70 @@ impl StackFrame<'_> {
         interpreter: &Interpreter<'_>,
         place: bir::Place,
         op: impl FnOnce(&Value, &Interpreter) -> eyre::Result<R>,
+    ) -> eyre::Result<R> {
+        self.access_place(interpreter, place);
+        self.with_place_storage(interpreter, place, |value, _storage, interpreter| {
+            op(value, interpreter)
+        })
+    }
+
+    /// Like [`Self::with_place`], but also gives `op` the storage reached by `place`.
+    /// Reports an error if `place` accesses shared, atomic storage outside of an
+    /// atomic section.
+    fn with_place_storage<R>(
+        &self,
+        interpreter: &Interpreter<'_>,
+        place: bir::Place,
+        op: impl FnOnce(&Value, PlaceStorage, &Interpreter) -> eyre::Result<R>,
     ) -> eyre::Result<R> {
         match place.data(self.tables) {
-            bir::PlaceData::LocalVariable(local_variable) => {
-                op(&self.local_variables[*local_variable], interpreter)
-            }
-            bir::PlaceData::Function(function) => {
-                op(&Value::our(interpreter, *function), interpreter)
-            }
-            bir::PlaceData::Class(class) => op(&Value::our(interpreter, *class), interpreter),
-            bir::PlaceData::Intrinsic(intrinsic) => {
-                op(&Value::our(interpreter, *intrinsic), interpreter)
-            }
-            bir::PlaceData::Dot(place, word) => {
-                self.with_place_box(interpreter, *place, |value, interpreter| {
-                    value.field(interpreter, *word, |v| op(v, interpreter))
-                })
+            bir::PlaceData::LocalVariable(local_variable) => op(
+                &self.local_variables[*local_variable],
+                self.local_variable_storage(*local_variable),
+                interpreter,
+            ),
+            bir::PlaceData::Function(function) => op(
+                &Value::our(interpreter, *function),
+                PlaceStorage::OUR,
+                interpreter,
+            ),
+            bir::PlaceData::Class(class) => op(
+                &Value::our(interpreter, *class),
+                PlaceStorage::OUR,
+                interpreter,
+            ),
+            bir::PlaceData::Intrinsic(intrinsic) => op(
+                &Value::our(interpreter, *intrinsic),
+                PlaceStorage::OUR,
+                interpreter,
+            ),
+            bir::PlaceData::Dot(owner_place, word) => {
+                let code = self.code(interpreter.db());
+                let origin = self.origins[place];
+                self.with_place_storage_box(
+                    interpreter,
+                    *owner_place,
+                    |value, storage, interpreter| {
+                        let storage =
+                            field_storage(interpreter, code, origin, value, storage, *word)?;
+                        value.field(interpreter, *word, |v| op(v, storage, interpreter))
+                    },
+                )
             }
         }
     }
 
-    /// Hack that invokes `with_place` after boxing and using dyn trait;
+    /// Hack that invokes `with_place_storage` after boxing and using dyn trait;
     /// without this, we get infinite monomorphic expansion for `PlaceData::Dot`.
-    fn with_place_box<R>(
+    fn with_place_storage_box<R>(
         &self,
         interpreter: &Interpreter<'_>,
         place: bir::Place,
-        op: impl FnOnce(&Value, &Interpreter) -> eyre::Result<R>,
+        op: impl FnOnce(&Value, PlaceStorage, &Interpreter) -> eyre::Result<R>,
     ) -> eyre::Result<R> {
-        let op: Box<dyn FnOnce(&Value, &Interpreter) -> eyre::Result<R>> = Box::new(op);
-        self.with_place(interpreter, place, op)
+        let op: Box<dyn FnOnce(&Value, PlaceStorage, &Interpreter) -> eyre::Result<R>> =
+            Box::new(op);
+        self.with_place_storage(interpreter, place, op)
     }
 
     fn with_place_mut<R>(
@@ -381,36 +620,76 @@ impl StackFrame<'_> {
         interpreter: &Interpreter<'_>,
         place: bir::Place,
         op: impl FnOnce(&mut Value, &Interpreter) -> eyre::Result<R>,
+    ) -> eyre::Result<R> {
+        self.access_place(interpreter, place);
+        self.with_place_mut_storage(interpreter, place, |value, _storage, interpreter| {
+            op(value, interpreter)
+        })
+    }
+
+    /// Like [`Self::with_place_storage`], but gives `op` mutable access to the value.
+    fn with_place_mut_storage<R>(
+        &mut self,
+        interpreter: &Interpreter<'_>,
+        place: bir::Place,
+        op: impl FnOnce(&mut Value, PlaceStorage, &Interpreter) -> eyre::Result<R>,
     ) -> eyre::Result<R> {
         match place.data(self.tables) {
             bir::PlaceData::LocalVariable(local_variable) => {
-                op(&mut self.local_variables[*local_variable], interpreter)
-            }
-            bir::PlaceData::Function(function) => {
-                op(&mut Value::our(interpreter, *function), interpreter)
+                let storage = self.local_variable_storage(*local_variable);
+                op(
+                    &mut self.local_variables[*local_variable],
+                    storage,
+                    interpreter,
+                )
             }
-            bir::PlaceData::Class(class) => op(&mut Value::our(interpreter, *class), interpreter),
-            bir::PlaceData::Intrinsic(intrinsic) => {
-                op(&mut Value::our(interpreter, *intrinsic), interpreter)
-            }
-            bir::PlaceData::Dot(place, word) => {
-                self.with_place_mut_box(interpreter, *place, |value, interpreter| {
-                    value.field_mut(interpreter, *word, |v| op(v, interpreter))
-                })
+            bir::PlaceData::Function(function) => op(
+                &mut Value::our(interpreter, *function),
+                PlaceStorage::OUR,
+                interpreter,
+            ),
+            bir::PlaceData::Class(class) => op(
+                &mut Value::our(interpreter, *class),
+                PlaceStorage::OUR,
+                interpreter,
+            ),
+            bir::PlaceData::Intrinsic(intrinsic) => op(
+                &mut Value::our(interpreter, *intrinsic),
+                PlaceStorage::OUR,
+                interpreter,
+            ),
+            bir::PlaceData::Dot(owner_place, word) => {
+                let code = self.code(interpreter.db());
+                let origin = self.origins[place];
+                self.with_place_mut_storage_box(
+                    interpreter,
+                    *owner_place,
+                    |value, storage, interpreter| {
+                        let storage =
+                            field_storage(interpreter, code, origin, value, storage, *word)?;
+                        value.field_mut(interpreter, *word, |v| op(v, storage, interpreter))
+                    },
+                )
             }
         }
     }
 
-    /// Hack that invokes `with_place` after boxing and using dyn trait;
+    /// Hack that invokes `with_place_mut_storage` after boxing and using dyn trait;
     /// without this, we get infinite monomorphic expansion for `PlaceData::Dot`.
-    fn with_place_mut_box<R>(
+    fn with_place_mut_storage_box<R>(
         &mut self,
         interpreter: &Interpreter<'_>,
         place: bir::Place,
-        op: impl FnOnce(&mut Value, &Interpreter) -> eyre::Result<R>,
+        op: impl FnOnce(&mut Value, PlaceStorage, &Interpreter) -> eyre::Result<R>,
     ) -> eyre::Result<R> {
-        let op: Box<dyn FnOnce(&mut Value, &Interpreter) -> eyre::Result<R>> = Box::new(op);
-        self.with_place_mut(interpreter, place, op)
+        let op: Box<dyn FnOnce(&mut Value, PlaceStorage, &Interpreter) -> eyre::Result<R>> =
+            Box::new(op);
+        self.with_place_mut_storage(interpreter, place, op)
+    }
+
+    /// The storage declared for a local variable.
+    fn local_variable_storage(&self, local_variable: bir::LocalVariable) -> PlaceStorage {
+        PlaceStorage::for_mode(local_variable.data(self.tables).storage_mode)
     }
 
     fn eval_place_to_bool(
@@ -431,6 +710,10 @@ impl StackFrame<'_> {
         match expr {
             bir::TerminatorExpr::Await(place) => {
                 let value = self.give_place(interpreter, *place)?;
+
+                // Awaiting gives the other tasks a chance to run.
+                interpreter.scheduler().yield_now().await;
+
                 let data = value.prepare_for_await(interpreter)?;
                 let thunk = data.into_thunk(interpreter)?;
                 thunk.invoke(interpreter, Some(self)).await
@@ -445,9 +728,10 @@ impl StackFrame<'_> {
                     .iter()
                     .map(|argument_place| self.give_place(interpreter, *argument_place))
                     .collect::<eyre::Result<Vec<_>>>()?;
-                function_value.read(interpreter, |data| {
-                    data.call(interpreter, argument_values, argument_labels, Some(self))
-                })
+                let call = function_value.read(interpreter, |data| {
+                    data.call(interpreter, argument_values, argument_labels, Some(&*self))
+                })?;
+                call.await
             }
         }
     }
@@ -469,15 +753,15 @@ impl StackFrame<'_> {
                 lhs.kind_str(interpreter),
                 rhs.kind_str(interpreter)
             )
-            .eyre(interpreter.db()))
+            .eyre(interpreter.db(), ErrorKind::TypeMismatch))
         };
         let div_zero_error = || {
             let span = self.span_from_bir(interpreter.db(), expr);
-            Err(error!(span, "divide by zero").eyre(interpreter.db()))
+            Err(error!(span, "divide by zero").eyre(interpreter.db(), ErrorKind::Arithmetic))
         };
         let overflow_error = || {
             let span = self.span_from_bir(interpreter.db(), expr);
-            Err(error!(span, "overflow").eyre(interpreter.db()))
+            Err(error!(span, "overflow").eyre(interpreter.db(), ErrorKind::Arithmetic))
         };
         match (lhs, rhs) {
             (Data::Bool(lhs), Data::Bool(rhs)) => match op {
@@ -526,7 +810,8 @@ impl StackFrame<'_> {
                             div_zero_error()
                         } else {
                             let span = self.span_from_bir(interpreter.db(), expr);
-                            Err(error!(span, "signed division overflow").eyre(interpreter.db()))
+                            Err(error!(span, "signed division overflow")
+                                .eyre(interpreter.db(), ErrorKind::Arithmetic))
                         }
                     }
                 },
@@ -545,3 +830,76 @@ impl StackFrame<'_> {
         }
     }
 }
+
+/// Computes the storage reached by the field `field_name` of `owner_value`, whose
+/// own storage is `owner_storage`. `origin` is the expression that accesses the
+/// field, within `code`.
+///
+/// Reports an error if the field is shared and atomic, but execution is not within
+/// an atomic section. If `owner_value` has no such field, returns `owner_storage`
+/// and leaves it to the access itself to report the error.
+fn field_storage(
+    interpreter: &Interpreter<'_>,
+    code: Code,
+    origin: syntax::Expr,
+    owner_value: &Value,
+    owner_storage: PlaceStorage,
+    field_name: Word,
+) -> eyre::Result<PlaceStorage> {
+    let db = interpreter.db();
+    let (class, permission_joint) = owner_value.peek(|permission, data| match data {
+        Data::Instance(instance) => (Some(instance.class), permission.joint()),
+        _ => (None, permission.joint()),
+    });
+    let (class, field_index) = match class.and_then(|c| Some((c, c.field_index(db, field_name)?))) {
+        Some(pair) => pair,
+        None => return Ok(owner_storage),
+    };
+    let field = class.fields(db)[field_index];
+
+    let field_decl = field.decl(db);
+    let field_mode = field_decl.mode.unwrap_or(StorageMode::Shared);
+    let storage = PlaceStorage {
+        atomic: owner_storage.atomic.max(field_mode.atomic()),
+        joint: owner_storage
+            .joint
+            .max(permission_joint)
+            .max(field_mode.joint()),
+    };
+
+    if let Atomic::Yes = storage.atomic {
+        if interpreter.atomic_depth() > 0 {
+            owner_value.record_atomic_access(interpreter, field_index);
+        }
+    }
+
+    if let (Atomic::Yes, Joint::Yes) = (storage.atomic, storage.joint) {
+        if interpreter.atomic_depth() == 0 {
+            // Point at the field name, which ends the `a.b` expression.
+            let span = code.syntax_tree(db).spans(db)[origin];
+            let name_len = field_name.as_str(db).len() as u32;
+            let field_span = if span.len() > name_len {
+                Span::from(span.start + (span.len() - name_len), span.end)
+            } else {
+                span
+            };
+            let mut diagnostic = error!(
+                field_span.in_file(code.filename(db)),
+                "access to shared, atomic field outside of atomic block"
+            )
+            .primary_label("when shared, atomic fields can only be accessed in an atomic block");
+            if let Some(StorageMode::Atomic) = field_decl.mode {
+                diagnostic = diagnostic.secondary_label(
+                    field
+                        .decl_span(db)
+                        .mode_span
+                        .in_file(class.span(db).filename),
+                    format!("`{}` is declared `atomic` here", field_name.as_str(db)),
+                );
+            }
+            return Err(diagnostic.eyre(db, ErrorKind::Permission));
+        }
+    }
+
+    Ok(storage)
+}
@@ -0,0 +1,202 @@
+//! A handle to an execution that can be paused, inspected, and resumed.
+//!
+//! [`crate::interpret`] runs a program to completion; the only way for a kernel
+//! to stop it early is to return an error. An [`Execution`] instead drives the
+//! interpreter one leg at a time: [`Execution::resume`] runs until the program
+//! reaches a breakpoint (or, after [`Execution::step`], the next step), captures
+//! the state of the program as a [`Pause`], and suspends the task that was
+//! running. Resuming continues that task right where it left off.
+//!
+//! Pausing suspends the interpreter's future, so an execution can be driven by
+//! anything that can poll it: the web playground, a debugger, or a REPL.
+
+use std::{future::Future, pin::Pin, sync::Arc, task::Poll};
+
+use dada_brew::prelude::*;
+use dada_ir::{code::bir, function::Function, span::FileSpan};
+use parking_lot::Mutex;
+
+use crate::{heap_graph::HeapGraph, kernel::Kernel, value::Value};
+
+/// A program that is executing, which can be paused and resumed.
+pub struct Execution<'me> {
+    future: Pin<Box<dyn Future<Output = eyre::Result<()>> + 'me>>,
+    control: Arc<Control>,
+    finished: bool,
+}
+
+/// Where execution paused, and the state of the program there.
+pub struct Pause {
+    pub reason: PauseReason,
+
+    /// The clock tick at which execution paused.
+    pub clock: u64,
+
+    /// The span of the code being executed.
+    pub span: FileSpan,
+
+    /// The stack and heap of the task that paused. At the end of a breakpoint,
+    /// the value of the breakpoint expression is the in-flight value.
+    pub heap_graph: HeapGraph,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PauseReason {
+    /// A breakpoint expression is about to execute.
+    BreakpointStart,
+
+    /// A breakpoint expression has finished executing.
+    BreakpointEnd,
+
+    /// A step is about to execute (see [`Execution::step`]).
+    Step,
+}
+
+/// What happened when an execution was resumed.
+pub enum Progress {
+    /// Execution paused and can be resumed again.
+    Paused(Pause),
+
+    /// Execution finished, with the given result.
+    Finished(eyre::Result<()>),
+}
+
+impl<'me> Execution<'me> {
+    /// Prepares to execute `function` on `arguments`; nothing executes until
+    /// [`Self::resume`] is called.
+    pub fn new(
+        db: &'me dyn crate::Db,
+        kernel: &'me dyn Kernel,
+        function: Function,
+        arguments: Vec<Value>,
+    ) -> Self {
+        Self::with_bir(
+            db,
+            kernel,
+            function,
+            function.brew_for_execution(db),
+            arguments,
+        )
+    }
+
+    /// Like [`Self::new`], but executes the given bir for `function` rather than
+    /// the bir brewed from its source (see [`crate::interpret_bir`]).
+    pub fn with_bir(
+        db: &'me dyn crate::Db,
+        kernel: &'me dyn Kernel,
+        function: Function,
+        bir: bir::Bir,
+        arguments: Vec<Value>,
+    ) -> Self {
+        let control = Arc::new(Control::default());
+        let future = crate::execute::interpret_with_control(
+            function,
+            bir,
+            db,
+            kernel,
+            arguments,
+            Some(control.clone()),
+        );
+        Self {
+            future: Box::pin(future),
+            control,
+            finished: false,
+        }
+    }
+
+    /// Builder method: if `pause_at_breakpoints` is false, breakpoints do not
+    /// pause execution (the kernel's breakpoint methods are still invoked).
+    /// By default, they do.
+    pub fn pause_at_breakpoints(self, pause_at_breakpoints: bool) -> Self {
+        self.control.state.lock().ignore_breakpoints = !pause_at_breakpoints;
+        self
+    }
+
+    /// Runs until execution pauses at a breakpoint or finishes.
+    ///
+    /// Panics if execution has already finished.
+    pub async fn resume(&mut self) -> Progress {
+        assert!(!self.finished, "execution has already finished");
+        let future = &mut self.future;
+        let control = &self.control;
+        let progress = std::future::poll_fn(|cx| match future.as_mut().poll(cx) {
+            Poll::Ready(result) => Poll::Ready(Progress::Finished(result)),
+            Poll::Pending => match control.state.lock().pause.take() {
+                Some(pause) => Poll::Ready(Progress::Paused(pause)),
+
+                // The interpreter is waiting on the outside world (e.g., the kernel
+                // is printing), which will wake us.
+                None => Poll::Pending,
+            },
+        })
+        .await;
+        self.finished = matches!(progress, Progress::Finished(_));
+        progress
+    }
+
+    /// Runs a single step, pausing before the next one (or at a breakpoint,
+    /// if one comes first). Steps are the same as the ticks of the interpreter's
+    /// clock, so a single line of code typically takes several steps.
+    ///
+    /// Panics if execution has already finished.
+    pub async fn step(&mut self) -> Progress {
+        self.control.state.lock().stepping = true;
+        let progress = self.resume().await;
+        self.control.state.lock().stepping = false;
+        progress
+    }
+
+    /// True once execution has finished; it cannot be resumed after that.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+/// Shared between an [`Execution`] and its interpreter, which checks whether
+/// to pause at each step and breakpoint.
+#[derive(Default)]
+pub(crate) struct Control {
+    state: Mutex<ControlState>,
+}
+
+#[derive(Default)]
+struct ControlState {
+    /// If true, pause at the next step.
+    stepping: bool,
+
+    /// If true, do not pause at breakpoints.
+    ignore_breakpoints: bool,
+
+    /// Set when execution pauses; taken by [`Execution::resume`].
+    pause: Option<Pause>,
+}
+
+impl Control {
+    /// True if execution should pause for `reason`.
+    pub(crate) fn pauses_at(&self, reason: PauseReason) -> bool {
+        let state = self.state.lock();
+        match reason {
+            PauseReason::BreakpointStart | PauseReason::BreakpointEnd => !state.ignore_breakpoints,
+            PauseReason::Step => state.stepping,
+        }
+    }
+
+    /// Records `pause` and suspends the current task until execution is resumed.
+    pub(crate) async fn pause(&self, pause: Pause) {
+        self.state.lock().pause = Some(pause);
+
+        // Return `Pending` once, without arranging to be woken: the scheduler
+        // sees the task is still running and returns `Pending` too, which
+        // [`Execution::resume`] recognizes as a pause because of the recorded
+        // `Pause`. When the execution is resumed, we are polled again.
+        let mut suspended = false;
+        std::future::poll_fn(move |_cx| {
+            if suspended {
+                return Poll::Ready(());
+            }
+            suspended = true;
+            Poll::Pending
+        })
+        .await
+    }
+}
@@ -0,0 +1,164 @@
+//! Accounting for the objects on the heap, so that runaway allocation can be
+//! stopped before it exhausts the memory of the host (e.g., a browser tab).
+//!
+//! Every object is recorded when it is allocated, along with its approximate
+//! size. Rather than tracking when each object is freed, we periodically prune
+//! the records of objects that have been freed since. The number of records is
+//! therefore an upper bound on the number of live objects; we only prune when
+//! that bound exceeds a limit or has doubled since the last pruning.
+
+use std::sync::{Arc, Weak};
+
+use dada_ir::{error, span::FileSpan};
+use parking_lot::Mutex;
+
+use crate::{
+    data::Data,
+    error::{DiagnosticBuilderExt, ErrorKind},
+    interpreter::Interpreter,
+};
+
+/// Statistics about the objects on the heap.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct HeapStats {
+    /// Number of objects that are live.
+    pub live_objects: usize,
+
+    /// Approximate number of bytes used by the live objects.
+    pub live_bytes: usize,
+
+    /// The most objects that were found to be live at once.
+    pub peak_objects: usize,
+
+    /// The most bytes that were found to be live at once.
+    pub peak_bytes: usize,
+}
+
+pub(crate) struct Heap {
+    max_objects: Option<usize>,
+    max_bytes: Option<usize>,
+    state: Mutex<HeapState>,
+}
+
+#[derive(Default)]
+struct HeapState {
+    /// Each object allocated, with its approximate size, except for
+    /// those that were already freed when we last pruned.
+    objects: Vec<(Weak<Mutex<Data>>, usize)>,
+
+    /// Sum of the sizes in `objects`.
+    bytes: usize,
+
+    /// Number of objects that were live when we last pruned.
+    live_at_last_prune: usize,
+
+    /// The peak counts found so far (the live counts are only exact right after pruning).
+    stats: HeapStats,
+
+    /// Set when an allocation exceeded a limit, to where it happened.
+    exceeded: Option<(FileSpan, Exceeded)>,
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Exceeded {
+    Objects(usize),
+    Bytes(usize),
+}
+
+/// We do not bother to prune until there are at least this many records.
+const MIN_PRUNE_LEN: usize = 1024;
+
+impl Heap {
+    pub(crate) fn new(max_objects: Option<usize>, max_bytes: Option<usize>) -> Self {
+        Self {
+            max_objects,
+            max_bytes,
+            state: Default::default(),
+        }
+    }
+
+    /// Records that `object` was allocated. If that makes the heap exceed
+    /// a limit, the next call to [`Self::check_limits`] fails.
+    pub(crate) fn record_allocation(
+        &self,
+        interpreter: &Interpreter<'_>,
+        object: &Arc<Mutex<Data>>,
+    ) {
+        let size = approximate_size(interpreter, &object.lock());
+        let mut state = self.state.lock();
+        state.objects.push((Arc::downgrade(object), size));
+        state.bytes += size;
+
+        let over_limit = self.exceeded(&state).is_some();
+        if over_limit || state.objects.len() >= MIN_PRUNE_LEN.max(2 * state.live_at_last_prune) {
+            state.prune();
+            if state.exceeded.is_none() {
+                state.exceeded = self
+                    .exceeded(&state)
+                    .map(|exceeded| (interpreter.span_now(), exceeded));
+            }
+        }
+    }
+
+    /// Fails if an allocation has exceeded one of the limits.
+    pub(crate) fn check_limits(&self, interpreter: &Interpreter<'_>) -> eyre::Result<()> {
+        let (span, exceeded) = match self.state.lock().exceeded {
+            Some(exceeded) => exceeded,
+            None => return Ok(()),
+        };
+        let (limit, what) = match exceeded {
+            Exceeded::Objects(limit) => (limit, "objects"),
+            Exceeded::Bytes(limit) => (limit, "bytes"),
+        };
+        Err(
+            error!(span, "out of memory: more than {} {} are live", limit, what)
+                .primary_label("this allocation exceeded the limit")
+                .eyre(interpreter.db(), ErrorKind::ResourceLimit),
+        )
+    }
+
+    /// Returns statistics about the objects that are live now.
+    pub(crate) fn stats(&self) -> HeapStats {
+        let mut state = self.state.lock();
+        state.prune();
+        state.stats
+    }
+
+    fn exceeded(&self, state: &HeapState) -> Option<Exceeded> {
+        match (self.max_objects, self.max_bytes) {
+            (Some(max_objects), _) if state.objects.len() > max_objects => {
+                Some(Exceeded::Objects(max_objects))
+            }
+            (_, Some(max_bytes)) if state.bytes > max_bytes => Some(Exceeded::Bytes(max_bytes)),
+            _ => None,
+        }
+    }
+}
+
+impl HeapState {
+    /// Removes the records of objects that have been freed and updates the statistics.
+    fn prune(&mut self) {
+        self.objects.retain(|(object, _)| object.strong_count() > 0);
+        self.bytes = self.objects.iter().map(|(_, size)| size).sum();
+        self.live_at_last_prune = self.objects.len();
+
+        let stats = &mut self.stats;
+        stats.live_objects = self.objects.len();
+        stats.live_bytes = self.bytes;
+        stats.peak_objects = stats.peak_objects.max(stats.live_objects);
+        stats.peak_bytes = stats.peak_bytes.max(stats.live_bytes);
+    }
+}
+
+/// Approximate number of bytes used by an object holding `data`.
+fn approximate_size(interpreter: &Interpreter<'_>, data: &Data) -> usize {
+    // The allocation holds the reference counts along with the data.
+    let base = std::mem::size_of::<Mutex<Data>>() + 2 * std::mem::size_of::<usize>();
+    let value_size = std::mem::size_of::<crate::value::Value>();
+    base + match data {
+        Data::Instance(instance) => instance.fields.len() * value_size,
+        Data::Tuple(tuple) => tuple.fields.len() * value_size,
+        Data::String(word) => word.as_str(interpreter.db()).len(),
+        _ => 0,
+    }
+}
@@ -18,10 +18,17 @@ use crate::{interpreter::Interpreter, StackFrame};
 
 mod capture;
 mod graphviz;
+mod text;
+
+pub use text::{FrameText, VariableText};
 
 pub struct HeapGraph {
     // 0 is the bottom of the stack, length is the top of the stack.
     stack: Vec<StackFrameNode>,
+
+    /// Writes to atomic storage that were rolled back, oldest first.
+    rolled_back_writes: Vec<RolledBackWriteEdge>,
+
     tables: Tables,
 }
 
@@ -33,10 +40,12 @@ impl HeapGraph {
     ) -> Self {
         let mut this = Self {
             stack: vec![],
+            rolled_back_writes: vec![],
             tables: Default::default(),
         };
         let cache = &mut capture::Cache::default();
         this.capture_from(interpreter, cache, top, place);
+        this.capture_rolled_back_writes(interpreter, cache);
         this
     }
 }
@@ -68,6 +77,17 @@ pub(crate) struct LocalVariableEdge {
     pub(crate) value: ValueEdge,
 }
 
+/// A write to atomic storage that was undone when its transaction rolled back.
+#[derive(Debug)]
+pub(crate) struct RolledBackWriteEdge {
+    pub(crate) span: FileSpan,
+    pub(crate) class: Class,
+    pub(crate) field_name: Word,
+
+    /// The value that was written and then discarded.
+    pub(crate) value: ValueEdge,
+}
+
 id!(pub(crate) struct ObjectNode);
 
 #[derive(Debug)]
@@ -94,6 +114,9 @@ id!(pub(crate) struct DataNode);
 
 #[derive(Debug)]
 pub(crate) struct DataNodeData {
+    /// What kind of data this is (e.g., `integer`).
+    kind: &'static str,
+
     debug: Box<dyn Debug + Send + Sync>,
 }
 
@@ -14,7 +14,8 @@ use crate::{
 
 use super::{
     DataNodeData, HeapGraph, LocalVariableEdge, ObjectNode, ObjectNodeData, PermissionNode,
-    PermissionNodeData, PermissionNodeLabel, StackFrameNodeData, ValueEdge, ValueEdgeTarget,
+    PermissionNodeData, PermissionNodeLabel, RolledBackWriteEdge, StackFrameNodeData, ValueEdge,
+    ValueEdgeTarget,
 };
 
 #[derive(Default)]
@@ -71,6 +72,26 @@ impl HeapGraph {
         self.stack.push(stack_frame);
     }
 
+    /// Captures the writes to atomic storage that have been rolled back so far.
+    pub(super) fn capture_rolled_back_writes(
+        &mut self,
+        interpreter: &Interpreter<'_>,
+        cache: &mut Cache,
+    ) {
+        let db = interpreter.db();
+        interpreter
+            .transactions()
+            .for_each_rolled_back_write(|write| {
+                let value = self.value_node(cache, db, &write.value);
+                self.rolled_back_writes.push(RolledBackWriteEdge {
+                    span: write.span,
+                    class: write.class,
+                    field_name: write.field_name,
+                    value,
+                });
+            });
+    }
+
     fn value_node(&mut self, cache: &mut Cache, db: &dyn crate::Db, value: &Value) -> ValueEdge {
         value.peek(|permission, data| {
             let permission = self.permission_node(cache, db, permission);
@@ -80,15 +101,20 @@ impl HeapGraph {
                 }
                 crate::data::Data::Class(c) => ValueEdgeTarget::Class(*c),
                 crate::data::Data::Function(f) => ValueEdgeTarget::Function(*f),
-                crate::data::Data::Intrinsic(i) => self.data_target(db, &i.as_str(db)),
-                crate::data::Data::Thunk(_thunk) => self.data_target(db, &"<thunk>"), // FIXME
-                crate::data::Data::Tuple(_tuple) => self.data_target(db, &"<tuple>"), // FIXME
-                crate::data::Data::Bool(b) => self.data_target(db, b),
-                crate::data::Data::Uint(v) => self.data_target(db, v),
-                crate::data::Data::Int(i) => self.data_target(db, i),
-                crate::data::Data::Float(f) => self.data_target(db, f),
-                crate::data::Data::String(w) => self.data_target(db, &w.as_str(db).to_string()),
-                crate::data::Data::Unit(u) => self.data_target(db, u),
+                crate::data::Data::Intrinsic(i) => self.data_target(db, "function", &i.as_str(db)),
+                crate::data::Data::Thunk(_thunk) => self.data_target(db, "thunk", &"<thunk>"), // FIXME
+                crate::data::Data::Channel(_channel) => {
+                    self.data_target(db, "channel", &"<channel>")
+                }
+                crate::data::Data::Tuple(_tuple) => self.data_target(db, "tuple", &"<tuple>"), // FIXME
+                crate::data::Data::Bool(b) => self.data_target(db, "boolean", b),
+                crate::data::Data::Uint(v) => self.data_target(db, "unsigned integer", v),
+                crate::data::Data::Int(i) => self.data_target(db, "integer", i),
+                crate::data::Data::Float(f) => self.data_target(db, "float", f),
+                crate::data::Data::String(w) => {
+                    self.data_target(db, "string", &w.as_str(db).to_string())
+                }
+                crate::data::Data::Unit(u) => self.data_target(db, "()", u),
             };
             ValueEdge { permission, target }
         })
@@ -97,9 +123,11 @@ impl HeapGraph {
     fn data_target(
         &mut self,
         _db: &dyn crate::Db,
+        kind: &'static str,
         d: &(impl std::fmt::Debug + Send + Sync + Clone + 'static),
     ) -> ValueEdgeTarget {
         let b = DataNodeData {
+            kind,
             debug: Box::new(d.clone()),
         };
         ValueEdgeTarget::Data(self.tables.add(b))
@@ -112,6 +112,8 @@ impl HeapGraph {
     fn stack_and_heap(&self, w: &mut GraphvizWriter<'_>) -> eyre::Result<()> {
         self.print_stack(w)?;
 
+        self.print_rolled_back_writes(w)?;
+
         self.print_heap(w)?;
 
         let value_edge_list = std::mem::take(&mut w.value_edge_list);
@@ -203,6 +205,38 @@ impl HeapGraph {
         Ok(())
     }
 
+    /// Prints the writes to atomic storage that were rolled back, if any,
+    /// each labeled with the field and the line where the write occurred.
+    fn print_rolled_back_writes(&self, w: &mut GraphvizWriter<'_>) -> eyre::Result<()> {
+        if self.rolled_back_writes.is_empty() {
+            return Ok(());
+        }
+
+        let np = w.name_prefix;
+
+        w.indent(format!("subgraph cluster_{np}rolled_back {{"))?;
+        w.println("label=<<b>rolled back</b>>")?;
+
+        let rolled_back_node_name = format!("{np}rolled_back");
+        w.indent(format!(r#"{rolled_back_node_name}["#))?;
+        w.println(r#"shape="none";"#)?;
+        w.indent(r#"label=<"#)?;
+        w.println(r#"<table border="0">"#)?;
+        for (index, write) in self.rolled_back_writes.iter().enumerate() {
+            let class_name = write.class.name(w.db).as_str(w.db);
+            let field_name = write.field_name.as_str(w.db);
+            let line =
+                dada_ir::lines::line_column(w.db, write.span.filename, write.span.start).line1();
+            let name = format!("{class_name}.{field_name} (line {line})");
+            self.print_field(w, &write.value, Some(&name), &rolled_back_node_name, index)?;
+        }
+        w.println(r#"</table>"#)?;
+        w.undent(r#">;"#)?;
+        w.undent(r#"];"#)?;
+        w.undent("}")?;
+        Ok(())
+    }
+
     fn print_heap(&self, w: &mut GraphvizWriter<'_>) -> eyre::Result<()> {
         while let Some(edge) = w.node_queue.pop() {
             self.print_heap_node(w, edge)?;
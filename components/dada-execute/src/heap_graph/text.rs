@@ -0,0 +1,389 @@
+//! Renders a heap graph as plain text, for use in terminals (e.g., `dada debug`)
+//! and in debuggers (e.g., `dada dap`).
+
+use dada_collections::Map;
+use dada_id::InternKey;
+use dada_ir::span::FileSpan;
+use dada_parse::prelude::*;
+
+use super::{HeapGraph, ObjectNode, PermissionNode, StackFrameNode, ValueEdge, ValueEdgeTarget};
+
+/// A stack frame of a heap graph, described as text.
+pub struct FrameText {
+    pub function_name: String,
+
+    /// Where execution is within the frame.
+    pub span: FileSpan,
+
+    /// The named local variables.
+    pub variables: Vec<VariableText>,
+}
+
+/// A local variable or a field of a heap graph, described as text.
+pub struct VariableText {
+    pub name: String,
+
+    /// The permission and the value (e.g., `my Point#0` or `our 22`).
+    pub value: String,
+
+    /// If the value is an object, its index, to pass to [`HeapGraph::fields_text`].
+    pub object: Option<usize>,
+}
+
+impl HeapGraph {
+    /// Describes each stack frame, topmost first.
+    pub fn frames_text(&self, db: &dyn crate::Db) -> Vec<FrameText> {
+        let objects = self.reachable_objects();
+        self.stack
+            .iter()
+            .rev()
+            .map(|stack_frame| {
+                let data = stack_frame.data(&self.tables);
+                let variables = data
+                    .variables
+                    .iter()
+                    .filter_map(|variable| {
+                        let name = variable.name?.as_str(db).to_string();
+                        Some(self.variable_text(db, &objects, name, &variable.value))
+                    })
+                    .collect();
+                FrameText {
+                    function_name: data.function.name(db).as_str(db).to_string(),
+                    span: data.span,
+                    variables,
+                }
+            })
+            .collect()
+    }
+
+    /// Describes the fields of the object with the given index
+    /// (see [`VariableText::object`]).
+    pub fn fields_text(&self, db: &dyn crate::Db, object: usize) -> Vec<VariableText> {
+        let objects = self.reachable_objects();
+        let data = match objects.get(object) {
+            Some(object) => object.data(&self.tables),
+            None => return vec![],
+        };
+        data.class
+            .fields(db)
+            .iter()
+            .zip(&data.fields)
+            .map(|(field, value)| {
+                let name = field.name(db).as_str(db).to_string();
+                self.variable_text(db, &objects, name, value)
+            })
+            .collect()
+    }
+
+    fn variable_text(
+        &self,
+        db: &dyn crate::Db,
+        objects: &[ObjectNode],
+        name: String,
+        value: &ValueEdge,
+    ) -> VariableText {
+        let object = match value.target {
+            ValueEdgeTarget::Object(object) => objects.iter().position(|o| *o == object),
+            _ => None,
+        };
+        VariableText {
+            name,
+            value: self.value_text(db, objects, value),
+            object,
+        }
+    }
+
+    /// Describes the named local variables of the topmost stack frame, one per line
+    /// (e.g., `p: my Point#0`).
+    pub fn locals_text(&self, db: &dyn crate::Db) -> String {
+        let mut output = String::new();
+        if let Some(top) = self.stack.last() {
+            let objects = self.reachable_objects();
+            self.write_variables(db, &objects, &mut output, *top, "");
+        }
+        output
+    }
+
+    /// Describes the entire stack (topmost frame first) and every object reachable from it.
+    /// Objects are numbered (e.g., `Point#0`) so that aliases can be recognized.
+    pub fn text(&self, db: &dyn crate::Db) -> String {
+        let objects = self.reachable_objects();
+        let mut output = String::from("stack:\n");
+        for &stack_frame in self.stack.iter().rev() {
+            let data = stack_frame.data(&self.tables);
+            let start = dada_ir::lines::line_column(db, data.span.filename, data.span.start);
+            output.push_str(&format!(
+                "  {} at {}:{}:{}\n",
+                data.function.name(db).as_str(db),
+                data.span.filename.as_str(db),
+                start.line1(),
+                start.column1(),
+            ));
+            self.write_variables(db, &objects, &mut output, stack_frame, "    ");
+            if let Some(in_flight_value) = &data.in_flight_value {
+                output.push_str(&format!(
+                    "    (in-flight): {}\n",
+                    self.value_text(db, &objects, in_flight_value)
+                ));
+            }
+        }
+
+        if !objects.is_empty() {
+            output.push_str("heap:\n");
+            for &object in &objects {
+                let data = object.data(&self.tables);
+                let fields = data
+                    .class
+                    .fields(db)
+                    .iter()
+                    .zip(&data.fields)
+                    .map(|(field, value)| {
+                        format!(
+                            "{}: {}",
+                            field.name(db).as_str(db),
+                            self.value_text(db, &objects, value)
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                output.push_str(&format!(
+                    "  {} = {}({})\n",
+                    self.object_name(db, &objects, object),
+                    data.class.name(db).as_str(db),
+                    fields.join(", "),
+                ));
+            }
+        }
+
+        output
+    }
+
+    /// Describes the permission of the local variable `name` in the topmost
+    /// stack frame, including who it is leased or shared to (or from).
+    /// Returns `None` if there is no such variable.
+    pub fn permission_text(&self, db: &dyn crate::Db, name: &str) -> Option<String> {
+        let top = self.stack.last()?.data(&self.tables);
+        let variable = top
+            .variables
+            .iter()
+            .find(|v| v.name.map(|w| w.as_str(db)) == Some(name))?;
+
+        let places = self.permission_places(db);
+        let describe_places = |permission: PermissionNode| {
+            let mut names = places.get(&permission).cloned().unwrap_or_default();
+            names.retain(|n| n != name);
+            if names.is_empty() {
+                "a temporary".to_string()
+            } else {
+                names
+                    .iter()
+                    .map(|n| format!("`{}`", n))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }
+        };
+
+        let permission = variable.value.permission.data(&self.tables);
+        let mut text = format!("`{}` is {}", name, permission.label.as_str());
+        if let Some(lessor) = permission.lessor {
+            text.push_str(&format!(", from {}", describe_places(lessor)));
+        }
+        if let Some(tenant) = permission.tenant {
+            let tenant_label = tenant.data(&self.tables).label.as_str();
+            text.push_str(&format!(
+                ", and is {} to {}",
+                tenant_label,
+                describe_places(tenant)
+            ));
+        }
+        Some(text)
+    }
+
+    /// Describes the in-flight value of the topmost stack frame (e.g., the value of the
+    /// expression that a breakpoint stopped at), including the fields of objects
+    /// (e.g., `my Point(x: our 22, y: our 44)`). Returns `None` if there is no
+    /// in-flight value or it is `()`.
+    pub fn in_flight_text(&self, db: &dyn crate::Db) -> Option<String> {
+        let value = self.in_flight_value()?;
+        if let ValueEdgeTarget::Data(data) = value.target {
+            if data.data(&self.tables).kind == "()" {
+                return None;
+            }
+        }
+        let objects = self.reachable_objects();
+        let mut expanded = vec![];
+        Some(self.expanded_value_text(db, &objects, &mut expanded, &value))
+    }
+
+    /// Describes the permission and the kind of the in-flight value of the topmost
+    /// stack frame (e.g., `my Point` or `our integer`), if there is one.
+    pub fn in_flight_type_text(&self, db: &dyn crate::Db) -> Option<String> {
+        let value = self.in_flight_value()?;
+        let permission = value.permission.data(&self.tables).label.as_str();
+        let kind = match value.target {
+            ValueEdgeTarget::Object(object) => object
+                .data(&self.tables)
+                .class
+                .name(db)
+                .as_str(db)
+                .to_string(),
+            ValueEdgeTarget::Class(_) => "class".to_string(),
+            ValueEdgeTarget::Function(_) => "function".to_string(),
+            ValueEdgeTarget::Data(data) => data.data(&self.tables).kind.to_string(),
+        };
+        Some(format!("{} {}", permission, kind))
+    }
+
+    fn in_flight_value(&self) -> Option<ValueEdge> {
+        self.stack.last()?.data(&self.tables).in_flight_value
+    }
+
+    /// Like [`Self::value_text`], but describes objects by their fields. Objects that
+    /// have already been expanded (e.g., because of a cycle) are only named.
+    fn expanded_value_text(
+        &self,
+        db: &dyn crate::Db,
+        objects: &[ObjectNode],
+        expanded: &mut Vec<ObjectNode>,
+        value: &ValueEdge,
+    ) -> String {
+        let object = match value.target {
+            ValueEdgeTarget::Object(object) if !expanded.contains(&object) => object,
+            _ => return self.value_text(db, objects, value),
+        };
+        expanded.push(object);
+
+        let data = object.data(&self.tables);
+        let fields = data
+            .class
+            .fields(db)
+            .iter()
+            .zip(&data.fields)
+            .map(|(field, value)| {
+                format!(
+                    "{}: {}",
+                    field.name(db).as_str(db),
+                    self.expanded_value_text(db, objects, expanded, value)
+                )
+            })
+            .collect::<Vec<_>>();
+        format!(
+            "{} {}({})",
+            value.permission.data(&self.tables).label.as_str(),
+            data.class.name(db).as_str(db),
+            fields.join(", ")
+        )
+    }
+
+    fn write_variables(
+        &self,
+        db: &dyn crate::Db,
+        objects: &[ObjectNode],
+        output: &mut String,
+        stack_frame: StackFrameNode,
+        indent: &str,
+    ) {
+        for variable in &stack_frame.data(&self.tables).variables {
+            if let Some(name) = variable.name {
+                output.push_str(&format!(
+                    "{}{}: {}\n",
+                    indent,
+                    name.as_str(db),
+                    self.value_text(db, objects, &variable.value)
+                ));
+            }
+        }
+    }
+
+    /// Describes a value, e.g. `my Point#0` or `our 22`.
+    fn value_text(&self, db: &dyn crate::Db, objects: &[ObjectNode], value: &ValueEdge) -> String {
+        let permission = value.permission.data(&self.tables).label.as_str();
+        let target = match value.target {
+            ValueEdgeTarget::Object(object) => self.object_name(db, objects, object),
+            ValueEdgeTarget::Class(class) => class.name(db).as_str(db).to_string(),
+            ValueEdgeTarget::Function(function) => {
+                format!("{}()", function.name(db).as_str(db))
+            }
+            ValueEdgeTarget::Data(data) => format!("{:?}", data.data(&self.tables).debug),
+        };
+        format!("{} {}", permission, target)
+    }
+
+    /// Names `object` by its class and its index in `objects` (e.g., `Point#0`).
+    fn object_name(
+        &self,
+        db: &dyn crate::Db,
+        objects: &[ObjectNode],
+        object: ObjectNode,
+    ) -> String {
+        let class = object.data(&self.tables).class;
+        let index = objects.iter().position(|o| *o == object).unwrap();
+        format!("{}#{}", class.name(db).as_str(db), index)
+    }
+
+    /// All the objects reachable from the stack, in the order that they are first reached.
+    fn reachable_objects(&self) -> Vec<ObjectNode> {
+        let mut objects = vec![];
+        let mut queue: Vec<&ValueEdge> = self
+            .stack
+            .iter()
+            .flat_map(|s| {
+                let data = s.data(&self.tables);
+                data.variables
+                    .iter()
+                    .map(|v| &v.value)
+                    .chain(&data.in_flight_value)
+            })
+            .chain(self.rolled_back_writes.iter().map(|w| &w.value))
+            .rev()
+            .collect();
+        while let Some(value) = queue.pop() {
+            if let ValueEdgeTarget::Object(object) = value.target {
+                if !objects.contains(&object) {
+                    objects.push(object);
+                    queue.extend(object.data(&self.tables).fields.iter().rev());
+                }
+            }
+        }
+        objects
+    }
+
+    /// Maps each permission to the named places that hold it (e.g., `p` or `p.x`).
+    /// Variables in frames other than the topmost one are qualified by their function (e.g., `main::p`).
+    fn permission_places(&self, db: &dyn crate::Db) -> Map<PermissionNode, Vec<String>> {
+        let mut places: Map<PermissionNode, Vec<String>> = Map::default();
+        let mut visited = vec![];
+        let mut queue = vec![];
+        for (index, stack_frame) in self.stack.iter().enumerate().rev() {
+            let data = stack_frame.data(&self.tables);
+            for variable in &data.variables {
+                if let Some(name) = variable.name {
+                    let name = if index + 1 == self.stack.len() {
+                        name.as_str(db).to_string()
+                    } else {
+                        format!("{}::{}", data.function.name(db).as_str(db), name.as_str(db))
+                    };
+                    queue.push((name, variable.value));
+                }
+            }
+        }
+        queue.reverse();
+
+        while let Some((place, value)) = queue.pop() {
+            places
+                .entry(value.permission)
+                .or_default()
+                .push(place.clone());
+            if let ValueEdgeTarget::Object(object) = value.target {
+                if visited.contains(&object) {
+                    continue;
+                }
+                visited.push(object);
+                let data = object.data(&self.tables);
+                for (field, value) in data.class.fields(db).iter().zip(&data.fields).rev() {
+                    queue.push((format!("{}.{}", place, field.name(db).as_str(db)), *value));
+                }
+            }
+        }
+        places
+    }
+}
@@ -0,0 +1,92 @@
+//! The history of an execution, recorded so that a debugger can go back to
+//! earlier moments and inspect the state of the program there (see
+//! [`Kernel::records_history`](crate::kernel::Kernel::records_history)).
+//!
+//! A snapshot of the stack and heap is taken at every step, keyed by the
+//! interpreter's clock. Snapshots are large, so the history only keeps the
+//! most recent ones, up to a limit.
+
+use std::collections::VecDeque;
+
+use dada_ir::span::FileSpan;
+
+use crate::heap_graph::HeapGraph;
+
+/// The state of the program at a step of execution, just before the step executes.
+pub struct Snapshot {
+    /// The clock tick of the step; increases with every step.
+    pub clock: u64,
+
+    /// The span of the code that the step executes.
+    pub span: FileSpan,
+
+    /// The stack and heap of the task executing the step.
+    pub heap_graph: HeapGraph,
+}
+
+pub struct History {
+    /// The most recent snapshots, oldest first.
+    snapshots: VecDeque<Snapshot>,
+
+    /// The most snapshots to keep; older ones are discarded.
+    max_snapshots: usize,
+
+    /// Number of snapshots discarded so far.
+    discarded: usize,
+}
+
+impl History {
+    pub fn new(max_snapshots: usize) -> Self {
+        Self {
+            snapshots: VecDeque::new(),
+            max_snapshots,
+            discarded: 0,
+        }
+    }
+
+    /// Records a snapshot, which must come after all those recorded so far.
+    pub fn push(&mut self, snapshot: Snapshot) {
+        if let Some(last) = self.snapshots.back() {
+            assert!(last.clock < snapshot.clock);
+        }
+        if self.snapshots.len() == self.max_snapshots {
+            self.snapshots.pop_front();
+            self.discarded += 1;
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    /// Number of snapshots kept.
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// Number of snapshots discarded because there were more than the limit.
+    pub fn discarded(&self) -> usize {
+        self.discarded
+    }
+
+    /// The snapshot with the given index, 0 being the oldest kept.
+    pub fn get(&self, index: usize) -> Option<&Snapshot> {
+        self.snapshots.get(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Snapshot> {
+        self.snapshots.iter()
+    }
+
+    /// The index of the latest snapshot taken at or before `clock`, i.e. the state
+    /// of the program at that moment. Returns `None` if that snapshot was discarded
+    /// (or `clock` is before execution started).
+    pub fn index_at(&self, clock: u64) -> Option<usize> {
+        let after = self.snapshots.partition_point(|s| s.clock <= clock);
+        if after == 0 {
+            return None;
+        }
+        Some(after - 1)
+    }
+}
@@ -1,9 +1,22 @@
+use std::sync::Arc;
+
 use crossbeam::atomic::AtomicCell;
-use dada_collections::IndexVec;
-use dada_ir::span::FileSpan;
+use dada_collections::{IndexVec, Map};
+use dada_ir::{error, span::FileSpan};
 use parking_lot::Mutex;
 
-use crate::{kernel::Kernel, moment::Moment};
+use crate::{
+    error::{DiagnosticBuilderExt, ErrorKind},
+    execution::Control,
+    heap::Heap,
+    kernel::Kernel,
+    moment::Moment,
+    scheduler::{Scheduler, Task},
+    transaction::{Conflict, Transactions},
+};
+
+/// Number of clock ticks between checks of the kernel's deadline.
+const DEADLINE_CHECK_INTERVAL: u64 = 1024;
 
 pub(crate) struct Interpreter<'me> {
     db: &'me dyn crate::Db,
@@ -13,13 +26,41 @@ pub(crate) struct Interpreter<'me> {
     /// clock tick: increases monotonically
     clock: AtomicCell<u64>,
 
+    /// if set, execution stops when the clock passes this value
+    max_steps: Option<u64>,
+
+    /// if true, the state of the program is recorded at every clock tick
+    /// (see [`Kernel::records_history`])
+    records_history: bool,
+
+    /// if set, execution is driven by an [`Execution`](crate::execution::Execution),
+    /// which may pause it
+    control: Option<Arc<Control>>,
+
     /// span of current clock tick
     span: AtomicCell<FileSpan>,
 
+    /// span of the place (e.g., `p.x`) being accessed during the current clock tick, if any
+    place_span: AtomicCell<Option<FileSpan>>,
+
     /// recorded moments in history: occur at significant events
     /// (e.g., when a permission is canceled) so that we can
     /// go back and report errors if needed
     moments: Mutex<IndexVec<Moment, MomentData>>,
+
+    /// number of atomic sections that each task is currently within
+    /// (tasks that are not within any have no entry)
+    atomic_depths: Mutex<Map<Task, usize>>,
+
+    /// transaction for the outermost atomic section of each task, along with
+    /// the writes that have been rolled back recently
+    transactions: Transactions,
+
+    /// the tasks that are executing concurrently
+    scheduler: Scheduler,
+
+    /// accounting for the objects that have been allocated
+    heap: Heap,
 }
 
 impl<'me> Interpreter<'me> {
@@ -27,13 +68,22 @@ impl<'me> Interpreter<'me> {
         db: &'me dyn crate::Db,
         kernel: &'me dyn Kernel,
         start_span: FileSpan,
+        control: Option<Arc<Control>>,
     ) -> Self {
         Self {
             db,
             kernel,
             clock: Default::default(),
+            max_steps: kernel.max_steps(),
+            records_history: kernel.records_history(),
+            control,
             span: AtomicCell::new(start_span),
+            place_span: Default::default(),
             moments: Default::default(),
+            atomic_depths: Default::default(),
+            transactions: Default::default(),
+            scheduler: Scheduler::new(start_span),
+            heap: Heap::new(kernel.max_objects(), kernel.max_heap_bytes()),
         }
     }
 
@@ -43,9 +93,48 @@ impl<'me> Interpreter<'me> {
 
     /// Advance to the next clock tick, potentially altering the current span
     /// in the process.
-    pub(crate) fn tick_clock(&self, span: FileSpan) {
-        self.clock.fetch_add(1);
+    ///
+    /// Fails if execution has taken more than the maximum number of steps,
+    /// if the kernel's deadline has passed, or if the heap has grown too large.
+    pub(crate) fn tick_clock(&self, span: FileSpan) -> eyre::Result<()> {
+        let clock = self.clock.fetch_add(1) + 1;
         self.span.store(span);
+        self.place_span.store(None);
+
+        if let Some(max_steps) = self.max_steps {
+            if clock > max_steps {
+                return Err(error!(span, "execution took more than {} steps", max_steps)
+                    .primary_label("execution was stopped here")
+                    .eyre(self.db, ErrorKind::ResourceLimit));
+            }
+        }
+
+        self.heap.check_limits(self)?;
+
+        // Checking the deadline may be expensive (e.g., it reads the time),
+        // so only do it every so often.
+        if clock % DEADLINE_CHECK_INTERVAL == 0 && self.kernel.deadline_passed() {
+            return Err(error!(span, "execution did not finish before its deadline")
+                .primary_label("execution was stopped here")
+                .eyre(self.db, ErrorKind::ResourceLimit));
+        }
+
+        Ok(())
+    }
+
+    /// The current clock tick.
+    pub(crate) fn clock_now(&self) -> u64 {
+        self.clock.load()
+    }
+
+    /// True if the state of the program should be recorded at every clock tick.
+    pub(crate) fn records_history(&self) -> bool {
+        self.records_history
+    }
+
+    /// The [`Execution`](crate::execution::Execution) driving this interpreter, if any.
+    pub(crate) fn control(&self) -> Option<&Control> {
+        self.control.as_deref()
     }
 
     /// Return the span at the current moment.
@@ -53,6 +142,17 @@ impl<'me> Interpreter<'me> {
         self.span.load()
     }
 
+    /// Record that the current clock tick is accessing the place at `span`,
+    /// so that permissions canceled by the access can say which place it was.
+    pub(crate) fn access_place(&self, span: FileSpan) {
+        self.place_span.store(Some(span));
+    }
+
+    /// Return the span of the place being accessed at the current moment, if any.
+    pub(crate) fn place_span_now(&self) -> Option<FileSpan> {
+        self.place_span.load()
+    }
+
     /// Record the current moment for posterity.
     pub(crate) fn moment_now(&self) -> Moment {
         let clock = self.clock.load();
@@ -75,6 +175,78 @@ impl<'me> Interpreter<'me> {
         moments[moment].span
     }
 
+    /// Number of atomic sections that the current task is within.
+    pub(crate) fn atomic_depth(&self) -> usize {
+        let task = self.scheduler.current_task();
+        self.atomic_depths.lock().get(&task).copied().unwrap_or(0)
+    }
+
+    /// Record that the current task has entered an atomic section,
+    /// starting a transaction if it is the outermost one.
+    pub(crate) fn start_atomic(&self) {
+        let task = self.scheduler.current_task();
+        let mut atomic_depths = self.atomic_depths.lock();
+        let depth = atomic_depths.entry(task).or_insert(0);
+        *depth += 1;
+        if *depth == 1 {
+            self.transactions.begin(task);
+        }
+    }
+
+    /// Record that the current task has left its innermost atomic section,
+    /// committing the transaction if it was the outermost one.
+    ///
+    /// Fails if the transaction conflicts with another task, in which
+    /// case it has been rolled back.
+    pub(crate) fn end_atomic(&self) -> Result<(), Conflict> {
+        let task = self.scheduler.current_task();
+        let mut atomic_depths = self.atomic_depths.lock();
+        let depth = atomic_depths
+            .get_mut(&task)
+            .expect("end of atomic section that was never started");
+        *depth -= 1;
+        if *depth == 0 {
+            atomic_depths.remove(&task);
+            drop(atomic_depths);
+            self.transactions.commit(task)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Leave atomic sections of the current task until only `depth` remain, because
+    /// execution failed within them. Rolls back the transaction if `depth` is zero.
+    pub(crate) fn abort_atomic(&self, depth: usize) {
+        let task = self.scheduler.current_task();
+        let mut atomic_depths = self.atomic_depths.lock();
+        let old_depth = atomic_depths.get(&task).copied().unwrap_or(0);
+        assert!(old_depth >= depth);
+        if depth > 0 {
+            atomic_depths.insert(task, depth);
+        } else {
+            atomic_depths.remove(&task);
+            drop(atomic_depths);
+            if old_depth > 0 {
+                self.transactions.roll_back(task);
+            }
+        }
+    }
+
+    /// The transactions for atomic sections.
+    pub(crate) fn transactions(&self) -> &Transactions {
+        &self.transactions
+    }
+
+    /// The scheduler for concurrent tasks.
+    pub(crate) fn scheduler(&self) -> &Scheduler {
+        &self.scheduler
+    }
+
+    /// The objects allocated on the heap.
+    pub(crate) fn heap(&self) -> &Heap {
+        &self.heap
+    }
+
     pub(crate) fn kernel(&self) -> &dyn Kernel {
         &*self.kernel
     }
@@ -1,7 +1,13 @@
 use dada_ir::{error, intrinsic::Intrinsic, word::Word};
 use eyre::Context;
 
-use crate::{error::DiagnosticBuilderExt, interpreter::Interpreter, value::Value};
+use crate::{
+    data::Data,
+    error::{DiagnosticBuilderExt, ErrorKind},
+    interpreter::Interpreter,
+    scheduler::check_sendable,
+    value::Value,
+};
 
 pub(crate) type IntrinsicFn = fn(&Interpreter<'_>, Vec<Value>) -> eyre::Result<Value>;
 
@@ -12,16 +18,51 @@ pub(crate) struct IntrinsicDefinition {
 
 impl IntrinsicDefinition {
     pub(crate) fn for_intrinsic(db: &dyn crate::Db, intrinsic: Intrinsic) -> IntrinsicDefinition {
+        let definition = |argument_names: &[&str], function: IntrinsicFn| IntrinsicDefinition {
+            argument_names: argument_names
+                .iter()
+                .map(|name| Word::from(db, name))
+                .collect(),
+            function,
+        };
         match intrinsic {
-            Intrinsic::Print => IntrinsicDefinition {
-                argument_names: vec![Word::from(db, "message")],
-                function: intrinsic_write,
-            },
+            Intrinsic::Print => definition(&["message"], intrinsic_print),
+            Intrinsic::Write => definition(&["message"], intrinsic_write),
+            Intrinsic::Spawn => definition(&["task"], intrinsic_spawn),
+            Intrinsic::Channel => definition(&[], intrinsic_channel),
+            Intrinsic::Send => definition(&["channel", "message"], intrinsic_send),
+            Intrinsic::Receive => definition(&["channel"], intrinsic_receive),
+            Intrinsic::Assert => definition(&["condition", "message"], intrinsic_assert),
+            Intrinsic::Panic => definition(&["message"], intrinsic_panic),
+            Intrinsic::StringLength => definition(&["string"], intrinsic_string_length),
+            Intrinsic::Concat => definition(&["left", "right"], intrinsic_concat),
+            Intrinsic::Substring => definition(&["string", "start", "end"], intrinsic_substring),
+            Intrinsic::Split => definition(&["string", "separator", "index"], intrinsic_split),
+            Intrinsic::Contains => definition(&["string", "substring"], intrinsic_contains),
+            Intrinsic::StringToInteger => definition(&["string"], intrinsic_string_to_integer),
+            Intrinsic::IntegerToString => definition(&["integer"], intrinsic_integer_to_string),
+            Intrinsic::Min => definition(&["left", "right"], intrinsic_min),
+            Intrinsic::Max => definition(&["left", "right"], intrinsic_max),
+            Intrinsic::Abs => definition(&["integer"], intrinsic_abs),
         }
     }
 }
 
-fn intrinsic_write(interpreter: &Interpreter<'_>, mut values: Vec<Value>) -> eyre::Result<Value> {
+/// Returns a thunk that, when awaited, prints a message followed by a newline.
+fn intrinsic_print(interpreter: &Interpreter<'_>, values: Vec<Value>) -> eyre::Result<Value> {
+    print_thunk(interpreter, values, true)
+}
+
+/// Returns a thunk that, when awaited, prints a message without a newline.
+fn intrinsic_write(interpreter: &Interpreter<'_>, values: Vec<Value>) -> eyre::Result<Value> {
+    print_thunk(interpreter, values, false)
+}
+
+fn print_thunk(
+    interpreter: &Interpreter<'_>,
+    mut values: Vec<Value>,
+    newline: bool,
+) -> eyre::Result<Value> {
     Ok(Value::new(
         interpreter,
         thunk!(async move |interpreter, _parent_stack_frame| {
@@ -30,14 +71,279 @@ fn intrinsic_write(interpreter: &Interpreter<'_>, mut values: Vec<Value>) -> eyr
             let message_str = message.as_str(interpreter.db());
             async {
                 interpreter.kernel().print(message_str).await?;
-                interpreter.kernel().print_newline().await
+                if newline {
+                    interpreter.kernel().print_newline().await
+                } else {
+                    Ok(())
+                }
             }
             .await
             .with_context(|| {
                 let span_now = interpreter.span_now();
-                error!(span_now, "error printing `{:?}`", message_str).eyre(interpreter.db())
+                error!(span_now, "error printing `{:?}`", message_str)
+                    .eyre(interpreter.db(), ErrorKind::Other)
             })?;
             Ok(Value::unit(interpreter))
         }),
     ))
 }
+
+/// Spawns the thunk `task` as a concurrent task, returning a handle that,
+/// when awaited, waits for the task to finish and yields its result.
+fn intrinsic_spawn(interpreter: &Interpreter<'_>, mut values: Vec<Value>) -> eyre::Result<Value> {
+    let task = values.pop().unwrap();
+    check_sendable(interpreter, &task, "to a spawned task")?;
+    let thunk = task
+        .prepare_for_await(interpreter)?
+        .into_thunk(interpreter)?;
+    let task = interpreter.scheduler().spawn(thunk, interpreter.span_now());
+    Ok(Value::new(
+        interpreter,
+        thunk!(async move |interpreter, _parent_stack_frame| {
+            let span_now = interpreter.span_now();
+            interpreter.scheduler().join(task, span_now).await
+        }),
+    ))
+}
+
+/// Creates a new channel, which is used with `send` and `receive`
+/// to pass messages between tasks.
+fn intrinsic_channel(interpreter: &Interpreter<'_>, _values: Vec<Value>) -> eyre::Result<Value> {
+    let channel = interpreter.scheduler().new_channel();
+    Ok(Value::new(interpreter, channel))
+}
+
+/// Sends a message on a channel. The message is given to whichever task
+/// receives it, so it must be `my` or `our`. Sending never blocks.
+fn intrinsic_send(interpreter: &Interpreter<'_>, mut values: Vec<Value>) -> eyre::Result<Value> {
+    let message = values.pop().unwrap();
+    let channel = values.pop().unwrap();
+    let channel = channel.read(interpreter, |data| data.to_channel(interpreter))?;
+    check_sendable(interpreter, &message, "to a channel")?;
+    interpreter.scheduler().send(channel, message);
+    Ok(Value::unit(interpreter))
+}
+
+/// Returns a thunk that, when awaited, waits for the next message on a channel.
+fn intrinsic_receive(interpreter: &Interpreter<'_>, mut values: Vec<Value>) -> eyre::Result<Value> {
+    let channel = values.pop().unwrap();
+    let channel = channel.read(interpreter, |data| data.to_channel(interpreter))?;
+    Ok(Value::new(
+        interpreter,
+        thunk!(async move |interpreter, _parent_stack_frame| {
+            let span_now = interpreter.span_now();
+            Ok(interpreter.scheduler().receive(channel, span_now).await)
+        }),
+    ))
+}
+
+/// Fails with `message` if `condition` is false.
+fn intrinsic_assert(interpreter: &Interpreter<'_>, mut values: Vec<Value>) -> eyre::Result<Value> {
+    let message = string_argument(interpreter, values.pop().unwrap())?;
+    let condition = values.pop().unwrap();
+    if !condition.read(interpreter, |data| data.to_bool(interpreter))? {
+        return Err(
+            error!(interpreter.span_now(), "assertion failed: {}", message)
+                .eyre(interpreter.db(), ErrorKind::Panic),
+        );
+    }
+    Ok(Value::unit(interpreter))
+}
+
+/// Fails with `message`.
+fn intrinsic_panic(interpreter: &Interpreter<'_>, mut values: Vec<Value>) -> eyre::Result<Value> {
+    let message = string_argument(interpreter, values.pop().unwrap())?;
+    Err(error!(interpreter.span_now(), "panicked: {}", message)
+        .eyre(interpreter.db(), ErrorKind::Panic))
+}
+
+/// The number of characters in a string.
+fn intrinsic_string_length(
+    interpreter: &Interpreter<'_>,
+    mut values: Vec<Value>,
+) -> eyre::Result<Value> {
+    let string = string_argument(interpreter, values.pop().unwrap())?;
+    Ok(Value::new(interpreter, string.chars().count() as u64))
+}
+
+/// A new string with the characters of `left` followed by those of `right`.
+fn intrinsic_concat(interpreter: &Interpreter<'_>, mut values: Vec<Value>) -> eyre::Result<Value> {
+    let right = string_argument(interpreter, values.pop().unwrap())?;
+    let left = string_argument(interpreter, values.pop().unwrap())?;
+    Ok(string_value(interpreter, format!("{left}{right}")))
+}
+
+/// The characters of a string from index `start` up to (but not including) `end`.
+fn intrinsic_substring(
+    interpreter: &Interpreter<'_>,
+    mut values: Vec<Value>,
+) -> eyre::Result<Value> {
+    let end = integer_argument(interpreter, values.pop().unwrap())?;
+    let start = integer_argument(interpreter, values.pop().unwrap())?;
+    let string = string_argument(interpreter, values.pop().unwrap())?;
+    let length = string.chars().count() as i128;
+    if start.as_i128() < 0 || start.as_i128() > end.as_i128() || end.as_i128() > length {
+        return Err(error!(
+            interpreter.span_now(),
+            "cannot take the substring from {} to {} of a string of length {}", start, end, length
+        )
+        .eyre(interpreter.db(), ErrorKind::Other));
+    }
+    let substring: String = string
+        .chars()
+        .skip(start.as_i128() as usize)
+        .take((end.as_i128() - start.as_i128()) as usize)
+        .collect();
+    Ok(string_value(interpreter, substring))
+}
+
+/// Splits a string into pieces wherever `separator` occurs and returns the piece
+/// at `index` (counting from 0). Dada does not have lists yet, so there is no
+/// way to return all of the pieces at once.
+fn intrinsic_split(interpreter: &Interpreter<'_>, mut values: Vec<Value>) -> eyre::Result<Value> {
+    let index = integer_argument(interpreter, values.pop().unwrap())?;
+    let separator = string_argument(interpreter, values.pop().unwrap())?;
+    let string = string_argument(interpreter, values.pop().unwrap())?;
+    if separator.is_empty() {
+        return Err(
+            error!(interpreter.span_now(), "cannot split on an empty separator")
+                .eyre(interpreter.db(), ErrorKind::Other),
+        );
+    }
+    let pieces: Vec<&str> = string.split(&separator).collect();
+    let piece = usize::try_from(index.as_i128())
+        .ok()
+        .and_then(|index| pieces.get(index));
+    match piece {
+        Some(piece) => Ok(string_value(interpreter, piece.to_string())),
+        None => Err(error!(
+            interpreter.span_now(),
+            "cannot take piece {} of a string split into {} pieces",
+            index,
+            pieces.len()
+        )
+        .eyre(interpreter.db(), ErrorKind::Other)),
+    }
+}
+
+/// True if `substring` occurs in `string`.
+fn intrinsic_contains(
+    interpreter: &Interpreter<'_>,
+    mut values: Vec<Value>,
+) -> eyre::Result<Value> {
+    let substring = string_argument(interpreter, values.pop().unwrap())?;
+    let string = string_argument(interpreter, values.pop().unwrap())?;
+    Ok(Value::new(interpreter, string.contains(&substring)))
+}
+
+/// Parses a string like `22` or `-22` into an integer.
+fn intrinsic_string_to_integer(
+    interpreter: &Interpreter<'_>,
+    mut values: Vec<Value>,
+) -> eyre::Result<Value> {
+    let string = string_argument(interpreter, values.pop().unwrap())?;
+    let integer = match (string.parse::<u64>(), string.parse::<i64>()) {
+        (Ok(value), _) => Integer::Unsigned(value),
+        (Err(_), Ok(value)) => Integer::Signed(value),
+        (Err(_), Err(_)) => {
+            return Err(error!(
+                interpreter.span_now(),
+                "cannot convert the string `{}` to an integer", string
+            )
+            .eyre(interpreter.db(), ErrorKind::Other))
+        }
+    };
+    Ok(integer.into_value(interpreter))
+}
+
+/// Formats an integer as a string, e.g. for printing.
+fn intrinsic_integer_to_string(
+    interpreter: &Interpreter<'_>,
+    mut values: Vec<Value>,
+) -> eyre::Result<Value> {
+    let integer = integer_argument(interpreter, values.pop().unwrap())?;
+    Ok(string_value(interpreter, integer.to_string()))
+}
+
+/// Returns the smaller of two integers.
+fn intrinsic_min(interpreter: &Interpreter<'_>, mut values: Vec<Value>) -> eyre::Result<Value> {
+    let right = integer_argument(interpreter, values.pop().unwrap())?;
+    let left = integer_argument(interpreter, values.pop().unwrap())?;
+    let min = if left.as_i128() <= right.as_i128() {
+        left
+    } else {
+        right
+    };
+    Ok(min.into_value(interpreter))
+}
+
+/// Returns the larger of two integers.
+fn intrinsic_max(interpreter: &Interpreter<'_>, mut values: Vec<Value>) -> eyre::Result<Value> {
+    let right = integer_argument(interpreter, values.pop().unwrap())?;
+    let left = integer_argument(interpreter, values.pop().unwrap())?;
+    let max = if left.as_i128() >= right.as_i128() {
+        left
+    } else {
+        right
+    };
+    Ok(max.into_value(interpreter))
+}
+
+/// Returns the absolute value of an integer, which is always unsigned.
+fn intrinsic_abs(interpreter: &Interpreter<'_>, mut values: Vec<Value>) -> eyre::Result<Value> {
+    let abs = match integer_argument(interpreter, values.pop().unwrap())? {
+        Integer::Unsigned(value) => value,
+        Integer::Signed(value) => value.unsigned_abs(),
+    };
+    Ok(Value::new(interpreter, abs))
+}
+
+fn string_argument(interpreter: &Interpreter<'_>, value: Value) -> eyre::Result<String> {
+    let word = value.read(interpreter, |data| data.to_word(interpreter))?;
+    Ok(word.as_str(interpreter.db()).to_string())
+}
+
+fn string_value(interpreter: &Interpreter<'_>, string: String) -> Value {
+    Value::new(interpreter, Word::from(interpreter.db(), string))
+}
+
+/// An integer argument, which may be signed or unsigned.
+#[derive(Copy, Clone, Debug)]
+enum Integer {
+    Unsigned(u64),
+    Signed(i64),
+}
+
+impl Integer {
+    /// Every unsigned and signed integer fits in an `i128`, so they can be compared.
+    fn as_i128(self) -> i128 {
+        match self {
+            Integer::Unsigned(value) => value.into(),
+            Integer::Signed(value) => value.into(),
+        }
+    }
+
+    fn into_value(self, interpreter: &Interpreter<'_>) -> Value {
+        match self {
+            Integer::Unsigned(value) => Value::new(interpreter, value),
+            Integer::Signed(value) => Value::new(interpreter, value),
+        }
+    }
+}
+
+impl std::fmt::Display for Integer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Integer::Unsigned(value) => write!(f, "{value}"),
+            Integer::Signed(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+fn integer_argument(interpreter: &Interpreter<'_>, value: Value) -> eyre::Result<Integer> {
+    value.read(interpreter, |data| match data {
+        Data::Uint(value) => Ok(Integer::Unsigned(*value)),
+        Data::Int(value) => Ok(Integer::Signed(*value)),
+        _ => Err(data.expected(interpreter, "an integer")),
+    })
+}
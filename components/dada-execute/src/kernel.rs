@@ -1,14 +1,20 @@
 //! The "kernel" is the interface from the interpreter to the outside world.
 
-use dada_ir::{filename::Filename, function::Function, span::FileSpan};
+use dada_ir::{code::bir::Bir, filename::Filename, function::Function, span::FileSpan};
 use parking_lot::Mutex;
 use salsa::DebugWithDb;
 
-use crate::{heap_graph::HeapGraph, value::Value};
+use crate::{
+    heap::HeapStats,
+    heap_graph::HeapGraph,
+    history::{History, Snapshot},
+    value::Value,
+};
 
 #[async_trait::async_trait]
 pub trait Kernel: Send + Sync {
-    /// Implementation for the `print` intrinsic, that prints a line of text.
+    /// Implementation for the `print` and `write` intrinsics, that print text
+    /// (`print` follows it with [`Self::print_newline`]).
     async fn print(&self, text: &str) -> eyre::Result<()>;
 
     /// Prints a newline.
@@ -34,6 +40,145 @@ pub trait Kernel: Send + Sync {
         breakpoint_span: FileSpan,
         generate_heap_graph: &dyn Fn() -> HeapGraph,
     ) -> eyre::Result<()>;
+
+    /// Chooses which of the runnable tasks (always more than one) runs next,
+    /// returning an index less than `_num_choices`. Choice 0 continues in
+    /// round-robin order, so that is the default.
+    fn choose_task(&self, _num_choices: usize) -> usize {
+        0
+    }
+
+    /// If this returns a value, execution stops with an error once it has
+    /// taken more than that many steps (see `Interpreter::tick_clock`).
+    fn max_steps(&self) -> Option<u64> {
+        None
+    }
+
+    /// Returns true if execution should stop because it has run for too long.
+    /// This is checked periodically, not on every step.
+    fn deadline_passed(&self) -> bool {
+        false
+    }
+
+    /// If this returns a value, execution stops with an out-of-memory error
+    /// once more than that many objects are live.
+    fn max_objects(&self) -> Option<usize> {
+        None
+    }
+
+    /// If this returns a value, execution stops with an out-of-memory error
+    /// once the live objects take up more than (approximately) that many bytes.
+    fn max_heap_bytes(&self) -> Option<usize> {
+        None
+    }
+
+    /// Invoked when execution finishes, with statistics about the heap.
+    fn report_heap_stats(&self, _stats: HeapStats) {}
+
+    /// If this returns true, the state of the program is captured at every step
+    /// and passed to [`Self::record_step`], so that a debugger can go back to any
+    /// earlier moment. This is expensive, so it is off by default.
+    fn records_history(&self) -> bool {
+        false
+    }
+
+    /// Invoked at every step if [`Self::records_history`] returns true,
+    /// with the state of the program just before the step executes.
+    fn record_step(&self, _db: &dyn crate::Db, _snapshot: Snapshot) {}
+}
+
+/// Determines the choices made by a [`TaskChooser`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Schedule {
+    /// Always run the tasks in round-robin order.
+    #[default]
+    RoundRobin,
+
+    /// Make pseudo-random choices, determined by the given seed.
+    Seeded(u64),
+
+    /// Make the given choices, in order, and then continue round-robin.
+    /// A choice that is out of range wraps around.
+    Replay(Vec<usize>),
+}
+
+impl Schedule {
+    /// Parses a replayable schedule in the format produced by [`Self::replay_string`],
+    /// e.g. `0.2.1`.
+    pub fn parse_replay(text: &str) -> eyre::Result<Schedule> {
+        let choices = text
+            .split('.')
+            .filter(|choice| !choice.is_empty())
+            .map(|choice| {
+                choice
+                    .parse()
+                    .map_err(|_| eyre::eyre!("invalid choice `{choice}` in schedule `{text}`"))
+            })
+            .collect::<eyre::Result<Vec<usize>>>()?;
+        Ok(Schedule::Replay(choices))
+    }
+
+    /// Formats `choices` so that [`Self::parse_replay`] can read them back.
+    /// Trailing zeroes are left out, since round-robin is the default anyway.
+    pub fn replay_string(choices: &[usize]) -> String {
+        let len = choices.len() - choices.iter().rev().take_while(|&&c| c == 0).count();
+        if len == 0 {
+            return "0".to_string();
+        }
+        choices[..len]
+            .iter()
+            .map(|choice| choice.to_string())
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+}
+
+/// Chooses tasks according to a [`Schedule`] and records each choice made,
+/// so that kernels can implement [`Kernel::choose_task`] deterministically.
+#[derive(Debug, Default)]
+pub struct TaskChooser {
+    schedule: Schedule,
+
+    /// State of the pseudo-random number generator for [`Schedule::Seeded`].
+    rng_state: u64,
+
+    /// Each choice made so far, along with the number of options it had.
+    choices: Vec<(usize, usize)>,
+}
+
+impl TaskChooser {
+    pub fn new(schedule: Schedule) -> Self {
+        let rng_state = match schedule {
+            Schedule::Seeded(seed) => seed,
+            _ => 0,
+        };
+        Self {
+            schedule,
+            rng_state,
+            choices: vec![],
+        }
+    }
+
+    pub fn choose(&mut self, num_choices: usize) -> usize {
+        let choice = match &self.schedule {
+            Schedule::RoundRobin => 0,
+            Schedule::Seeded(_) => (self.next_random() % num_choices as u64) as usize,
+            Schedule::Replay(choices) => choices
+                .get(self.choices.len())
+                .map_or(0, |choice| choice % num_choices),
+        };
+        self.choices.push((choice, num_choices));
+        choice
+    }
+
+    /// Advances the generator; this is splitmix64.
+    fn next_random(&mut self) -> u64 {
+        self.rng_state = self.rng_state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.rng_state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
 }
 
 #[derive(Default)]
@@ -48,6 +193,27 @@ pub struct BufferKernel {
     /// When we end a breakpoint, we construct a `BreakpointHeapGraph` and
     /// either invoke `breakpoint_callback` or else buffer it here.
     heap_graphs: Mutex<Vec<BreakpointRecord>>,
+
+    /// Chooses which task runs next.
+    task_chooser: Mutex<TaskChooser>,
+
+    /// See [`Kernel::max_steps`].
+    max_steps: Option<u64>,
+
+    /// Returns true once the deadline has passed; see [`Kernel::deadline_passed`].
+    deadline: Option<DeadlineCallback>,
+
+    /// See [`Kernel::max_objects`].
+    max_objects: Option<usize>,
+
+    /// See [`Kernel::max_heap_bytes`].
+    max_heap_bytes: Option<usize>,
+
+    /// Statistics about the heap when execution finished.
+    heap_stats: Mutex<Option<HeapStats>>,
+
+    /// If set, the recorded history of execution; see [`Kernel::records_history`].
+    history: Option<Mutex<History>>,
 }
 
 pub struct BreakpointRecord {
@@ -68,6 +234,8 @@ impl BreakpointRecord {
 type BreakpointCallback =
     Box<dyn Fn(&dyn crate::Db, &BufferKernel, BreakpointRecord) + Send + Sync>;
 
+type DeadlineCallback = Box<dyn Fn() -> bool + Send + Sync>;
+
 impl BufferKernel {
     pub fn new() -> Self {
         Self::default()
@@ -96,6 +264,62 @@ impl BufferKernel {
         }
     }
 
+    /// Builder method: choose which task runs next according to `schedule`
+    /// (by default, round-robin).
+    pub fn schedule(self, schedule: Schedule) -> Self {
+        Self {
+            task_chooser: Mutex::new(TaskChooser::new(schedule)),
+            ..self
+        }
+    }
+
+    /// Builder method: stop execution with an error once it has taken
+    /// more than `max_steps` steps.
+    pub fn max_steps(self, max_steps: u64) -> Self {
+        Self {
+            max_steps: Some(max_steps),
+            ..self
+        }
+    }
+
+    /// Builder method: stop execution with an error once `deadline_passed`
+    /// returns true. It is invoked periodically during execution; taking a
+    /// callback lets the caller decide how to read the time, since
+    /// `std::time::Instant` is not available on every platform (e.g., the web).
+    pub fn deadline(self, deadline_passed: impl Fn() -> bool + Send + Sync + 'static) -> Self {
+        Self {
+            deadline: Some(Box::new(deadline_passed)),
+            ..self
+        }
+    }
+
+    /// Builder method: stop execution with an out-of-memory error once
+    /// more than `max_objects` objects are live.
+    pub fn max_objects(self, max_objects: usize) -> Self {
+        Self {
+            max_objects: Some(max_objects),
+            ..self
+        }
+    }
+
+    /// Builder method: stop execution with an out-of-memory error once the live
+    /// objects take up more than (approximately) `max_heap_bytes` bytes.
+    pub fn max_heap_bytes(self, max_heap_bytes: usize) -> Self {
+        Self {
+            max_heap_bytes: Some(max_heap_bytes),
+            ..self
+        }
+    }
+
+    /// Builder method: record the state of the program at every step, keeping
+    /// the most recent `max_snapshots` (see [`Self::take_history`]).
+    pub fn record_history(self, max_snapshots: usize) -> Self {
+        Self {
+            history: Some(Mutex::new(History::new(max_snapshots))),
+            ..self
+        }
+    }
+
     pub async fn interpret(
         &self,
         db: &dyn crate::Db,
@@ -119,12 +343,45 @@ impl BufferKernel {
         }
     }
 
+    /// Like [`Self::interpret_and_buffer`], but executes the given bir for `function`
+    /// (e.g., bir parsed from text) instead of the bir brewed from its source.
+    pub async fn interpret_bir_and_buffer(
+        &self,
+        db: &dyn crate::Db,
+        function: Function,
+        bir: Bir,
+        arguments: Vec<Value>,
+    ) {
+        match crate::interpret_bir(function, bir, db, self, arguments).await {
+            Ok(()) => {}
+            Err(e) => {
+                self.append(&e.to_string());
+            }
+        }
+    }
+
     /// Take the recorded data from breakpoints that triggered.
     /// This vec will be empty if there is a breakpoint callback set.
     pub fn take_recorded_breakpoints(&mut self) -> Vec<BreakpointRecord> {
         std::mem::take(self.heap_graphs.get_mut())
     }
 
+    /// Take the choices made by the scheduler, each along with the number of
+    /// options it had.
+    pub fn take_task_choices(&mut self) -> Vec<(usize, usize)> {
+        std::mem::take(&mut self.task_chooser.get_mut().choices)
+    }
+
+    /// Take the recorded history of execution, if [`Self::record_history`] was used.
+    pub fn take_history(&mut self) -> Option<History> {
+        self.history.take().map(Mutex::into_inner)
+    }
+
+    /// Statistics about the heap when execution last finished, if it has.
+    pub fn heap_stats(&self) -> Option<HeapStats> {
+        *self.heap_stats.lock()
+    }
+
     /// Convert the buffer into the output
     pub fn take_buffer(&mut self) -> String {
         std::mem::take(self.buffer.get_mut())
@@ -202,4 +459,40 @@ impl Kernel for BufferKernel {
 
         Ok(())
     }
+
+    fn choose_task(&self, num_choices: usize) -> usize {
+        self.task_chooser.lock().choose(num_choices)
+    }
+
+    fn max_steps(&self) -> Option<u64> {
+        self.max_steps
+    }
+
+    fn max_objects(&self) -> Option<usize> {
+        self.max_objects
+    }
+
+    fn max_heap_bytes(&self) -> Option<usize> {
+        self.max_heap_bytes
+    }
+
+    fn report_heap_stats(&self, stats: HeapStats) {
+        *self.heap_stats.lock() = Some(stats);
+    }
+
+    fn deadline_passed(&self) -> bool {
+        self.deadline
+            .as_ref()
+            .is_some_and(|deadline_passed| deadline_passed())
+    }
+
+    fn records_history(&self) -> bool {
+        self.history.is_some()
+    }
+
+    fn record_step(&self, _db: &dyn crate::Db, snapshot: Snapshot) {
+        if let Some(history) = &self.history {
+            history.lock().push(snapshot);
+        }
+    }
 }
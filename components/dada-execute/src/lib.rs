@@ -21,16 +21,24 @@ mod macros;
 mod data;
 mod error;
 mod execute;
+pub mod execution;
 mod ext;
+mod heap;
 pub mod heap_graph;
+pub mod history;
 mod interpreter;
 mod intrinsic;
 pub mod kernel;
+pub mod model_check;
 mod moment;
 mod permission;
-mod poll_once;
+mod scheduler;
 mod thunk;
+mod transaction;
 mod value;
 
+pub use error::{DiagnosticError, ErrorKind};
 pub use execute::interpret;
+pub use execute::interpret_bir;
 pub use execute::StackFrame;
+pub use heap::HeapStats;
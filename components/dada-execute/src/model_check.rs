@@ -0,0 +1,164 @@
+//! Model checking of concurrent programs.
+//!
+//! Tasks only switch at `.await` points, and whenever more than one task
+//! could run next the kernel chooses which one (see [`crate::scheduler`]).
+//! A *schedule* is the sequence of those choices. The model checker first
+//! runs the program round-robin, which is the baseline, and then explores
+//! the alternative schedules breadth-first, so that schedules that deviate
+//! from round-robin fewer times are explored first. It reports each schedule
+//! that produces an error or an output different from the baseline, along
+//! with a replay string that reproduces it (see [`Schedule::parse_replay`]).
+
+use std::collections::VecDeque;
+
+use dada_ir::function::Function;
+
+use crate::kernel::{BufferKernel, Schedule};
+
+/// The result of [`model_check`].
+pub struct ModelCheckReport {
+    /// Number of schedules that were executed.
+    pub schedules_explored: usize,
+
+    /// True if every possible schedule was explored; false if we stopped at the bound.
+    pub exhaustive: bool,
+
+    /// What the program printed when executed round-robin.
+    pub baseline_output: String,
+
+    /// Schedules that produced an error or a different output. When several
+    /// schedules produce the same output, only the first one is reported.
+    pub failures: Vec<ScheduleFailure>,
+}
+
+pub struct ScheduleFailure {
+    /// The choices made, in the format of [`Schedule::replay_string`].
+    pub schedule: String,
+
+    pub kind: ScheduleFailureKind,
+
+    /// What the program printed, including the error (if any).
+    pub output: String,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ScheduleFailureKind {
+    /// Execution stopped with an error, e.g., a permission error.
+    Error,
+
+    /// Execution finished, but printed something different from the baseline.
+    DifferentOutput,
+}
+
+impl ModelCheckReport {
+    /// True if no schedule produced an error or a different output.
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl std::fmt::Display for ModelCheckReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bound = if self.exhaustive {
+            "all of them"
+        } else {
+            "stopped at the bound"
+        };
+        writeln!(
+            f,
+            "explored {} schedules ({bound})",
+            self.schedules_explored
+        )?;
+        if self.failures.is_empty() {
+            return writeln!(f, "no schedule produced an error or a different output");
+        }
+
+        writeln!(f, "round-robin output:")?;
+        write_indented(f, &self.baseline_output)?;
+        for failure in &self.failures {
+            let what = match failure.kind {
+                ScheduleFailureKind::Error => "an error",
+                ScheduleFailureKind::DifferentOutput => "a different output",
+            };
+            writeln!(f, "schedule `{}` produced {what}:", failure.schedule)?;
+            write_indented(f, &failure.output)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_indented(f: &mut std::fmt::Formatter<'_>, text: &str) -> std::fmt::Result {
+    for line in text.lines() {
+        writeln!(f, "    {line}")?;
+    }
+    Ok(())
+}
+
+/// Executes `function` under each possible schedule, up to `max_schedules` of them,
+/// and reports the ones that produce an error or an output different from round-robin.
+pub async fn model_check(
+    db: &dyn crate::Db,
+    function: Function,
+    max_schedules: usize,
+) -> ModelCheckReport {
+    // Each entry is a prefix of choices; the choices after it are 0 (round-robin).
+    let mut pending: VecDeque<Vec<usize>> = VecDeque::new();
+    pending.push_back(vec![]);
+
+    let mut baseline_output = None;
+    let mut failures: Vec<ScheduleFailure> = vec![];
+    let mut schedules_explored = 0;
+    while schedules_explored < max_schedules {
+        let prefix = match pending.pop_front() {
+            Some(prefix) => prefix,
+            None => break,
+        };
+
+        let mut kernel = BufferKernel::new().schedule(Schedule::Replay(prefix.clone()));
+        let result = kernel.interpret(db, function, vec![]).await;
+        if let Err(e) = &result {
+            kernel.append(&e.to_string());
+        }
+        let output = kernel.take_buffer();
+        let task_choices = kernel.take_task_choices();
+        schedules_explored += 1;
+
+        // Every choice after the prefix was 0; each alternative to one of them is
+        // the start of schedules that we have not explored yet.
+        for (index, &(_, num_choices)) in task_choices.iter().enumerate().skip(prefix.len()) {
+            for alternative in 1..num_choices {
+                let mut choices: Vec<usize> = task_choices[..index]
+                    .iter()
+                    .map(|&(choice, _)| choice)
+                    .collect();
+                choices.push(alternative);
+                pending.push_back(choices);
+            }
+        }
+
+        let baseline = baseline_output.get_or_insert_with(|| output.clone());
+        let kind = if result.is_err() {
+            ScheduleFailureKind::Error
+        } else if output != *baseline {
+            ScheduleFailureKind::DifferentOutput
+        } else {
+            continue;
+        };
+        if failures.iter().any(|failure| failure.output == output) {
+            continue;
+        }
+        let choices: Vec<usize> = task_choices.iter().map(|&(choice, _)| choice).collect();
+        failures.push(ScheduleFailure {
+            schedule: Schedule::replay_string(&choices),
+            kind,
+            output,
+        });
+    }
+
+    ModelCheckReport {
+        schedules_explored,
+        exhaustive: pending.is_empty(),
+        baseline_output: baseline_output.unwrap_or_default(),
+        failures,
+    }
+}
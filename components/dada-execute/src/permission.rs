@@ -7,6 +7,8 @@ mod our;
 mod shared;
 mod tenant;
 
+use dada_ir::storage_mode::Joint;
+
 use crate::interpreter::Interpreter;
 
 #[derive(Debug)]
@@ -65,6 +67,15 @@ impl Permission {
         self.data.is_valid()
     }
 
+    /// Is this permission joint, meaning that the data may also be accessed through aliases?
+    pub(crate) fn joint(&self) -> Joint {
+        if self.data.exclusive() {
+            Joint::No
+        } else {
+            Joint::Yes
+        }
+    }
+
     /// Checks that this permission permits reading of a field.
     pub(crate) fn perform_read(&self, interpreter: &Interpreter<'_>) -> eyre::Result<()> {
         self.data.perform_read(interpreter)
@@ -179,11 +190,15 @@ impl PermissionData {
         }
     }
 
-    /// See [`Permission::cancel`]
-    fn cancel(&self, interpreter: &Interpreter<'_>) -> eyre::Result<()> {
+    /// Cancels this permission because of `action` on the permission it was leased or shared from.
+    fn cancel(
+        &self,
+        interpreter: &Interpreter<'_>,
+        action: invalidated::Action,
+    ) -> eyre::Result<()> {
         match self {
-            PermissionData::Leased(p) => p.cancel(interpreter),
-            PermissionData::Shared(p) => p.cancel(interpreter),
+            PermissionData::Leased(p) => p.cancel(interpreter, action),
+            PermissionData::Shared(p) => p.cancel(interpreter, action),
             PermissionData::My(_) | PermissionData::Our(_) => {
                 unreachable!("cannot cancel an owned permission")
             }
@@ -1,33 +1,147 @@
 use crossbeam::atomic::AtomicCell;
-use dada_ir::error;
+use dada_ir::{error, span::FileSpan};
 
-use crate::{error::DiagnosticBuilderExt, interpreter::Interpreter, moment::Moment};
+use crate::{
+    error::{DiagnosticBuilderExt, ErrorKind},
+    interpreter::Interpreter,
+    moment::Moment,
+};
 
-#[derive(Default, Debug)]
+#[derive(Debug)]
 pub(super) struct Invalidated {
-    /// Has this permision been canceled? (if so, when)
-    invalidated: AtomicCell<Option<Moment>>,
+    /// What kind of permission is invalidated; determines how errors are reported.
+    kind: InvalidatedKind,
+
+    /// Has this permision been invalidated? (if so, when and why)
+    invalidated: AtomicCell<Option<Cause>>,
+}
+
+#[derive(Copy, Clone, Debug)]
+enum InvalidatedKind {
+    /// An owned permission, invalidated when it is given away.
+    Given,
+
+    /// A leased permission, granted at the given moment and invalidated
+    /// when it is canceled by its owner.
+    Lease(Moment),
+
+    /// A shared permission, granted at the given moment and invalidated
+    /// when it is canceled by its owner.
+    Share(Moment),
+}
+
+/// Why a permission was invalidated.
+#[derive(Copy, Clone, Debug)]
+struct Cause {
+    moment: Moment,
+    action: Action,
+
+    /// The place that was accessed, if known.
+    place: Option<FileSpan>,
+}
+
+/// An action on a permission that may invalidate it or cancel its tenant.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(super) enum Action {
+    Read,
+    Write,
+    Lease,
+    Share,
+    Give,
+    Await,
 }
 
 impl Invalidated {
-    pub(super) fn invalidate(&self, interpreter: &Interpreter<'_>) -> eyre::Result<()> {
+    /// Creates the state of an owned permission.
+    pub(super) fn given() -> Self {
+        Self::new(InvalidatedKind::Given)
+    }
+
+    /// Creates the state of a leased permission granted at `granted`.
+    pub(super) fn lease(granted: Moment) -> Self {
+        Self::new(InvalidatedKind::Lease(granted))
+    }
+
+    /// Creates the state of a shared permission granted at `granted`.
+    pub(super) fn share(granted: Moment) -> Self {
+        Self::new(InvalidatedKind::Share(granted))
+    }
+
+    fn new(kind: InvalidatedKind) -> Self {
+        Self {
+            kind,
+            invalidated: Default::default(),
+        }
+    }
+
+    /// Invalidates the permission, recording that `action` at the current moment did so.
+    pub(super) fn invalidate(
+        &self,
+        interpreter: &Interpreter<'_>,
+        action: Action,
+    ) -> eyre::Result<()> {
         self.check_still_valid(interpreter)?;
-        self.invalidated.store(Some(interpreter.moment_now()));
+        self.invalidated.store(Some(Cause {
+            moment: interpreter.moment_now(),
+            action,
+            place: interpreter.place_span_now(),
+        }));
         Ok(())
     }
 
     pub(super) fn check_still_valid(&self, interpreter: &Interpreter<'_>) -> eyre::Result<()> {
-        if let Some(previous_moment) = self.invalidated.load() {
-            let span_now = interpreter.span_now();
-            let span_then = interpreter.span(previous_moment);
-            return Err(error!(span_now, "permission already given")
-                .secondary_label(span_then, "permission given here")
-                .eyre(interpreter.db()));
-        }
-        Ok(())
+        let cause = match self.invalidated.load() {
+            Some(cause) => cause,
+            None => return Ok(()),
+        };
+
+        let db = interpreter.db();
+        let span_now = interpreter.span_now();
+        let span_then = interpreter.span(cause.moment);
+        let builder = match self.kind {
+            InvalidatedKind::Given => error!(span_now, "permission already given")
+                .primary_label("access attempted here")
+                .secondary_label(
+                    span_then,
+                    format!("permission given here by {}", cause.describe(db)),
+                ),
+            InvalidatedKind::Lease(granted) => error!(span_now, "lease was cancelled")
+                .primary_label("access attempted here")
+                .secondary_label(interpreter.span(granted), "lease granted here")
+                .secondary_label(
+                    span_then,
+                    format!("cancelled here by {}", cause.describe(db)),
+                ),
+            InvalidatedKind::Share(granted) => error!(span_now, "shared permission was cancelled")
+                .primary_label("access attempted here")
+                .secondary_label(interpreter.span(granted), "permission shared here")
+                .secondary_label(
+                    span_then,
+                    format!("cancelled here by {}", cause.describe(db)),
+                ),
+        };
+        Err(builder.eyre(db, ErrorKind::Permission))
     }
 
     pub(crate) fn is_valid(&self) -> bool {
         self.invalidated.load().is_none()
     }
 }
+
+impl Cause {
+    /// Describes the action, e.g. "write to `p.x`", or just "write" if the place is not known.
+    fn describe(&self, db: &dyn crate::Db) -> String {
+        let (action, preposition) = match self.action {
+            Action::Read => ("read", "of"),
+            Action::Write => ("write", "to"),
+            Action::Lease => ("lease", "of"),
+            Action::Share => ("share", "of"),
+            Action::Give => ("give", "of"),
+            Action::Await => ("await", "of"),
+        };
+        match self.place {
+            Some(place) => format!("{} {} `{}`", action, preposition, place.snippet(db)),
+            None => action.to_string(),
+        }
+    }
+}
@@ -1,8 +1,16 @@
 use dada_ir::error;
 
-use crate::{error::DiagnosticBuilderExt, interpreter::Interpreter, moment::Moment};
+use crate::{
+    error::{DiagnosticBuilderExt, ErrorKind},
+    interpreter::Interpreter,
+    moment::Moment,
+};
 
-use super::{invalidated::Invalidated, tenant::Tenant, Permission, PermissionData};
+use super::{
+    invalidated::{Action, Invalidated},
+    tenant::Tenant,
+    Permission, PermissionData,
+};
 
 /// Represents an "Exclusive Lease" (nobody else has access during the lease)
 #[derive(Debug)]
@@ -23,16 +31,18 @@ impl From<Leased> for PermissionData {
 
 impl Leased {
     pub(super) fn new(interpreter: &Interpreter<'_>) -> Self {
+        let granted = interpreter.moment_now();
         Self {
-            granted: interpreter.moment_now(),
-            canceled: Invalidated::default(),
+            granted,
+            canceled: Invalidated::lease(granted),
             tenant: Tenant::default(),
         }
     }
 
-    pub(super) fn cancel(&self, interpreter: &Interpreter<'_>) -> eyre::Result<()> {
-        self.canceled.check_still_valid(interpreter)?;
-        self.tenant.cancel_tenant(interpreter);
+    /// Cancels this lease, along with any sublease, because of `action` by the owner.
+    pub(super) fn cancel(&self, interpreter: &Interpreter<'_>, action: Action) -> eyre::Result<()> {
+        self.canceled.invalidate(interpreter, action)?;
+        self.tenant.cancel_tenant(interpreter, action);
         Ok(())
     }
 
@@ -48,13 +58,14 @@ impl Leased {
 
     pub(super) fn check_read(&self, interpreter: &Interpreter) -> eyre::Result<()> {
         self.canceled.check_still_valid(interpreter)?;
-        self.tenant.cancel_tenant_if_exclusive(interpreter);
+        self.tenant
+            .cancel_tenant_if_exclusive(interpreter, Action::Read);
         Ok(())
     }
 
     pub(super) fn check_write(&self, interpreter: &Interpreter) -> eyre::Result<()> {
         self.canceled.check_still_valid(interpreter)?;
-        self.tenant.cancel_tenant(interpreter);
+        self.tenant.cancel_tenant(interpreter, Action::Write);
         Ok(())
     }
 
@@ -63,7 +74,7 @@ impl Leased {
         let span_then = interpreter.span(self.granted);
         Err(error!(span_now, "leased permission does not permit await")
             .secondary_label(span_then, "permission granted here")
-            .eyre(interpreter.db()))
+            .eyre(interpreter.db(), ErrorKind::Permission))
     }
 
     pub(super) fn is_valid(&self) -> bool {
@@ -1,6 +1,10 @@
 use crate::interpreter::Interpreter;
 
-use super::{invalidated::Invalidated, tenant::Tenant, Permission, PermissionData};
+use super::{
+    invalidated::{Action, Invalidated},
+    tenant::Tenant,
+    Permission, PermissionData,
+};
 
 #[derive(Debug)]
 pub(crate) struct My {
@@ -20,13 +24,13 @@ impl From<My> for PermissionData {
 impl My {
     pub(super) fn new(_interpreter: &Interpreter<'_>) -> Self {
         Self {
-            given: Invalidated::default(),
+            given: Invalidated::given(),
             tenant: Tenant::default(),
         }
     }
 
     pub(super) fn give(&self, interpreter: &Interpreter<'_>) -> eyre::Result<Permission> {
-        self.check_owned(interpreter)?;
+        self.check_owned(interpreter, Action::Give)?;
         let permission = Permission::my(interpreter);
         Ok(permission)
     }
@@ -37,31 +41,32 @@ impl My {
     }
 
     pub(super) fn give_share(&self, interpreter: &Interpreter<'_>) -> eyre::Result<Permission> {
-        self.check_owned(interpreter)?;
+        self.check_owned(interpreter, Action::Share)?;
         let permission = Permission::our(interpreter);
         Ok(permission)
     }
 
     pub(super) fn check_read(&self, interpreter: &Interpreter) -> eyre::Result<()> {
         self.given.check_still_valid(interpreter)?;
-        self.tenant.cancel_tenant_if_exclusive(interpreter);
+        self.tenant
+            .cancel_tenant_if_exclusive(interpreter, Action::Read);
         Ok(())
     }
 
     pub(super) fn check_write(&self, interpreter: &Interpreter) -> eyre::Result<()> {
         self.given.check_still_valid(interpreter)?;
-        self.tenant.cancel_tenant(interpreter);
+        self.tenant.cancel_tenant(interpreter, Action::Write);
         Ok(())
     }
 
     pub(crate) fn check_await(&self, interpreter: &Interpreter) -> eyre::Result<()> {
-        self.check_owned(interpreter)
+        self.check_owned(interpreter, Action::Await)
     }
 
-    /// Check that giving ownership of this is ok (and do it).
-    fn check_owned(&self, interpreter: &Interpreter) -> eyre::Result<()> {
-        self.given.invalidate(interpreter)?;
-        self.tenant.cancel_tenant(interpreter);
+    /// Check that giving ownership of this by `action` is ok (and do it).
+    fn check_owned(&self, interpreter: &Interpreter, action: Action) -> eyre::Result<()> {
+        self.given.invalidate(interpreter, action)?;
+        self.tenant.cancel_tenant(interpreter, action);
         Ok(())
     }
 
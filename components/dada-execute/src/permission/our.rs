@@ -1,6 +1,10 @@
 use dada_ir::error;
 
-use crate::{error::DiagnosticBuilderExt, interpreter::Interpreter, moment::Moment};
+use crate::{
+    error::{DiagnosticBuilderExt, ErrorKind},
+    interpreter::Interpreter,
+    moment::Moment,
+};
 
 use super::{Permission, PermissionData};
 
@@ -39,7 +43,7 @@ impl Our {
         let span_then = interpreter.span(self.granted);
         Err(error!(span_now, "shared permission does not permit writes")
             .secondary_label(span_then, "permission granted here")
-            .eyre(interpreter.db()))
+            .eyre(interpreter.db(), ErrorKind::Permission))
     }
 
     pub(crate) fn check_await(&self, interpreter: &Interpreter) -> eyre::Result<()> {
@@ -47,7 +51,7 @@ impl Our {
         let span_then = interpreter.span(self.granted);
         Err(error!(span_now, "shared permission does not permit await")
             .secondary_label(span_then, "permission granted here")
-            .eyre(interpreter.db()))
+            .eyre(interpreter.db(), ErrorKind::Permission))
     }
 
     pub(crate) fn is_valid(&self) -> bool {
@@ -1,8 +1,15 @@
 use dada_ir::error;
 
-use crate::{error::DiagnosticBuilderExt, interpreter::Interpreter, moment::Moment};
+use crate::{
+    error::{DiagnosticBuilderExt, ErrorKind},
+    interpreter::Interpreter,
+    moment::Moment,
+};
 
-use super::{invalidated::Invalidated, Permission, PermissionData};
+use super::{
+    invalidated::{Action, Invalidated},
+    Permission, PermissionData,
+};
 
 #[derive(Debug)]
 pub(crate) struct Shared {
@@ -18,14 +25,16 @@ impl From<Shared> for PermissionData {
 
 impl Shared {
     pub(super) fn new(interpreter: &Interpreter<'_>) -> Self {
+        let granted = interpreter.moment_now();
         Self {
-            granted: interpreter.moment_now(),
-            canceled: Default::default(),
+            granted,
+            canceled: Invalidated::share(granted),
         }
     }
 
-    pub(super) fn cancel(&self, interpreter: &Interpreter<'_>) -> eyre::Result<()> {
-        self.canceled.invalidate(interpreter)?;
+    /// Cancels this permission because of `action` by the owner.
+    pub(super) fn cancel(&self, interpreter: &Interpreter<'_>, action: Action) -> eyre::Result<()> {
+        self.canceled.invalidate(interpreter, action)?;
         Ok(())
     }
 
@@ -47,7 +56,7 @@ impl Shared {
         let span_then = interpreter.span(self.granted);
         Err(error!(span_now, "shared permission does not permit writes")
             .secondary_label(span_then, "permission granted here")
-            .eyre(interpreter.db()))
+            .eyre(interpreter.db(), ErrorKind::Permission))
     }
 
     pub(crate) fn check_await(&self, interpreter: &Interpreter) -> eyre::Result<()> {
@@ -55,7 +64,7 @@ impl Shared {
         let span_then = interpreter.span(self.granted);
         Err(error!(span_now, "shared permission does not permit await")
             .secondary_label(span_then, "permission granted here")
-            .eyre(interpreter.db()))
+            .eyre(interpreter.db(), ErrorKind::Permission))
     }
 
     pub(crate) fn is_valid(&self) -> bool {
@@ -2,7 +2,7 @@ use arc_swap::ArcSwapOption;
 
 use crate::interpreter::Interpreter;
 
-use super::{Permission, PermissionData};
+use super::{invalidated::Action, Permission, PermissionData};
 
 /// Core struct for any unique permission
 #[derive(Default, Debug)]
@@ -13,14 +13,14 @@ pub(crate) struct Tenant {
 
 impl Tenant {
     pub(super) fn lease(&self, interpreter: &Interpreter<'_>) -> Permission {
-        self.cancel_tenant(interpreter);
+        self.cancel_tenant(interpreter, Action::Lease);
         let perm = Permission::leased(interpreter);
         self.tenant.store(Some(perm.data.clone()));
         perm
     }
 
     pub(super) fn share(&self, interpreter: &Interpreter<'_>) -> Permission {
-        self.cancel_tenant_if_exclusive(interpreter);
+        self.cancel_tenant_if_exclusive(interpreter, Action::Share);
 
         // If there is already a tenant, it must be a shared tenant.
         let tenant = self.tenant.load();
@@ -35,19 +35,25 @@ impl Tenant {
         perm
     }
 
-    pub(super) fn cancel_tenant(&self, interpreter: &Interpreter<'_>) {
+    /// Cancels the tenant, if any, because of `action` on this permission.
+    pub(super) fn cancel_tenant(&self, interpreter: &Interpreter<'_>, action: Action) {
         let tenant = self.tenant.load();
         if let Some(tenant) = &*tenant {
-            tenant.cancel(interpreter).expect("failed to cancel tenant");
+            tenant
+                .cancel(interpreter, action)
+                .expect("failed to cancel tenant");
             self.tenant.store(None);
         }
     }
 
-    pub(crate) fn cancel_tenant_if_exclusive(&self, interpreter: &Interpreter) {
+    /// Cancels the tenant, if it is exclusive, because of `action` on this permission.
+    pub(super) fn cancel_tenant_if_exclusive(&self, interpreter: &Interpreter, action: Action) {
         let tenant = self.tenant.load();
         if let Some(tenant) = &*tenant {
             if tenant.exclusive() {
-                tenant.cancel(interpreter).expect("failed to cancel tenant");
+                tenant
+                    .cancel(interpreter, action)
+                    .expect("failed to cancel tenant");
                 self.tenant.store(None);
             }
         }
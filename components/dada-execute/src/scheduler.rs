@@ -0,0 +1,432 @@
+//! Cooperative scheduling of concurrent tasks.
+//!
+//! The `main` function runs as the first task. Each call to the `spawn`
+//! intrinsic creates another task that runs the given thunk. Tasks only
+//! switch at `.await` points: awaiting yields to the other tasks, awaiting
+//! a task handle blocks until that task has finished, and awaiting a
+//! `receive` blocks until the channel has a message. If every task is
+//! blocked, execution stops with a deadlock error.
+//!
+//! Whenever more than one task could run next, the kernel chooses which one
+//! (see [`Kernel::choose_task`]). The choices are numbered in round-robin
+//! order, beginning after the task that ran last, so a kernel that always
+//! chooses 0 runs the tasks round-robin. Since the kernel makes every choice,
+//! execution is deterministic given the same choices, which lets us replay a
+//! schedule or explore the alternatives (see [`crate::model_check`]).
+//!
+//! [`Kernel::choose_task`]: crate::kernel::Kernel::choose_task
+
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use dada_collections::IndexVec;
+use dada_id::id;
+use dada_ir::{error, span::FileSpan};
+use parking_lot::Mutex;
+
+use crate::{
+    data::{DadaFuture, Data},
+    error::{DiagnosticBuilderExt, ErrorKind},
+    interpreter::Interpreter,
+    permission::PermissionData,
+    thunk::Thunk,
+    value::Value,
+};
+
+id!(pub(crate) struct Task);
+
+id!(pub(crate) struct Channel);
+
+pub(crate) struct Scheduler {
+    state: Mutex<SchedulerState>,
+}
+
+struct SchedulerState {
+    tasks: IndexVec<Task, TaskData>,
+
+    /// Messages sent on each channel that have not been received yet.
+    channels: IndexVec<Channel, VecDeque<Value>>,
+
+    /// The task whose future is being polled.
+    current: Task,
+}
+
+struct TaskData {
+    /// Where the task was spawned (for `main`, the name of the function).
+    spawn_span: FileSpan,
+
+    status: TaskStatus,
+}
+
+enum TaskStatus {
+    /// Spawned, but its thunk has not started executing yet.
+    Spawned(Thunk),
+
+    /// Executing; it may be waiting on the outside world (e.g., for printing to finish).
+    Running,
+
+    /// Gave the other tasks a chance to run at an `.await`; it can continue right away.
+    Yielded,
+
+    /// Cannot continue until something happens in another task.
+    Blocked {
+        on: Blocker,
+
+        /// Where the task blocked.
+        span: FileSpan,
+    },
+
+    /// Finished with the given result, which has not been joined yet.
+    Finished(eyre::Result<Value>),
+
+    /// Finished, and the result was taken by joining it.
+    Joined,
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Blocker {
+    /// Waiting for the given task to finish.
+    Join(Task),
+
+    /// Waiting for a message on the given channel.
+    Receive(Channel),
+}
+
+impl Blocker {
+    fn label(self) -> &'static str {
+        match self {
+            Blocker::Join(_) => "this task is waiting for another task to finish",
+            Blocker::Receive(_) => "this task is waiting to receive a message",
+        }
+    }
+}
+
+impl Scheduler {
+    pub(crate) fn new(main_span: FileSpan) -> Self {
+        let mut tasks = IndexVec::new();
+        let main = tasks.push_and_get_key(TaskData {
+            spawn_span: main_span,
+            status: TaskStatus::Running,
+        });
+        Scheduler {
+            state: Mutex::new(SchedulerState {
+                tasks,
+                channels: IndexVec::new(),
+                current: main,
+            }),
+        }
+    }
+
+    /// Creates a new task that will execute `thunk` and returns it.
+    pub(crate) fn spawn(&self, thunk: Thunk, spawn_span: FileSpan) -> Task {
+        self.state.lock().tasks.push_and_get_key(TaskData {
+            spawn_span,
+            status: TaskStatus::Spawned(thunk),
+        })
+    }
+
+    /// The task that is executing.
+    pub(crate) fn current_task(&self) -> Task {
+        self.state.lock().current
+    }
+
+    /// Creates a new channel with no messages.
+    pub(crate) fn new_channel(&self) -> Channel {
+        self.state.lock().channels.push_and_get_key(VecDeque::new())
+    }
+
+    /// Adds `message` to the end of `channel`.
+    pub(crate) fn send(&self, channel: Channel, message: Value) {
+        self.state.lock().channels[channel].push_back(message);
+    }
+
+    /// Waits for a message on `channel` and returns it; `span` is where we wait.
+    pub(crate) fn receive(
+        &self,
+        channel: Channel,
+        span: FileSpan,
+    ) -> impl Future<Output = Value> + '_ {
+        std::future::poll_fn(move |_cx| {
+            let mut state = self.state.lock();
+            match state.channels[channel].pop_front() {
+                Some(message) => Poll::Ready(message),
+                None => {
+                    let current = state.current;
+                    state.tasks[current].status = TaskStatus::Blocked {
+                        on: Blocker::Receive(channel),
+                        span,
+                    };
+                    Poll::Pending
+                }
+            }
+        })
+    }
+
+    /// Gives the other tasks a chance to run before the current one continues.
+    pub(crate) fn yield_now(&self) -> impl Future<Output = ()> + '_ {
+        let mut yielded = false;
+        std::future::poll_fn(move |_cx| {
+            if yielded {
+                return Poll::Ready(());
+            }
+            yielded = true;
+            let mut state = self.state.lock();
+            let current = state.current;
+            state.tasks[current].status = TaskStatus::Yielded;
+            Poll::Pending
+        })
+    }
+
+    /// Waits for `task` to finish and returns its result; `span` is where we wait.
+    pub(crate) fn join(
+        &self,
+        task: Task,
+        span: FileSpan,
+    ) -> impl Future<Output = eyre::Result<Value>> + '_ {
+        std::future::poll_fn(move |_cx| {
+            let mut state = self.state.lock();
+            match std::mem::replace(&mut state.tasks[task].status, TaskStatus::Joined) {
+                TaskStatus::Finished(result) => Poll::Ready(result),
+                TaskStatus::Joined => panic!("task {task:?} joined twice"),
+                status => {
+                    state.tasks[task].status = status;
+                    let current = state.current;
+                    state.tasks[current].status = TaskStatus::Blocked {
+                        on: Blocker::Join(task),
+                        span,
+                    };
+                    Poll::Pending
+                }
+            }
+        })
+    }
+
+    /// The tasks that can make progress, in round-robin order beginning with `first`.
+    fn runnable_tasks(&self, first: usize) -> Vec<Task> {
+        let state = self.state.lock();
+        let num_tasks = state.tasks.len();
+        (0..num_tasks)
+            .map(|offset| Task::from((first + offset) % num_tasks))
+            .filter(|&task| state.is_runnable(task))
+            .collect()
+    }
+
+    /// Marks `task`, which must be runnable, as the current task.
+    fn start_polling(&self, task: Task) {
+        let mut state = self.state.lock();
+        debug_assert!(state.is_runnable(task));
+        state.tasks[task].status = TaskStatus::Running;
+        state.current = task;
+    }
+
+    /// Takes the thunks of tasks that have been spawned but not started.
+    fn take_spawned(&self) -> Vec<(Task, Thunk)> {
+        let mut state = self.state.lock();
+        let mut spawned = vec![];
+        for (task, data) in state.tasks.iter_mut_enumerated() {
+            if let TaskStatus::Spawned(_) = data.status {
+                if let TaskStatus::Spawned(thunk) =
+                    std::mem::replace(&mut data.status, TaskStatus::Running)
+                {
+                    spawned.push((task, thunk));
+                }
+            }
+        }
+        spawned
+    }
+
+    /// True if `task` is still running, i.e., it is waiting on the outside world
+    /// rather than having yielded or blocked on another task.
+    fn status_is_running(&self, task: Task) -> bool {
+        matches!(self.state.lock().tasks[task].status, TaskStatus::Running)
+    }
+
+    fn finish(&self, task: Task, result: eyre::Result<Value>) {
+        self.state.lock().tasks[task].status = TaskStatus::Finished(result);
+    }
+
+    /// Takes the result of `task`, which must have finished and not been joined.
+    fn take_result(&self, task: Task) -> eyre::Result<Value> {
+        match std::mem::replace(
+            &mut self.state.lock().tasks[task].status,
+            TaskStatus::Joined,
+        ) {
+            TaskStatus::Finished(result) => result,
+            _ => panic!("task {task:?} has not finished"),
+        }
+    }
+
+    /// Takes the error of the first task that failed without being joined, if any.
+    fn take_unjoined_error(&self) -> Option<eyre::Report> {
+        let mut state = self.state.lock();
+        for data in state.tasks.iter_mut().skip(1) {
+            if let TaskStatus::Finished(Err(_)) = data.status {
+                if let TaskStatus::Finished(Err(error)) =
+                    std::mem::replace(&mut data.status, TaskStatus::Joined)
+                {
+                    return Some(error);
+                }
+            }
+        }
+        None
+    }
+
+    /// What each blocked task is waiting for, and where.
+    fn blocked_tasks(&self) -> Vec<(Blocker, FileSpan)> {
+        self.state
+            .lock()
+            .tasks
+            .iter()
+            .filter_map(|data| match data.status {
+                TaskStatus::Blocked { on, span } => Some((on, span)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl SchedulerState {
+    fn is_runnable(&self, task: Task) -> bool {
+        match &self.tasks[task].status {
+            TaskStatus::Running | TaskStatus::Yielded => true,
+            TaskStatus::Blocked {
+                on: Blocker::Join(other),
+                ..
+            } => matches!(
+                self.tasks[*other].status,
+                TaskStatus::Finished(_) | TaskStatus::Joined
+            ),
+            TaskStatus::Blocked {
+                on: Blocker::Receive(channel),
+                ..
+            } => !self.channels[*channel].is_empty(),
+            TaskStatus::Spawned(_) | TaskStatus::Finished(_) | TaskStatus::Joined => false,
+        }
+    }
+}
+
+/// Executes `main` as the first task, along with any tasks that it spawns, until all
+/// of them have finished. Returns the result of `main`, or the first error from a
+/// task that was never joined.
+pub(crate) async fn run<'i>(
+    interpreter: &'i Interpreter<'_>,
+    main: DadaFuture<'i>,
+) -> eyre::Result<Value> {
+    let scheduler = interpreter.scheduler();
+    let mut futures: IndexVec<Task, Option<DadaFuture<'i>>> = IndexVec::new();
+    futures.push(Some(main));
+    let main_task = Task::from(0_usize);
+
+    // Where the next round-robin search for a runnable task begins.
+    let mut next_task = 0;
+
+    // A task that was suspended while running (e.g., waiting for the kernel to print,
+    // or paused by an `Execution`); it continues before any other task runs.
+    let mut suspended_task = None;
+
+    std::future::poll_fn(|cx: &mut Context<'_>| loop {
+        for (task, thunk) in scheduler.take_spawned() {
+            assert_eq!(futures.next_key(), task);
+            futures.push(Some(Box::pin(thunk.invoke(interpreter, None))));
+        }
+
+        let runnable_tasks = match suspended_task.take() {
+            Some(task) => vec![task],
+            None => scheduler.runnable_tasks(next_task),
+        };
+        let task = match runnable_tasks.len() {
+            0 => {
+                if futures.iter().all(|future| future.is_none()) {
+                    if let Some(error) = scheduler.take_unjoined_error() {
+                        return Poll::Ready(Err(error));
+                    }
+                    return Poll::Ready(scheduler.take_result(main_task));
+                }
+
+                // Every unfinished task is blocked on another one.
+                return Poll::Ready(Err(deadlock_error(interpreter)));
+            }
+            1 => runnable_tasks[0],
+            num_choices => runnable_tasks[interpreter.kernel().choose_task(num_choices)],
+        };
+
+        scheduler.start_polling(task);
+        next_task = usize::from(task) + 1;
+        let slot = &mut futures[task];
+        let future = slot.as_mut().expect("runnable task has no future");
+        match Pin::new(future).poll(cx) {
+            Poll::Ready(result) => {
+                *slot = None;
+                if task == main_task && result.is_err() {
+                    return Poll::Ready(result);
+                }
+                scheduler.finish(task, result);
+            }
+            Poll::Pending => {
+                if scheduler.status_is_running(task) {
+                    // The task is waiting on the outside world, or was paused; we will
+                    // be polled again when it can continue.
+                    suspended_task = Some(task);
+                    return Poll::Pending;
+                }
+            }
+        }
+    })
+    .await
+}
+
+/// Reports that every task is blocked, pointing at where each one waits.
+fn deadlock_error(interpreter: &Interpreter<'_>) -> eyre::Report {
+    let blocked_tasks = interpreter.scheduler().blocked_tasks();
+    let (first_blocker, first_span) = blocked_tasks[0];
+    let mut diagnostic =
+        error!(first_span, "deadlock: every task is blocked").primary_label(first_blocker.label());
+    for (blocker, span) in &blocked_tasks[1..] {
+        diagnostic = diagnostic.secondary_label(*span, blocker.label());
+    }
+    diagnostic.eyre(interpreter.db(), ErrorKind::Concurrency)
+}
+
+/// Checks that `value` can be given to another task (`destination` says how, e.g.
+/// "to a spawned task"): it, and everything it owns, must be `my` or `our`, so that
+/// the tasks do not share any data except through jointly owned values.
+pub(crate) fn check_sendable(
+    interpreter: &Interpreter<'_>,
+    value: &Value,
+    destination: &str,
+) -> eyre::Result<()> {
+    let kind = value.peek(|permission, data| match permission.peek_data() {
+        PermissionData::My(_) => {
+            let mut owned = vec![];
+            match data {
+                Data::Instance(instance) => owned.extend(instance.fields.iter()),
+                Data::Tuple(tuple) => owned.extend(tuple.fields.iter()),
+                Data::Thunk(thunk) => thunk.for_each_captured_value(&mut |v| owned.push(v)),
+                _ => {}
+            }
+            owned
+                .into_iter()
+                .try_for_each(|v| check_sendable(interpreter, v, destination))
+                .map(|()| None)
+        }
+        PermissionData::Our(_) => Ok(None),
+        PermissionData::Leased(_) => Ok(Some("leased")),
+        PermissionData::Shared(_) => Ok(Some("shared")),
+    })?;
+
+    match kind {
+        None => Ok(()),
+        Some(kind) => Err(error!(
+            interpreter.span_now(),
+            "cannot give a `{}` value {}", kind, destination
+        )
+        .primary_label(format!(
+            "only `my` and `our` values can be given {destination}"
+        ))
+        .eyre(interpreter.db(), ErrorKind::Permission)),
+    }
+}
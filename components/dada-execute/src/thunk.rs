@@ -33,6 +33,12 @@ impl Thunk {
     ) -> eyre::Result<Value> {
         self.object.invoke(interpreter, parent_stack_frame).await
     }
+
+    /// Invokes `op` on each value that this thunk will use when invoked, if known.
+    /// Thunks created from closures do not report the values they capture.
+    pub(crate) fn for_each_captured_value<'a>(&'a self, op: &mut dyn FnMut(&'a Value)) {
+        self.object.for_each_captured_value(op)
+    }
 }
 
 impl std::fmt::Debug for Thunk {
@@ -48,6 +54,8 @@ trait ThunkTrait {
         interpreter: &Interpreter<'_>,
         parent_stack_frame: Option<&StackFrame<'_>>,
     ) -> eyre::Result<Value>;
+
+    fn for_each_captured_value<'a>(&'a self, _op: &mut dyn FnMut(&'a Value)) {}
 }
 
 #[async_trait::async_trait(?Send)]
@@ -76,9 +84,13 @@ impl ThunkTrait for FunctionThunk {
         interpreter: &Interpreter<'_>,
         parent_stack_frame: Option<&StackFrame<'_>>,
     ) -> eyre::Result<Value> {
-        let bir = self.function.brew(interpreter.db());
+        let bir = self.function.brew_for_execution(interpreter.db());
         interpreter
             .execute_bir(self.function, bir, self.arguments, parent_stack_frame)
             .await
     }
+
+    fn for_each_captured_value<'a>(&'a self, op: &mut dyn FnMut(&'a Value)) {
+        self.arguments.iter().for_each(op)
+    }
 }
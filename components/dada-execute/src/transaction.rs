@@ -0,0 +1,247 @@
+//! Software transactional memory for atomic sections.
+//!
+//! While execution is within an atomic section, every write to atomic storage
+//! is logged together with the value that it overwrote, and every atomic
+//! location that is accessed is recorded along with its version. When the
+//! outermost atomic section ends, the transaction commits. If some other task
+//! committed a write to one of the locations in the meantime, the transaction
+//! conflicts and is rolled back instead. A transaction is also rolled back if
+//! execution fails before it ends.
+//!
+//! Each task has its own transaction. Dada code cannot `.await` within an
+//! atomic section, so tasks do not switch there, but hand-written bir can.
+
+use std::{
+    collections::VecDeque,
+    hash::{Hash, Hasher},
+    sync::{Arc, Weak},
+};
+
+use dada_collections::Map;
+use dada_ir::{class::Class, span::FileSpan, word::Word};
+use parking_lot::Mutex;
+
+use crate::{data::Data, scheduler::Task, value::Value};
+
+/// How many rolled back writes we remember (for display in the heap graph);
+/// older ones are forgotten.
+const MAX_ROLLED_BACK_WRITES: usize = 32;
+
+#[derive(Default)]
+pub(crate) struct Transactions {
+    /// Number of committed writes to each atomic location.
+    versions: Mutex<Map<Location, u64>>,
+
+    /// The transaction in progress for each task that is within an atomic section.
+    in_progress: Mutex<Map<Task, Transaction>>,
+
+    /// The most recent writes that were undone by rolling back a transaction, oldest first.
+    rolled_back_writes: Mutex<VecDeque<RolledBackWrite>>,
+}
+
+/// A field of some object.
+///
+/// Objects are identified by address. Holding a `Weak` reference keeps the
+/// allocation alive, so the address cannot be reused by another object while
+/// the location is in use, even if the object itself has been freed.
+#[derive(Clone, Debug)]
+struct Location {
+    object: Weak<Mutex<Data>>,
+
+    /// Index of the field.
+    field: usize,
+}
+
+impl Location {
+    fn new(object: &Arc<Mutex<Data>>, field: usize) -> Self {
+        Location {
+            object: Arc::downgrade(object),
+            field,
+        }
+    }
+}
+
+impl PartialEq for Location {
+    fn eq(&self, other: &Self) -> bool {
+        self.object.ptr_eq(&other.object) && self.field == other.field
+    }
+}
+
+impl Eq for Location {}
+
+impl Hash for Location {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.object.as_ptr().hash(state);
+        self.field.hash(state);
+    }
+}
+
+#[derive(Default)]
+struct Transaction {
+    /// Version of each location when the transaction first accessed it.
+    versions: Map<Location, u64>,
+
+    /// Writes to atomic storage, in the order they occurred.
+    writes: Vec<LoggedWrite>,
+}
+
+struct LoggedWrite {
+    object: Arc<Mutex<Data>>,
+    class: Class,
+    field_index: usize,
+    field_name: Word,
+
+    /// The value overwritten by the write, restored on rollback.
+    old_value: Value,
+
+    /// Where the write occurred.
+    span: FileSpan,
+}
+
+/// A write to atomic storage that was undone when its transaction rolled back.
+pub(crate) struct RolledBackWrite {
+    pub(crate) class: Class,
+    pub(crate) field_name: Word,
+
+    /// Where the write occurred.
+    pub(crate) span: FileSpan,
+
+    /// The value that was written and then discarded.
+    pub(crate) value: Value,
+}
+
+/// Returned when a transaction cannot commit because another task committed
+/// a conflicting write first. The transaction has been rolled back.
+#[derive(Debug)]
+pub(crate) struct Conflict;
+
+impl Transactions {
+    /// Starts a new transaction for `task`; it must not have one in progress.
+    pub(crate) fn begin(&self, task: Task) {
+        let old_transaction = self.in_progress.lock().insert(task, Transaction::default());
+        assert!(
+            old_transaction.is_none(),
+            "transaction already in progress for {task:?}"
+        );
+    }
+
+    /// Records that the transaction in progress for `task` (if any) accessed the given field.
+    pub(crate) fn record_access(&self, task: Task, object: &Arc<Mutex<Data>>, field_index: usize) {
+        if let Some(transaction) = self.in_progress.lock().get_mut(&task) {
+            let location = Location::new(object, field_index);
+            let version = self.version(&location);
+            transaction.versions.entry(location).or_insert(version);
+        }
+    }
+
+    /// Logs a write to atomic storage made by the transaction in progress for `task`.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn record_write(
+        &self,
+        task: Task,
+        object: Arc<Mutex<Data>>,
+        class: Class,
+        field_index: usize,
+        field_name: Word,
+        old_value: Value,
+        span: FileSpan,
+    ) {
+        let location = Location::new(&object, field_index);
+        let version = self.version(&location);
+        let mut in_progress = self.in_progress.lock();
+        let transaction = in_progress
+            .get_mut(&task)
+            .expect("write to atomic storage outside of a transaction");
+        transaction.versions.entry(location).or_insert(version);
+        transaction.writes.push(LoggedWrite {
+            object,
+            class,
+            field_index,
+            field_name,
+            old_value,
+            span,
+        });
+    }
+
+    /// Commits the transaction in progress for `task`, unless some location that it
+    /// accessed was written by another transaction since, in which case it is rolled back.
+    pub(crate) fn commit(&self, task: Task) -> Result<(), Conflict> {
+        let transaction = self.take(task);
+        let idle = self.in_progress.lock().is_empty();
+
+        let mut versions = self.versions.lock();
+        let conflicted = transaction
+            .versions
+            .iter()
+            .any(|(location, version)| versions.get(location).copied().unwrap_or(0) != *version);
+        if conflicted {
+            drop(versions);
+            self.undo(transaction);
+            return Err(Conflict);
+        }
+
+        for write in &transaction.writes {
+            let location = Location::new(&write.object, write.field_index);
+            *versions.entry(location).or_insert(0) += 1;
+        }
+
+        // With no transaction in progress, nobody cares about the versions of
+        // objects that have been freed (and they can never be accessed again).
+        if idle {
+            versions.retain(|location, _| location.object.strong_count() > 0);
+        }
+
+        Ok(())
+    }
+
+    /// Rolls back the transaction in progress for `task`.
+    pub(crate) fn roll_back(&self, task: Task) {
+        let transaction = self.take(task);
+        self.undo(transaction);
+    }
+
+    /// Invokes `op` on each of the most recent writes that have been rolled back.
+    pub(crate) fn for_each_rolled_back_write(&self, mut op: impl FnMut(&RolledBackWrite)) {
+        for write in self.rolled_back_writes.lock().iter() {
+            op(write);
+        }
+    }
+
+    fn take(&self, task: Task) -> Transaction {
+        self.in_progress
+            .lock()
+            .remove(&task)
+            .unwrap_or_else(|| panic!("no transaction in progress for {task:?}"))
+    }
+
+    fn version(&self, location: &Location) -> u64 {
+        self.versions.lock().get(location).copied().unwrap_or(0)
+    }
+
+    /// Restores the values overwritten by the transaction, most recent write first.
+    fn undo(&self, transaction: Transaction) {
+        let mut undone = vec![];
+        for write in transaction.writes.into_iter().rev() {
+            let value = match &mut *write.object.lock() {
+                Data::Instance(instance) => {
+                    std::mem::replace(&mut instance.fields[write.field_index], write.old_value)
+                }
+                data => panic!("logged write to a field of {data:?}"),
+            };
+            undone.push(RolledBackWrite {
+                class: write.class,
+                field_name: write.field_name,
+                span: write.span,
+                value,
+            });
+        }
+
+        // We undid the writes in reverse, but report them in the order they occurred.
+        let mut rolled_back_writes = self.rolled_back_writes.lock();
+        rolled_back_writes.extend(undone.into_iter().rev());
+        let excess = rolled_back_writes
+            .len()
+            .saturating_sub(MAX_ROLLED_BACK_WRITES);
+        rolled_back_writes.drain(..excess);
+    }
+}
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use dada_ir::word::Word;
+use dada_ir::{storage_mode::Joint, word::Word};
 use parking_lot::Mutex;
 
 use crate::{data::Data, interpreter::Interpreter, permission::Permission};
@@ -15,7 +15,7 @@ impl Value {
     pub(crate) fn new(interpreter: &Interpreter<'_>, value: impl Into<Data>) -> Value {
         Value {
             permission: Permission::my(interpreter),
-            data: Arc::new(Mutex::new(value.into())),
+            data: Self::allocate(interpreter, value),
         }
     }
 
@@ -28,10 +28,18 @@ impl Value {
     pub(crate) fn our(interpreter: &Interpreter<'_>, value: impl Into<Data>) -> Value {
         Value {
             permission: Permission::our(interpreter),
-            data: Arc::new(Mutex::new(value.into())),
+            data: Self::allocate(interpreter, value),
         }
     }
 
+    /// Allocates a new object on the heap, accounting for it so that
+    /// we can stop execution if the heap grows too large.
+    fn allocate(interpreter: &Interpreter<'_>, value: impl Into<Data>) -> Arc<Mutex<Data>> {
+        let data = Arc::new(Mutex::new(value.into()));
+        interpreter.heap().record_allocation(interpreter, &data);
+        data
+    }
+
     pub(crate) fn unit(interpreter: &Interpreter<'_>) -> Value {
         Value::new(interpreter, ())
     }
@@ -60,6 +68,43 @@ impl Value {
         op(&mut self.data.lock())
     }
 
+    /// Assigns `value` to the atomic field `name` of the data from this value
+    /// from within an atomic section, logging the write so that it can be rolled back.
+    ///
+    /// Atomic storage can be written through a joint permission, so long as it permits reads.
+    pub(crate) fn assign_atomic_field(
+        &self,
+        interpreter: &Interpreter<'_>,
+        name: Word,
+        value: Value,
+    ) -> eyre::Result<()> {
+        match self.permission.joint() {
+            Joint::Yes => self.permission.perform_read(interpreter)?,
+            Joint::No => self.permission.perform_write(interpreter)?,
+        }
+        let (class, index, old_value) = self.data.lock().replace_field(interpreter, name, value)?;
+        interpreter.transactions().record_write(
+            interpreter.scheduler().current_task(),
+            self.data.clone(),
+            class,
+            index,
+            name,
+            old_value,
+            interpreter.span_now(),
+        );
+        Ok(())
+    }
+
+    /// Records that the transaction in progress, if any, accessed the field
+    /// with the given index of the data from this value.
+    pub(crate) fn record_atomic_access(&self, interpreter: &Interpreter<'_>, index: usize) {
+        interpreter.transactions().record_access(
+            interpreter.scheduler().current_task(),
+            &self.data,
+            index,
+        );
+    }
+
     pub(crate) fn field<R>(
         &self,
         interpreter: &Interpreter<'_>,
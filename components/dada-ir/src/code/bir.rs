@@ -13,11 +13,14 @@ use crate::{
     storage_mode::StorageMode,
     word::{SpannedOptionalWord, Word},
 };
+use dada_collections::Set;
 use dada_id::{id, prelude::*, tables};
 use salsa::DebugWithDb;
 
 use super::{syntax, validated, Code};
 
+pub mod text;
+
 salsa::entity2! {
     entity Bir in crate::Jar {
         origin: Code,
@@ -100,6 +103,20 @@ impl BirData {
     pub fn all_basic_blocks(&self) -> impl Iterator<Item = BasicBlock> {
         self.max_basic_block().iter()
     }
+
+    /// Returns the set of basic blocks that can be reached from the
+    /// start block. Any other block can never execute.
+    pub fn reachable_basic_blocks(&self) -> Set<BasicBlock> {
+        let mut reachable = Set::default();
+        let mut stack = vec![self.start_basic_block];
+        while let Some(block) = stack.pop() {
+            if reachable.insert(block) {
+                let terminator = block.data(&self.tables).terminator;
+                stack.extend(terminator.data(&self.tables).successors());
+            }
+        }
+        reachable
+    }
 }
 
 tables! {
@@ -265,6 +282,20 @@ pub enum TerminatorData {
     Panic,
 }
 
+impl TerminatorData {
+    /// Returns the basic blocks that control may flow to next.
+    pub fn successors(&self) -> Vec<BasicBlock> {
+        match self {
+            TerminatorData::Goto(block)
+            | TerminatorData::StartAtomic(block)
+            | TerminatorData::EndAtomic(block)
+            | TerminatorData::Assign(_, _, block) => vec![*block],
+            TerminatorData::If(_, if_true, if_false) => vec![*if_true, *if_false],
+            TerminatorData::Return(_) | TerminatorData::Error | TerminatorData::Panic => vec![],
+        }
+    }
+}
+
 impl DebugWithDb<InIrDb<'_, Bir>> for TerminatorData {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>, db: &InIrDb<'_, Bir>) -> std::fmt::Result {
         match self {
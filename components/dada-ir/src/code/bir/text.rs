@@ -0,0 +1,1005 @@
+//! A human-readable textual form of the bir, loosely modeled on the MIR dumps from rustc.
+//! It looks like this:
+//!
+//! ```text
+//! fn(_0: shared name) {
+//!     let _1: shared;
+//!     let _2: var greeting;
+//!     let _3: shared;
+//!     let _4: shared;
+//!     start bb0;
+//!
+//!     bb0: {
+//!         breakpoint_start 0;
+//!         _1 = "Hello, ";
+//!         _2 = _1 + _0;
+//!         _3 = call intrinsic print(_2) -> bb1;
+//!     }
+//!
+//!     bb1: {
+//!         _4 = await _3 -> bb2;
+//!     }
+//!
+//!     bb2: {
+//!         return _4;
+//!     }
+//! }
+//! ```
+//!
+//! The parameters are listed in the header, the remaining local variables
+//! follow in `let` declarations, and `start` names the block where execution
+//! begins. Local variables and basic blocks must be numbered consecutively,
+//! in the order in which they are declared. Comments start with `//`.
+//!
+//! The format does not record origins: when text is [parsed](`parse`), every
+//! part of the resulting bir is attributed to a single syntax expression
+//! supplied by the caller (typically the root expression of the function).
+//! Concretely, printing and then parsing bir loses:
+//!
+//! * the origins of local variables, which all become temporaries
+//!   (their names and storage modes are kept);
+//! * the expression that completed at a `breakpoint_end`, which becomes the
+//!   caller's expression, so a kernel sees the span of that expression;
+//! * the file that a breakpoint belongs to, which becomes the file of the
+//!   code that the bir is parsed for. Brewed bir only has breakpoints from
+//!   its own file, so this loses nothing in practice.
+//!
+//! [`check_round_trip`] checks that nothing else is lost.
+
+use std::fmt::Write;
+
+use dada_id::prelude::*;
+
+use crate::{
+    code::{validated::op::Op, Code},
+    intrinsic::Intrinsic,
+    item::Item,
+    span::FileSpan,
+    storage_mode::StorageMode,
+    word::{SpannedOptionalWord, Word},
+};
+
+use super::{
+    syntax, validated, BasicBlock, BasicBlockData, Bir, BirData, Expr, ExprData, LocalVariable,
+    LocalVariableData, Origins, Place, PlaceData, StatementData, Tables, TerminatorData,
+    TerminatorExpr,
+};
+
+/// Renders `bir` in the textual format described in the [module docs](self).
+pub fn print(db: &dyn crate::Db, bir: Bir) -> String {
+    let data = bir.data(db);
+    let printer = Printer {
+        db,
+        tables: data.tables(),
+    };
+    let mut output = String::new();
+    printer.print_bir(data, &mut output).unwrap();
+    output
+}
+
+struct Printer<'me> {
+    db: &'me dyn crate::Db,
+    tables: &'me Tables,
+}
+
+impl Printer<'_> {
+    fn print_bir(&self, data: &BirData, out: &mut String) -> std::fmt::Result {
+        write!(out, "fn(")?;
+        for (parameter, i) in data.parameters().zip(0..) {
+            if i > 0 {
+                write!(out, ", ")?;
+            }
+            self.print_local_variable_decl(parameter, out)?;
+        }
+        writeln!(out, ") {{")?;
+
+        for local_variable in data.max_local_variable().iter().skip(data.num_parameters()) {
+            write!(out, "    let ")?;
+            self.print_local_variable_decl(local_variable, out)?;
+            writeln!(out, ";")?;
+        }
+        writeln!(out, "    start {};", self.block(data.start_basic_block))?;
+
+        for basic_block in data.all_basic_blocks() {
+            writeln!(out)?;
+            writeln!(out, "    {}: {{", self.block(basic_block))?;
+            let block_data = basic_block.data(self.tables);
+            for statement in &block_data.statements {
+                write!(out, "        ")?;
+                self.print_statement(statement.data(self.tables), out)?;
+                writeln!(out, ";")?;
+            }
+            write!(out, "        ")?;
+            self.print_terminator(block_data.terminator.data(self.tables), out)?;
+            writeln!(out, ";")?;
+            writeln!(out, "    }}")?;
+        }
+
+        writeln!(out, "}}")
+    }
+
+    fn print_local_variable_decl(
+        &self,
+        local_variable: LocalVariable,
+        out: &mut String,
+    ) -> std::fmt::Result {
+        let data = local_variable.data(self.tables);
+        write!(out, "_{}: {}", u32::from(local_variable), data.storage_mode)?;
+        if let Some(name) = data.name {
+            write!(out, " {}", name.as_str(self.db))?;
+        }
+        Ok(())
+    }
+
+    fn print_statement(&self, data: &StatementData, out: &mut String) -> std::fmt::Result {
+        match data {
+            StatementData::Assign(place, expr) => {
+                self.print_place(*place, out)?;
+                write!(out, " = ")?;
+                self.print_expr(*expr, out)
+            }
+            StatementData::BreakpointStart(_, index) => write!(out, "breakpoint_start {index}"),
+            StatementData::BreakpointEnd(_, index, _, place) => {
+                write!(out, "breakpoint_end {index}")?;
+                if let Some(place) = place {
+                    write!(out, " ")?;
+                    self.print_place(*place, out)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn print_terminator(&self, data: &TerminatorData, out: &mut String) -> std::fmt::Result {
+        match data {
+            TerminatorData::Goto(block) => write!(out, "goto {}", self.block(*block)),
+            TerminatorData::If(condition, if_true, if_false) => {
+                write!(out, "if ")?;
+                self.print_place(*condition, out)?;
+                write!(
+                    out,
+                    " then {} else {}",
+                    self.block(*if_true),
+                    self.block(*if_false)
+                )
+            }
+            TerminatorData::StartAtomic(block) => {
+                write!(out, "start_atomic {}", self.block(*block))
+            }
+            TerminatorData::EndAtomic(block) => write!(out, "end_atomic {}", self.block(*block)),
+            TerminatorData::Return(place) => {
+                write!(out, "return ")?;
+                self.print_place(*place, out)
+            }
+            TerminatorData::Assign(target, expr, next) => {
+                self.print_place(*target, out)?;
+                write!(out, " = ")?;
+                match expr {
+                    TerminatorExpr::Await(thunk) => {
+                        write!(out, "await ")?;
+                        self.print_place(*thunk, out)?;
+                    }
+                    TerminatorExpr::Call {
+                        function,
+                        arguments,
+                        labels,
+                    } => {
+                        write!(out, "call ")?;
+                        self.print_place(*function, out)?;
+                        write!(out, "(")?;
+                        for ((argument, label), i) in arguments.iter().zip(labels).zip(0..) {
+                            if i > 0 {
+                                write!(out, ", ")?;
+                            }
+                            if let Some(label) = label.as_str(self.db) {
+                                write!(out, "{label}: ")?;
+                            }
+                            self.print_place(*argument, out)?;
+                        }
+                        write!(out, ")")?;
+                    }
+                }
+                write!(out, " -> {}", self.block(*next))
+            }
+            TerminatorData::Error => write!(out, "error"),
+            TerminatorData::Panic => write!(out, "panic"),
+        }
+    }
+
+    fn print_expr(&self, expr: Expr, out: &mut String) -> std::fmt::Result {
+        match expr.data(self.tables) {
+            ExprData::BooleanLiteral(b) => write!(out, "{b}"),
+            ExprData::IntegerLiteral(i) => write!(out, "{i}"),
+            ExprData::StringLiteral(w) => write!(out, "{:?}", w.as_str(self.db)),
+            ExprData::GiveShare(place) => {
+                write!(out, "share ")?;
+                self.print_place(*place, out)
+            }
+            ExprData::Lease(place) => {
+                write!(out, "lease ")?;
+                self.print_place(*place, out)
+            }
+            ExprData::Give(place) => {
+                write!(out, "give ")?;
+                self.print_place(*place, out)
+            }
+            ExprData::Unit => write!(out, "()"),
+            ExprData::Tuple(places) => {
+                write!(out, "tuple(")?;
+                for (place, i) in places.iter().zip(0..) {
+                    if i > 0 {
+                        write!(out, ", ")?;
+                    }
+                    self.print_place(*place, out)?;
+                }
+                write!(out, ")")
+            }
+            ExprData::Op(lhs, op, rhs) => {
+                self.print_place(*lhs, out)?;
+                write!(out, " {} ", op.str())?;
+                self.print_place(*rhs, out)
+            }
+            ExprData::Error => write!(out, "error"),
+        }
+    }
+
+    fn print_place(&self, place: Place, out: &mut String) -> std::fmt::Result {
+        match place.data(self.tables) {
+            PlaceData::LocalVariable(lv) => write!(out, "_{}", u32::from(*lv)),
+            PlaceData::Function(function) => {
+                write!(out, "fn {}", function.name(self.db).as_str(self.db))
+            }
+            PlaceData::Class(class) => write!(out, "class {}", class.name(self.db).as_str(self.db)),
+            PlaceData::Intrinsic(intrinsic) => {
+                write!(out, "intrinsic {}", intrinsic.as_str(self.db))
+            }
+            PlaceData::Dot(owner, field) => {
+                self.print_place(*owner, out)?;
+                write!(out, ".{}", field.as_str(self.db))
+            }
+        }
+    }
+
+    fn block(&self, block: BasicBlock) -> String {
+        format!("bb{}", u32::from(block))
+    }
+}
+
+/// An error that occurred while parsing the textual bir.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// 1-based line where the error occurred.
+    pub line: usize,
+
+    /// 1-based column where the error occurred.
+    pub column: usize,
+
+    pub message: String,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses bir in the textual format described in the [module docs](self).
+///
+/// * `code` is the code that the resulting bir is attributed to; breakpoints refer to its file.
+/// * `items` are the items that `fn` and `class` places may refer to.
+/// * `origin` is the syntax expression that every part of the bir originates from.
+/// * `span` is used for the labels of call arguments.
+pub fn parse(
+    db: &dyn crate::Db,
+    code: Code,
+    items: &[Item],
+    origin: syntax::Expr,
+    span: FileSpan,
+    text: &str,
+) -> Result<Bir, ParseError> {
+    let tokens = tokenize(text)?;
+    let mut parser = Parser {
+        db,
+        code,
+        items,
+        origin,
+        span,
+        text,
+        tokens,
+        position: 0,
+        tables: Tables::default(),
+        origins: Origins::default(),
+        block_references: vec![],
+    };
+    let data = parser.parse_bir()?;
+    Ok(Bir::new(db, code, data, parser.origins))
+}
+
+/// Checks that `parsed`, which was parsed from the text printed for `original`,
+/// differs from `original` only in what the format is documented to lose
+/// (see the [module docs](self)). Returns a description of the first other
+/// difference. This does not compare the statements themselves; comparing
+/// the printed text of the two does that.
+pub fn check_round_trip(db: &dyn crate::Db, original: Bir, parsed: Bir) -> Result<(), String> {
+    let (original_data, parsed_data) = (original.data(db), parsed.data(db));
+    let (original_tables, parsed_tables) = (original_data.tables(), parsed_data.tables());
+
+    if original_data.max_local_variable() != parsed_data.max_local_variable() {
+        return Err("the number of local variables changed".to_string());
+    }
+    for local_variable in parsed_data.max_local_variable().iter() {
+        if local_variable.data(original_tables) != local_variable.data(parsed_tables) {
+            return Err(format!("local variable {local_variable:?} changed"));
+        }
+        if !matches!(
+            parsed.origins(db)[local_variable],
+            validated::LocalVariableOrigin::Temporary(_)
+        ) {
+            return Err(format!(
+                "local variable {local_variable:?} was parsed with an origin"
+            ));
+        }
+    }
+
+    let parsed_filename = parsed.code(db).filename(db);
+    for basic_block in original_data.all_basic_blocks() {
+        for &statement in &basic_block.data(original_tables).statements {
+            let filename = match statement.data(original_tables) {
+                StatementData::BreakpointStart(filename, _)
+                | StatementData::BreakpointEnd(filename, _, _, _) => *filename,
+                StatementData::Assign(..) => continue,
+            };
+            if filename != parsed_filename {
+                return Err(format!(
+                    "{basic_block:?} has a breakpoint in `{}`, which is not the file of its code",
+                    filename.as_str(db)
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token<'t> {
+    Identifier(&'t str),
+    Integer(u64),
+    String(String),
+    Punctuation(&'static str),
+    EndOfInput,
+}
+
+impl std::fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Identifier(s) => write!(f, "`{s}`"),
+            Token::Integer(i) => write!(f, "`{i}`"),
+            Token::String(s) => write!(f, "`{s:?}`"),
+            Token::Punctuation(s) => write!(f, "`{s}`"),
+            Token::EndOfInput => write!(f, "end of input"),
+        }
+    }
+}
+
+/// Punctuation, longest first so that e.g. `->` is preferred over `-`.
+const PUNCTUATION: &[&str] = &[
+    "->", "==", "(", ")", "{", "}", ",", ":", ";", ".", "=", "+", "-", "*", "/", "<", ">",
+];
+
+fn tokenize(text: &str) -> Result<Vec<(Token<'_>, usize)>, ParseError> {
+    let mut tokens = vec![];
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(start, ch)) = chars.peek() {
+        if ch.is_whitespace() {
+            chars.next();
+        } else if text[start..].starts_with("//") {
+            for (_, ch) in chars.by_ref() {
+                if ch == '\n' {
+                    break;
+                }
+            }
+        } else if ch.is_alphabetic() || ch == '_' {
+            let mut end = start;
+            while let Some(&(i, ch)) = chars.peek() {
+                if !(ch.is_alphanumeric() || ch == '_') {
+                    break;
+                }
+                end = i + ch.len_utf8();
+                chars.next();
+            }
+            tokens.push((Token::Identifier(&text[start..end]), start));
+        } else if ch.is_ascii_digit() {
+            let mut end = start;
+            while let Some(&(i, ch)) = chars.peek() {
+                if !(ch.is_ascii_digit() || ch == '_') {
+                    break;
+                }
+                end = i + 1;
+                chars.next();
+            }
+            let digits = text[start..end].replace('_', "");
+            let value = digits
+                .parse()
+                .map_err(|_| error_at(text, start, "integer literal is too large"))?;
+            tokens.push((Token::Integer(value), start));
+        } else if ch == '"' {
+            chars.next();
+            let mut value = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, '"')) => break,
+                    Some((i, '\\')) => value.push(unescape(text, i, &mut chars)?),
+                    Some((_, ch)) => value.push(ch),
+                    None => return Err(error_at(text, start, "unterminated string literal")),
+                }
+            }
+            tokens.push((Token::String(value), start));
+        } else if let Some(p) = PUNCTUATION
+            .iter()
+            .copied()
+            .find(|p| text[start..].starts_with(*p))
+        {
+            for _ in 0..p.len() {
+                chars.next();
+            }
+            tokens.push((Token::Punctuation(p), start));
+        } else {
+            return Err(error_at(
+                text,
+                start,
+                format!("unexpected character `{ch}`"),
+            ));
+        }
+    }
+    tokens.push((Token::EndOfInput, text.len()));
+    Ok(tokens)
+}
+
+/// Reads the rest of an escape sequence whose `\` is at `offset`.
+/// Accepts the escapes that `{:?}` produces for strings.
+fn unescape(
+    text: &str,
+    offset: usize,
+    chars: &mut impl Iterator<Item = (usize, char)>,
+) -> Result<char, ParseError> {
+    let invalid = || error_at(text, offset, "invalid escape sequence");
+    match chars.next().ok_or_else(invalid)?.1 {
+        'n' => Ok('\n'),
+        't' => Ok('\t'),
+        'r' => Ok('\r'),
+        '0' => Ok('\0'),
+        '\\' => Ok('\\'),
+        '"' => Ok('"'),
+        '\'' => Ok('\''),
+        'u' => {
+            if chars.next().ok_or_else(invalid)?.1 != '{' {
+                return Err(invalid());
+            }
+            let mut hex = String::new();
+            loop {
+                match chars.next().ok_or_else(invalid)?.1 {
+                    '}' => break,
+                    ch => hex.push(ch),
+                }
+            }
+            u32::from_str_radix(&hex, 16)
+                .ok()
+                .and_then(char::from_u32)
+                .ok_or_else(invalid)
+        }
+        _ => Err(invalid()),
+    }
+}
+
+fn error_at(text: &str, offset: usize, message: impl ToString) -> ParseError {
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let column = before[line_start..].chars().count() + 1;
+    ParseError {
+        line,
+        column,
+        message: message.to_string(),
+    }
+}
+
+/// Keywords that begin a terminator (as opposed to a statement).
+const TERMINATOR_KEYWORDS: &[&str] = &[
+    "goto",
+    "if",
+    "start_atomic",
+    "end_atomic",
+    "return",
+    "error",
+    "panic",
+];
+
+struct Parser<'me> {
+    db: &'me dyn crate::Db,
+    code: Code,
+    items: &'me [Item],
+    origin: syntax::Expr,
+    span: FileSpan,
+    text: &'me str,
+    tokens: Vec<(Token<'me>, usize)>,
+    position: usize,
+    tables: Tables,
+    origins: Origins,
+
+    /// Every block referenced so far, along with the offset of the reference;
+    /// once all blocks are parsed, we check that they exist.
+    block_references: Vec<(BasicBlock, usize)>,
+}
+
+impl<'me> Parser<'me> {
+    fn parse_bir(&mut self) -> Result<BirData, ParseError> {
+        self.expect_keyword("fn")?;
+        self.expect("(")?;
+        let mut num_parameters = 0;
+        if !self.eat(")") {
+            loop {
+                self.parse_local_variable_decl()?;
+                num_parameters += 1;
+                if self.eat(")") {
+                    break;
+                }
+                self.expect(",")?;
+            }
+        }
+        self.expect("{")?;
+
+        while self.eat_keyword("let") {
+            self.parse_local_variable_decl()?;
+            self.expect(";")?;
+        }
+
+        self.expect_keyword("start")?;
+        let start_basic_block = self.parse_block_reference()?;
+        self.expect(";")?;
+
+        while !self.eat("}") {
+            self.parse_basic_block()?;
+        }
+        if self.peek() != &Token::EndOfInput {
+            return Err(self.error(format!("expected end of input, found {}", self.peek())));
+        }
+
+        let max_basic_block = BasicBlock::max_key(&self.tables);
+        for &(block, offset) in &self.block_references {
+            if block >= max_basic_block {
+                return Err(error_at(
+                    self.text,
+                    offset,
+                    format!("no block named `bb{}`", u32::from(block)),
+                ));
+            }
+        }
+
+        Ok(BirData::new(
+            std::mem::take(&mut self.tables),
+            num_parameters,
+            start_basic_block,
+        ))
+    }
+
+    /// Parses `_N: mode [name]`.
+    fn parse_local_variable_decl(&mut self) -> Result<LocalVariable, ParseError> {
+        let expected = LocalVariable::max_key(&self.tables);
+        let local_variable = self.parse_numbered("_", "a local variable")?;
+        if local_variable != u32::from(expected) as usize {
+            return Err(self.previous_error(format!(
+                "expected local variable `_{}`; local variables must be declared in order",
+                u32::from(expected)
+            )));
+        }
+        self.expect(":")?;
+        let storage_mode = match self.peek() {
+            Token::Identifier("shared") => StorageMode::Shared,
+            Token::Identifier("var") => StorageMode::Var,
+            Token::Identifier("atomic") => StorageMode::Atomic,
+            token => {
+                return Err(self.error(format!("expected a storage mode, found {token}")));
+            }
+        };
+        self.advance();
+        let name = match *self.peek() {
+            Token::Identifier(name) => {
+                self.advance();
+                Some(Word::from(self.db, name))
+            }
+            _ => None,
+        };
+        let local_variable = self.tables.add(LocalVariableData { name, storage_mode });
+        self.origins.push(
+            local_variable,
+            validated::LocalVariableOrigin::Temporary(self.origin),
+        );
+        Ok(local_variable)
+    }
+
+    /// Parses `bbN: { statements; terminator; }`.
+    fn parse_basic_block(&mut self) -> Result<(), ParseError> {
+        let expected = BasicBlock::max_key(&self.tables);
+        let block = self.parse_numbered("bb", "a basic block")?;
+        if block != u32::from(expected) as usize {
+            return Err(self.previous_error(format!(
+                "expected block `bb{}`; blocks must be declared in order",
+                u32::from(expected)
+            )));
+        }
+        self.expect(":")?;
+        self.expect("{")?;
+
+        let mut statements = vec![];
+        let terminator = loop {
+            let statement = match *self.peek() {
+                Token::Identifier(keyword) if TERMINATOR_KEYWORDS.contains(&keyword) => {
+                    break self.parse_terminator()?;
+                }
+                Token::Identifier("breakpoint_start") => {
+                    self.advance();
+                    let index = self.parse_integer()?;
+                    StatementData::BreakpointStart(self.code.filename(self.db), index)
+                }
+                Token::Identifier("breakpoint_end") => {
+                    self.advance();
+                    let index = self.parse_integer()?;
+                    let place = if self.peek() == &Token::Punctuation(";") {
+                        None
+                    } else {
+                        Some(self.parse_place()?)
+                    };
+                    StatementData::BreakpointEnd(
+                        self.code.filename(self.db),
+                        index,
+                        self.origin,
+                        place,
+                    )
+                }
+                _ => {
+                    let target = self.parse_place()?;
+                    self.expect("=")?;
+                    if let Some(expr) = self.parse_terminator_expr()? {
+                        self.expect("->")?;
+                        let next = self.parse_block_reference()?;
+                        break TerminatorData::Assign(target, expr, next);
+                    }
+                    let expr = self.parse_expr()?;
+                    StatementData::Assign(target, expr)
+                }
+            };
+            self.expect(";")?;
+            let statement = self.tables.add(statement);
+            self.origins.push(statement, self.origin);
+            statements.push(statement);
+        };
+        self.expect(";")?;
+        self.expect("}")?;
+
+        let terminator = self.tables.add(terminator);
+        self.origins.push(terminator, self.origin);
+        let block = self.tables.add(BasicBlockData {
+            statements,
+            terminator,
+        });
+        self.origins.push(block, self.origin);
+        Ok(())
+    }
+
+    fn parse_terminator(&mut self) -> Result<TerminatorData, ParseError> {
+        let keyword = self.parse_identifier()?;
+        match keyword {
+            "goto" => Ok(TerminatorData::Goto(self.parse_block_reference()?)),
+            "if" => {
+                let condition = self.parse_place()?;
+                self.expect_keyword("then")?;
+                let if_true = self.parse_block_reference()?;
+                self.expect_keyword("else")?;
+                let if_false = self.parse_block_reference()?;
+                Ok(TerminatorData::If(condition, if_true, if_false))
+            }
+            "start_atomic" => Ok(TerminatorData::StartAtomic(self.parse_block_reference()?)),
+            "end_atomic" => Ok(TerminatorData::EndAtomic(self.parse_block_reference()?)),
+            "return" => Ok(TerminatorData::Return(self.parse_place()?)),
+            "error" => Ok(TerminatorData::Error),
+            "panic" => Ok(TerminatorData::Panic),
+            _ => unreachable!("unexpected terminator keyword `{keyword}`"),
+        }
+    }
+
+    /// Parses `await place` or `call place(args)`, if present.
+    fn parse_terminator_expr(&mut self) -> Result<Option<TerminatorExpr>, ParseError> {
+        if self.eat_keyword("await") {
+            return Ok(Some(TerminatorExpr::Await(self.parse_place()?)));
+        }
+
+        if !self.eat_keyword("call") {
+            return Ok(None);
+        }
+        let function = self.parse_place()?;
+        self.expect("(")?;
+        let mut arguments = vec![];
+        let mut labels = vec![];
+        if !self.eat(")") {
+            loop {
+                let label = match (self.peek(), self.peek_next()) {
+                    (&Token::Identifier(label), Token::Punctuation(":")) => Some(label),
+                    _ => None,
+                };
+                let label = label.map(|label| {
+                    self.advance();
+                    self.advance();
+                    Word::from(self.db, label)
+                });
+                labels.push(SpannedOptionalWord::new(self.db, label, self.span));
+                arguments.push(self.parse_place()?);
+                if self.eat(")") {
+                    break;
+                }
+                self.expect(",")?;
+            }
+        }
+        Ok(Some(TerminatorExpr::Call {
+            function,
+            arguments,
+            labels,
+        }))
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, ParseError> {
+        let data = match self.peek().clone() {
+            Token::Identifier("true") => {
+                self.advance();
+                ExprData::BooleanLiteral(true)
+            }
+            Token::Identifier("false") => {
+                self.advance();
+                ExprData::BooleanLiteral(false)
+            }
+            Token::Integer(value) => {
+                self.advance();
+                ExprData::IntegerLiteral(value)
+            }
+            Token::String(value) => {
+                self.advance();
+                ExprData::StringLiteral(Word::from(self.db, value))
+            }
+            Token::Identifier("share") => {
+                self.advance();
+                ExprData::GiveShare(self.parse_place()?)
+            }
+            Token::Identifier("lease") => {
+                self.advance();
+                ExprData::Lease(self.parse_place()?)
+            }
+            Token::Identifier("give") => {
+                self.advance();
+                ExprData::Give(self.parse_place()?)
+            }
+            Token::Punctuation("(") => {
+                self.advance();
+                self.expect(")")?;
+                ExprData::Unit
+            }
+            Token::Identifier("tuple") => {
+                self.advance();
+                self.expect("(")?;
+                let mut places = vec![];
+                if !self.eat(")") {
+                    loop {
+                        places.push(self.parse_place()?);
+                        if self.eat(")") {
+                            break;
+                        }
+                        self.expect(",")?;
+                    }
+                }
+                ExprData::Tuple(places)
+            }
+            Token::Identifier("error") => {
+                self.advance();
+                ExprData::Error
+            }
+            _ => {
+                let lhs = self.parse_place()?;
+                let op = match *self.peek() {
+                    Token::Punctuation(p) => Op::all().find(|op| op.str() == p),
+                    _ => None,
+                };
+                let op = match op {
+                    Some(op) => op,
+                    None => {
+                        return Err(
+                            self.error(format!("expected an operator, found {}", self.peek()))
+                        );
+                    }
+                };
+                self.advance();
+                let rhs = self.parse_place()?;
+                ExprData::Op(lhs, op, rhs)
+            }
+        };
+        let expr = self.tables.add(data);
+        self.origins.push(expr, self.origin);
+        Ok(expr)
+    }
+
+    fn parse_place(&mut self) -> Result<Place, ParseError> {
+        let data = match *self.peek() {
+            Token::Identifier("fn") => {
+                self.advance();
+                let name = self.parse_identifier()?;
+                let function = self.items.iter().find_map(|item| match item {
+                    Item::Function(function) if function.name(self.db).as_str(self.db) == name => {
+                        Some(*function)
+                    }
+                    _ => None,
+                });
+                match function {
+                    Some(function) => PlaceData::Function(function),
+                    None => return Err(self.previous_error(format!("no function named `{name}`"))),
+                }
+            }
+            Token::Identifier("class") => {
+                self.advance();
+                let name = self.parse_identifier()?;
+                let class = self.items.iter().find_map(|item| match item {
+                    Item::Class(class) if class.name(self.db).as_str(self.db) == name => {
+                        Some(*class)
+                    }
+                    _ => None,
+                });
+                match class {
+                    Some(class) => PlaceData::Class(class),
+                    None => return Err(self.previous_error(format!("no class named `{name}`"))),
+                }
+            }
+            Token::Identifier("intrinsic") => {
+                self.advance();
+                let name = self.parse_identifier()?;
+                let intrinsic = Intrinsic::ALL
+                    .iter()
+                    .copied()
+                    .find(|intrinsic| intrinsic.as_str(self.db) == name);
+                match intrinsic {
+                    Some(intrinsic) => PlaceData::Intrinsic(intrinsic),
+                    None => return Err(self.previous_error(format!("no intrinsic named `{name}`"))),
+                }
+            }
+            _ => {
+                let index = self.parse_numbered("_", "a place")?;
+                if index >= u32::from(LocalVariable::max_key(&self.tables)) as usize {
+                    return Err(self.previous_error(format!("no local variable named `_{index}`")));
+                }
+                PlaceData::LocalVariable(LocalVariable::from(index))
+            }
+        };
+        let mut place = self.add_place(data);
+        while self.eat(".") {
+            let field = self.parse_identifier()?;
+            let field = Word::from(self.db, field);
+            place = self.add_place(PlaceData::Dot(place, field));
+        }
+        Ok(place)
+    }
+
+    fn add_place(&mut self, data: PlaceData) -> Place {
+        let place = self.tables.add(data);
+        self.origins.push(place, self.origin);
+        place
+    }
+
+    fn parse_block_reference(&mut self) -> Result<BasicBlock, ParseError> {
+        let offset = self.tokens[self.position].1;
+        let index = self.parse_numbered("bb", "a basic block")?;
+
+        // Blocks may be referenced before they are declared, so whether they
+        // exist is checked once all blocks are parsed. Declaring a block takes
+        // several tokens, though, so an index past the number of tokens can
+        // never be declared; reject it before it becomes a `BasicBlock`.
+        if index >= self.tokens.len() {
+            return Err(self.previous_error(format!("no block named `bb{index}`")));
+        }
+        let block = BasicBlock::from(index);
+        self.block_references.push((block, offset));
+        Ok(block)
+    }
+
+    /// Parses an identifier like `_3` or `bb3` (given the `prefix` `_` or `bb`)
+    /// and returns the number.
+    fn parse_numbered(&mut self, prefix: &str, expected: &str) -> Result<usize, ParseError> {
+        if let Token::Identifier(s) = *self.peek() {
+            if let Some(Ok(n)) = s
+                .strip_prefix(prefix)
+                .filter(|digits| digits.chars().all(|c| c.is_ascii_digit()))
+                .map(str::parse)
+            {
+                self.advance();
+                return Ok(n);
+            }
+        }
+        Err(self.error(format!("expected {expected}, found {}", self.peek())))
+    }
+
+    fn parse_identifier(&mut self) -> Result<&'me str, ParseError> {
+        match *self.peek() {
+            Token::Identifier(s) => {
+                self.advance();
+                Ok(s)
+            }
+            _ => Err(self.error(format!("expected an identifier, found {}", self.peek()))),
+        }
+    }
+
+    fn parse_integer(&mut self) -> Result<usize, ParseError> {
+        match *self.peek() {
+            Token::Integer(i) => {
+                self.advance();
+                Ok(i as usize)
+            }
+            _ => Err(self.error(format!("expected an integer, found {}", self.peek()))),
+        }
+    }
+
+    fn peek(&self) -> &Token<'me> {
+        &self.tokens[self.position].0
+    }
+
+    fn peek_next(&self) -> &Token<'me> {
+        let index = (self.position + 1).min(self.tokens.len() - 1);
+        &self.tokens[index].0
+    }
+
+    fn advance(&mut self) {
+        if self.position + 1 < self.tokens.len() {
+            self.position += 1;
+        }
+    }
+
+    fn eat(&mut self, punctuation: &str) -> bool {
+        if matches!(*self.peek(), Token::Punctuation(p) if p == punctuation) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, punctuation: &str) -> Result<(), ParseError> {
+        if self.eat(punctuation) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{punctuation}`, found {}", self.peek())))
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if matches!(*self.peek(), Token::Identifier(s) if s == keyword) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{keyword}`, found {}", self.peek())))
+        }
+    }
+
+    /// An error at the current token.
+    fn error(&self, message: String) -> ParseError {
+        error_at(self.text, self.tokens[self.position].1, message)
+    }
+
+    /// An error at the token that was just consumed.
+    fn previous_error(&self, message: String) -> ParseError {
+        error_at(self.text, self.tokens[self.position - 1].1, message)
+    }
+}
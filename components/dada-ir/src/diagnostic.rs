@@ -179,6 +179,15 @@ impl DiagnosticBuilder {
         self
     }
 
+    /// Add a child diagnostic if one is given (see [`Self::child`]).
+    #[must_use = "you have not emitted the diagnostic"]
+    pub fn maybe_child(self, diagnostic: Option<Diagnostic>) -> Self {
+        match diagnostic {
+            Some(diagnostic) => self.child(diagnostic),
+            None => self,
+        }
+    }
+
     /// Return the completed diagnostic.
     pub fn finish(mut self) -> Diagnostic {
         if self.add_primary_label {
@@ -0,0 +1,161 @@
+//! Edit-distance computations, used to suggest alternatives when
+//! the user references a name that doesn't exist.
+
+use crate::{diagnostic::Diagnostic, span::FileSpan, word::Word};
+
+/// Maximum number of suggestions we will offer for a single name.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Computes the Levenshtein distance between `a` and `b` (counted in chars).
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+
+    // `row[j]` is the distance between the prefix of `a` processed so far
+    // and `b[..j]`.
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Returns the words from `candidates` that are close enough to `target`
+/// that the user may have meant them, closest first.
+pub fn similar_words(
+    db: &dyn crate::Db,
+    target: Word,
+    candidates: impl IntoIterator<Item = Word>,
+) -> Vec<Word> {
+    rank_suggestions(
+        target.as_str(db),
+        candidates
+            .into_iter()
+            .map(|candidate| (candidate.as_str(db), candidate)),
+    )
+}
+
+/// Ranks `candidates` (each a string paired with the value to return for it)
+/// by their distance from `target`, keeping at most [`MAX_SUGGESTIONS`] that are
+/// within a third of the length of `target` (but at least 1 edit) and dropping
+/// `target` itself.
+fn rank_suggestions<'s, T: Copy + PartialEq>(
+    target: &str,
+    candidates: impl IntoIterator<Item = (&'s str, T)>,
+) -> Vec<T> {
+    let max_distance = (target.chars().count() / 3).max(1);
+
+    let mut scored: Vec<(usize, &str, T)> = candidates
+        .into_iter()
+        .filter(|&(candidate_str, _)| candidate_str != target)
+        .filter_map(|(candidate_str, candidate)| {
+            let distance = edit_distance(target, candidate_str);
+            if distance <= max_distance {
+                Some((distance, candidate_str, candidate))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    // Sort by distance, then alphabetically, so that output is deterministic.
+    scored.sort_by_key(|&(distance, candidate_str, _)| (distance, candidate_str));
+    scored.dedup_by(|a, b| a.2 == b.2);
+    scored
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, _, candidate)| candidate)
+        .collect()
+}
+
+/// If `suggestions` is non-empty, creates a "help" diagnostic that
+/// lists them, suitable for attaching as a child of an error.
+pub fn did_you_mean(
+    db: &dyn crate::Db,
+    span: FileSpan,
+    suggestions: &[Word],
+) -> Option<Diagnostic> {
+    let (last, rest) = suggestions.split_last()?;
+    let quote = |w: &Word| format!("`{}`", w.as_str(db));
+    let message = if rest.is_empty() {
+        format!("did you mean {}?", quote(last))
+    } else {
+        let rest: Vec<String> = rest.iter().map(quote).collect();
+        format!("did you mean {} or {}?", rest.join(", "), quote(last))
+    };
+    Some(
+        crate::help!(span, "{}", message)
+            .skip_primary_label()
+            .finish(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{edit_distance, rank_suggestions};
+
+    fn rank<'s>(target: &str, candidates: &[&'s str]) -> Vec<&'s str> {
+        rank_suggestions(target, candidates.iter().map(|&c| (c, c)))
+    }
+
+    #[test]
+    fn distances() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("abc", ""), 3);
+        assert_eq!(edit_distance("print", "print"), 0);
+        assert_eq!(edit_distance("prnt", "print"), 1);
+        assert_eq!(edit_distance("pint", "print"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("flaw", "lawn"), 2);
+
+        // Counted in chars, not bytes.
+        assert_eq!(edit_distance("naïve", "naive"), 1);
+    }
+
+    #[test]
+    fn threshold_is_a_third_of_the_length_but_at_least_one() {
+        // Short names allow a single edit.
+        assert_eq!(rank("x", &["y", "xy", "abc"]), vec!["xy", "y"]);
+        assert_eq!(rank("ab", &["abcd"]), Vec::<&str>::new());
+
+        // Six chars allow two edits, but not three.
+        assert_eq!(rank("helper", &["helpxx", "hexxxr"]), vec!["helpxx"]);
+    }
+
+    #[test]
+    fn target_itself_is_not_suggested() {
+        assert_eq!(rank("point", &["point", "pint"]), vec!["pint"]);
+    }
+
+    #[test]
+    fn closest_first_then_alphabetical() {
+        // `counts` is two edits away, so it comes last (and is cut by the cap).
+        assert_eq!(
+            rank("counter", &["counts", "countr", "counterr", "counted"]),
+            vec!["counted", "counterr", "countr"],
+        );
+        assert_eq!(
+            rank("abcdef", &["abcxyf", "abcdex"]),
+            vec!["abcdex", "abcxyf"]
+        );
+    }
+
+    #[test]
+    fn at_most_three_suggestions() {
+        assert_eq!(
+            rank("abc", &["abd", "abe", "abf", "abg", "abh"]),
+            vec!["abd", "abe", "abf"],
+        );
+    }
+
+    #[test]
+    fn duplicates_are_suggested_once() {
+        assert_eq!(rank("abc", &["abd", "abd"]), vec!["abd"]);
+    }
+}
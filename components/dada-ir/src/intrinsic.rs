@@ -30,4 +30,21 @@ macro_rules! intrinsic {
 
 intrinsic! {
     Print => "print",
+    Write => "write",
+    Spawn => "spawn",
+    Channel => "channel",
+    Send => "send",
+    Receive => "receive",
+    Assert => "assert",
+    Panic => "panic",
+    StringLength => "string_length",
+    Concat => "concat",
+    Substring => "substring",
+    Split => "split",
+    Contains => "contains",
+    StringToInteger => "string_to_integer",
+    IntegerToString => "integer_to_string",
+    Min => "min",
+    Max => "max",
+    Abs => "abs",
 }
@@ -7,6 +7,10 @@ use salsa::DebugWithDb;
 pub struct Options {
     paths: Vec<PathBuf>,
 
+    /// Warn about local variables that shadow other definitions.
+    #[structopt(long)]
+    warn_shadowing: bool,
+
     #[structopt(long)]
     log_syntax_tree: bool,
 
@@ -15,6 +19,10 @@ pub struct Options {
 
     #[structopt(long)]
     log_bir: bool,
+
+    /// Print the bir of each function in its textual format.
+    #[structopt(long)]
+    print_bir: bool,
 }
 
 impl Options {
@@ -26,6 +34,7 @@ impl Options {
                 .with_context(|| format!("reading `{}`", path.display()))?;
             let filename = dada_ir::filename::Filename::from(&db, path);
             db.update_file(filename, contents);
+            db.set_warn_shadowing(filename, self.warn_shadowing);
             all_diagnostics.extend(db.diagnostics(filename));
 
             if self.log_syntax_tree {
@@ -51,6 +60,14 @@ impl Options {
                     }
                 }
             }
+
+            if self.print_bir {
+                for item in db.items(filename) {
+                    if let Some(text) = db.bir_text(item) {
+                        println!("// {:?}\n{}", item.debug(&db), text);
+                    }
+                }
+            }
         }
 
         for diagnostic in all_diagnostics {
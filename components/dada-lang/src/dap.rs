@@ -0,0 +1,8 @@
+#[derive(structopt::StructOpt)]
+pub struct Options {}
+
+pub fn main(_crate_options: &crate::Options, _options: &Options) -> eyre::Result<()> {
+    let mut server = dada_dap::DapServer::stdio();
+    server.main_loop()?;
+    Ok(())
+}
@@ -0,0 +1,472 @@
+use std::{
+    io::{BufRead, Write},
+    path::PathBuf,
+};
+
+use dada_execute::{
+    heap_graph::HeapGraph,
+    history::{History, Snapshot},
+};
+use dada_ir::{
+    filename::Filename,
+    item::Item,
+    span::{FileSpan, LineColumn},
+};
+use dada_parse::prelude::*;
+use eyre::Context;
+use parking_lot::Mutex;
+use tokio::io::AsyncWriteExt;
+
+#[derive(structopt::StructOpt)]
+pub struct Options {
+    path: PathBuf,
+
+    /// Stop at the given location: either `LINE:COLUMN` or the name of a function.
+    /// May be given more than once. If no breakpoints are given, execution stops
+    /// at the first line that executes.
+    #[structopt(long = "break", short = "b")]
+    breakpoints: Vec<String>,
+}
+
+const HELP: &str = "\
+commands:
+  c, continue         continue to the next breakpoint
+  s, step             continue to the next line
+  l, locals           print the local variables
+  heap                print the stack and the heap
+  p, permission VAR   print the permission of the local variable VAR
+  back [N]            go back N steps (default 1) to inspect an earlier moment
+  forward [N]         go forward N steps (default 1), up to the present
+  b, break LOCATION   stop at LOCATION (`LINE:COLUMN` or a function name)
+  d, delete N         delete breakpoint N
+  breakpoints         list the breakpoints
+  q, quit             stop debugging
+";
+
+/// Number of steps of execution history kept for `back`.
+const HISTORY_LIMIT: usize = 10_000;
+
+impl Options {
+    pub async fn main(&self, _crate_options: &crate::Options) -> eyre::Result<()> {
+        let mut db = dada_db::Db::default();
+
+        let contents = std::fs::read_to_string(&self.path)
+            .with_context(|| format!("reading `{}`", self.path.display()))?;
+        let filename = Filename::from(&db, &self.path);
+        db.update_file(filename, contents);
+
+        for diagnostic in db.diagnostics(filename) {
+            dada_error_format::print_diagnostic(&db, &diagnostic)?;
+        }
+
+        // Execution can only stop at expressions that were compiled as breakpoints,
+        // so we compile one at the start of every line (to support `step`),
+        // plus the ones the user asked for.
+        let lines = line_locations(&db, filename);
+        let breakpoints = self
+            .breakpoints
+            .iter()
+            .map(|text| Breakpoint::resolve(&db, filename, text))
+            .collect::<eyre::Result<Vec<_>>>()?;
+        let locations = lines
+            .iter()
+            .chain(breakpoints.iter().map(|b| &b.location))
+            .map(|l| l.position)
+            .collect();
+        db.set_breakpoints(filename, locations);
+
+        let function = match db.function_named(filename, "main") {
+            Some(function) => function,
+            None => eyre::bail!(
+                "could not find a function named `main` in `{}`",
+                self.path.display()
+            ),
+        };
+
+        let input = Box::new(std::io::BufReader::new(std::io::stdin()));
+        let debugger = Debugger::new(filename, lines, breakpoints, input);
+        match dada_execute::interpret(function, &db, &debugger, vec![]).await {
+            Ok(()) => println!("program finished"),
+            Err(e) if e.downcast_ref::<Quit>().is_some() => {}
+            Err(e) => return Err(e),
+        }
+        Ok(())
+    }
+}
+
+/// An expression where execution can stop.
+#[derive(Copy, Clone, Debug)]
+struct Location {
+    /// The position given to [`dada_breakpoint::breakpoint::find`].
+    position: LineColumn,
+
+    /// The span of the breakpoint expression, which identifies it when execution stops there.
+    span: FileSpan,
+}
+
+struct Breakpoint {
+    /// What the user wrote to set the breakpoint (e.g., `4:9` or `main`).
+    text: String,
+
+    location: Location,
+}
+
+impl Breakpoint {
+    /// Finds the location that `text` refers to: either `LINE:COLUMN`
+    /// or the name of a function, which refers to its first expression.
+    fn resolve(db: &dyn dada_execute::Db, filename: Filename, text: &str) -> eyre::Result<Self> {
+        let position = match text.split_once(':') {
+            Some((line, column)) => {
+                let line: u32 = line
+                    .parse()
+                    .with_context(|| format!("bad line in `{text}`"))?;
+                let column: u32 = column
+                    .parse()
+                    .with_context(|| format!("bad column in `{text}`"))?;
+                LineColumn::new1(line, column)
+            }
+            None => {
+                let function = filename.items(db).iter().find_map(|item| match item {
+                    Item::Function(function) if function.name(db).as_str(db) == text => {
+                        Some(*function)
+                    }
+                    _ => None,
+                });
+                let function = match function {
+                    Some(function) => function,
+                    None => eyre::bail!("no function named `{text}`"),
+                };
+                let syntax_tree = function.syntax_tree(db);
+                let root_span = syntax_tree.spans(db)[syntax_tree.data(db).root_expr];
+                dada_ir::lines::line_column(db, filename, root_span.start)
+            }
+        };
+
+        match dada_breakpoint::breakpoint::find(db, filename, position) {
+            Some(breakpoint) => Ok(Breakpoint {
+                text: text.to_string(),
+                location: Location {
+                    position,
+                    span: breakpoint.span(db),
+                },
+            }),
+            None => eyre::bail!("no code to stop at `{text}`"),
+        }
+    }
+}
+
+/// Finds the location at the start of each line of code in `filename`.
+fn line_locations(db: &dyn dada_execute::Db, filename: Filename) -> Vec<Location> {
+    dada_breakpoint::locations::line_start_breakpoints(db, filename)
+        .into_iter()
+        .map(|(position, breakpoint)| Location {
+            position,
+            span: breakpoint.span(db),
+        })
+        .collect()
+}
+
+/// Returned from the kernel to stop execution when the user quits.
+#[derive(Debug)]
+struct Quit;
+
+impl std::fmt::Display for Quit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "stopped debugging")
+    }
+}
+
+impl std::error::Error for Quit {}
+
+struct Debugger {
+    filename: Filename,
+
+    /// The location at the start of each line of code.
+    lines: Vec<Location>,
+
+    /// Every location where execution can stop.
+    compiled: Vec<FileSpan>,
+
+    state: Mutex<DebuggerState>,
+}
+
+struct DebuggerState {
+    /// The breakpoints, numbered from 1; deleted breakpoints are `None`.
+    breakpoints: Vec<Option<Breakpoint>>,
+
+    /// If true, stop at the next line, whether or not there is a breakpoint.
+    stepping: bool,
+
+    /// Where commands are read from.
+    input: Box<dyn BufRead + Send>,
+
+    /// The state of the program at each step so far; the last one is the present.
+    history: History,
+}
+
+impl Debugger {
+    fn new(
+        filename: Filename,
+        lines: Vec<Location>,
+        breakpoints: Vec<Breakpoint>,
+        input: Box<dyn BufRead + Send>,
+    ) -> Self {
+        let compiled = lines
+            .iter()
+            .chain(breakpoints.iter().map(|b| &b.location))
+            .map(|l| l.span)
+            .collect();
+        let stepping = breakpoints.is_empty();
+        Self {
+            filename,
+            lines,
+            compiled,
+            state: Mutex::new(DebuggerState {
+                breakpoints: breakpoints.into_iter().map(Some).collect(),
+                stepping,
+                input,
+                history: History::new(HISTORY_LIMIT),
+            }),
+        }
+    }
+
+    /// Reads and executes commands until the user continues (`Ok`) or quits (`Err`).
+    fn pause(
+        &self,
+        db: &dyn dada_execute::Db,
+        state: &mut DebuggerState,
+        span: FileSpan,
+        heap_graph: &HeapGraph,
+    ) -> eyre::Result<()> {
+        let start = dada_ir::lines::line_column(db, span.filename, span.start);
+        let breakpoint = state
+            .breakpoints
+            .iter()
+            .position(|breakpoint| matches!(breakpoint, Some(b) if b.location.span == span));
+        match breakpoint {
+            Some(index) => println!(
+                "stopped at breakpoint {} ({}:{}:{})",
+                index + 1,
+                span.filename.as_str(db),
+                start.line1(),
+                start.column1()
+            ),
+            None => println!(
+                "stopped at {}:{}:{}",
+                span.filename.as_str(db),
+                start.line1(),
+                start.column1()
+            ),
+        }
+        let source_text = dada_ir::manifest::source_text(db, span.filename);
+        if let Some(line) = source_text.lines().nth(start.line0_usize()) {
+            println!("{:>4} | {}", start.line1(), line);
+        }
+
+        // While the user looks back at an earlier moment, the index of its
+        // snapshot in the history; `None` is the present.
+        let mut viewing: Option<usize> = None;
+
+        loop {
+            let heap_graph = match viewing {
+                Some(index) => &state.history.get(index).unwrap().heap_graph,
+                None => heap_graph,
+            };
+
+            print!("(dada) ");
+            std::io::stdout().flush()?;
+
+            let mut command = String::new();
+            if state.input.read_line(&mut command)? == 0 {
+                // End of input.
+                return Err(Quit.into());
+            }
+            let (command, argument) = match command.trim().split_once(char::is_whitespace) {
+                Some((command, argument)) => (command.to_string(), argument.trim().to_string()),
+                None => (command.trim().to_string(), String::new()),
+            };
+
+            match &command[..] {
+                "" => {}
+                "c" | "continue" => {
+                    state.stepping = false;
+                    return Ok(());
+                }
+                "s" | "step" => {
+                    state.stepping = true;
+                    return Ok(());
+                }
+                "l" | "locals" => print!("{}", heap_graph.locals_text(db)),
+                "heap" => print!("{}", heap_graph.text(db)),
+                "p" | "permission" => match heap_graph.permission_text(db, &argument) {
+                    Some(text) => println!("{text}"),
+                    None => println!("no local variable named `{argument}`"),
+                },
+                "back" | "forward" => {
+                    let steps = if argument.is_empty() {
+                        Ok(1)
+                    } else {
+                        argument.parse::<usize>()
+                    };
+                    match steps {
+                        Ok(steps) => {
+                            viewing = travel(db, &state.history, viewing, &command, steps);
+                        }
+                        Err(_) => println!("bad number of steps `{argument}`"),
+                    }
+                }
+                "b" | "break" => match self.add_breakpoint(db, state, &argument) {
+                    Ok(message) => println!("{message}"),
+                    Err(e) => println!("{e}"),
+                },
+                "d" | "delete" => match argument.parse::<usize>() {
+                    Ok(number) if (1..=state.breakpoints.len()).contains(&number) => {
+                        state.breakpoints[number - 1] = None;
+                        println!("deleted breakpoint {number}");
+                    }
+                    _ => println!("no breakpoint numbered `{argument}`"),
+                },
+                "breakpoints" => {
+                    for (index, breakpoint) in state.breakpoints.iter().enumerate() {
+                        if let Some(breakpoint) = breakpoint {
+                            println!("{}: {}", index + 1, breakpoint.text);
+                        }
+                    }
+                }
+                "q" | "quit" => return Err(Quit.into()),
+                "help" | "?" => print!("{HELP}"),
+                _ => println!("unknown command `{command}`; type `help` for a list of commands"),
+            }
+        }
+    }
+
+    /// Sets a breakpoint while the program is running. The program was compiled
+    /// with a fixed set of locations where it can stop, so if `text` refers to some
+    /// other location, the breakpoint is set at the start of its line instead.
+    fn add_breakpoint(
+        &self,
+        db: &dyn dada_execute::Db,
+        state: &mut DebuggerState,
+        text: &str,
+    ) -> eyre::Result<String> {
+        let mut breakpoint = Breakpoint::resolve(db, self.filename, text)?;
+        let mut message = String::new();
+        if !self.compiled.contains(&breakpoint.location.span) {
+            let line0 = breakpoint.location.position.line0();
+            match self.lines.iter().find(|l| l.position.line0() == line0) {
+                Some(location) => {
+                    breakpoint.location = *location;
+                    message.push_str(&format!(
+                        "while running, execution can only stop at the start of line {}; ",
+                        line0 + 1
+                    ));
+                }
+                None => eyre::bail!("execution cannot stop at `{text}` while running"),
+            }
+        }
+        state.breakpoints.push(Some(breakpoint));
+        message.push_str(&format!("set breakpoint {}", state.breakpoints.len()));
+        Ok(message)
+    }
+}
+
+/// Moves `steps` steps back or forward (depending on `command`) from the moment at
+/// `viewing` (see [`Debugger::pause`]), prints where that is, and returns it.
+fn travel(
+    db: &dyn dada_execute::Db,
+    history: &History,
+    viewing: Option<usize>,
+    command: &str,
+    steps: usize,
+) -> Option<usize> {
+    // The last snapshot is the step that is executing now.
+    let present = match history.len().checked_sub(1) {
+        Some(present) => present,
+        None => {
+            println!("no history has been recorded");
+            return None;
+        }
+    };
+    let from = viewing.unwrap_or(present);
+    let to = if command == "back" {
+        if from < steps {
+            println!("cannot go back further than the oldest recorded step");
+        }
+        from.saturating_sub(steps)
+    } else {
+        (from + steps).min(present)
+    };
+
+    if to == present {
+        println!("back at the present");
+        return None;
+    }
+    print_snapshot(db, history.get(to).unwrap());
+    Some(to)
+}
+
+fn print_snapshot(db: &dyn dada_execute::Db, snapshot: &Snapshot) {
+    let span = snapshot.span;
+    let start = dada_ir::lines::line_column(db, span.filename, span.start);
+    println!(
+        "at step {} ({}:{}:{})",
+        snapshot.clock,
+        span.filename.as_str(db),
+        start.line1(),
+        start.column1()
+    );
+    let source_text = dada_ir::manifest::source_text(db, span.filename);
+    if let Some(line) = source_text.lines().nth(start.line0_usize()) {
+        println!("{:>4} | {}", start.line1(), line);
+    }
+}
+
+#[async_trait::async_trait]
+impl dada_execute::kernel::Kernel for Debugger {
+    async fn print(&self, text: &str) -> eyre::Result<()> {
+        let mut stdout = tokio::io::stdout();
+        stdout.write_all(text.as_bytes()).await?;
+
+        // Flush so that the output appears before the debugger's own output.
+        stdout.flush().await?;
+        Ok(())
+    }
+
+    fn breakpoint_start(
+        &self,
+        _db: &dyn dada_execute::Db,
+        _breakpoint_filename: Filename,
+        _breakpoint_index: usize,
+        _generate_heap_graph: &dyn Fn() -> HeapGraph,
+    ) -> eyre::Result<()> {
+        Ok(())
+    }
+
+    fn breakpoint_end(
+        &self,
+        db: &dyn dada_execute::Db,
+        _breakpoint_filename: Filename,
+        _breakpoint_index: usize,
+        breakpoint_span: FileSpan,
+        generate_heap_graph: &dyn Fn() -> HeapGraph,
+    ) -> eyre::Result<()> {
+        let state = &mut *self.state.lock();
+        let at_breakpoint = state
+            .breakpoints
+            .iter()
+            .any(|breakpoint| matches!(breakpoint, Some(b) if b.location.span == breakpoint_span));
+        if !at_breakpoint && !state.stepping {
+            return Ok(());
+        }
+
+        self.pause(db, state, breakpoint_span, &generate_heap_graph())
+    }
+
+    fn records_history(&self) -> bool {
+        true
+    }
+
+    fn record_step(&self, _db: &dyn dada_execute::Db, snapshot: Snapshot) {
+        self.state.lock().history.push(snapshot);
+    }
+}
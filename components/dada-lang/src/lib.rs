@@ -7,7 +7,10 @@ use tracing_subscriber::prelude::*;
 use tracing_subscriber::EnvFilter;
 
 mod check;
+mod dap;
+mod debug;
 mod ide;
+mod repl;
 mod run;
 mod test_harness;
 
@@ -62,6 +65,11 @@ impl Options {
             Command::Check(command_options) => command_options.main(self)?,
             Command::Test(command_options) => command_options.main(self).await?,
             Command::Run(command_options) => command_options.main(self).await?,
+            Command::Debug(command_options) => command_options.main(self).await?,
+            Command::Dap(command_options) => {
+                dap::main(self, command_options)?;
+            }
+            Command::Repl(command_options) => command_options.main(self).await?,
         }
         Ok(())
     }
@@ -74,4 +82,7 @@ pub enum Command {
     Check(check::Options),
     Test(test_harness::Options),
     Run(run::Options),
+    Debug(debug::Options),
+    Dap(dap::Options),
+    Repl(repl::Options),
 }
@@ -0,0 +1,333 @@
+//! An interactive session: `dada repl`.
+//!
+//! The session is kept as the source of a single file: the functions and classes
+//! that the user has defined, followed by a function that executes the lines they
+//! have entered. Rather than keeping values alive from one line to the next, each
+//! line re-executes the lines before it, hiding their output; Dada programs are
+//! deterministic, so this recreates the same local variables. Salsa reuses what it
+//! can when the file changes.
+
+use std::io::{BufRead, Write};
+
+use dada_execute::{
+    execution::{Execution, PauseReason, Progress},
+    heap_graph::HeapGraph,
+    kernel::BufferKernel,
+};
+use dada_ir::{diagnostic::Severity, filename::Filename, span::LineColumn};
+
+/// The function that executes the lines entered so far.
+const SESSION_FUNCTION: &str = "repl";
+
+const HELP: &str = "\
+Enter a function or class to define it (redefining any with the same name),
+or a line of code to execute it; the value of an expression is printed.
+Lines with unclosed brackets continue on the next line.
+
+commands:
+  :bir [NAME]    print the bir of the function NAME (default: the lines entered so far)
+  :heap          print the local variables and the heap
+  :type EXPR     print the permission and kind of the value of EXPR
+  :help          print this message
+  :quit          leave the repl
+";
+
+#[derive(structopt::StructOpt)]
+pub struct Options {}
+
+impl Options {
+    pub async fn main(&self, _crate_options: &crate::Options) -> eyre::Result<()> {
+        let mut repl = Repl::new();
+        let mut input = std::io::BufReader::new(std::io::stdin());
+
+        println!("Dada repl; type `:help` for help.");
+        loop {
+            let text = match read_entry(&mut input)? {
+                Some(text) => text,
+                None => break,
+            };
+            let text = text.trim();
+
+            if let Some(command) = text.strip_prefix(':') {
+                let (command, argument) = match command.split_once(char::is_whitespace) {
+                    Some((command, argument)) => (command, argument.trim()),
+                    None => (command, ""),
+                };
+                match command {
+                    "bir" => repl.print_bir(argument),
+                    "heap" => repl.print_heap(),
+                    "type" => repl.print_type(argument).await?,
+                    "help" => print!("{HELP}"),
+                    "q" | "quit" => break,
+                    _ => println!(
+                        "unknown command `:{command}`; type `:help` for a list of commands"
+                    ),
+                }
+            } else if text.is_empty() {
+                continue;
+            } else if let Some(name) = item_name(text) {
+                repl.define(name, text)?;
+            } else {
+                repl.execute(text).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads one entry, which continues over several lines while it has unclosed brackets.
+/// Returns `None` at the end of the input.
+fn read_entry(input: &mut dyn BufRead) -> eyre::Result<Option<String>> {
+    let mut text = String::new();
+    loop {
+        print!("{}", if text.is_empty() { "dada> " } else { "...   " });
+        std::io::stdout().flush()?;
+
+        if input.read_line(&mut text)? == 0 {
+            if text.is_empty() {
+                return Ok(None);
+            }
+            return Ok(Some(text));
+        }
+        if !has_unclosed_brackets(&text) {
+            return Ok(Some(text));
+        }
+    }
+}
+
+fn has_unclosed_brackets(text: &str) -> bool {
+    let mut depth = 0;
+    let mut in_string = false;
+    for ch in text.chars() {
+        match ch {
+            '"' => in_string = !in_string,
+            '(' | '[' | '{' if !in_string => depth += 1,
+            ')' | ']' | '}' if !in_string => depth -= 1,
+            _ => {}
+        }
+    }
+    depth > 0
+}
+
+/// If `text` defines a function or class, returns its name.
+fn item_name(text: &str) -> Option<&str> {
+    let re =
+        regex::Regex::new(r"^(async\s+)?(fn|class)\s+(?P<name>[A-Za-z_][A-Za-z_0-9]*)").unwrap();
+    Some(re.captures(text)?.name("name")?.as_str())
+}
+
+struct Repl {
+    db: dada_db::Db,
+    filename: Filename,
+
+    /// The functions and classes defined so far, with their names.
+    items: Vec<(String, String)>,
+
+    /// The lines entered so far, all of which executed successfully.
+    lines: Vec<String>,
+
+    /// Length of the output from executing `lines`, which is hidden
+    /// when they are executed again.
+    output_len: usize,
+
+    /// The state of the program after executing `lines`.
+    heap_graph: Option<HeapGraph>,
+}
+
+/// The outcome of executing the lines entered so far followed by a new one.
+struct Outcome {
+    /// Everything printed, including by the earlier lines.
+    output: String,
+
+    /// The state of the program after the new line, or the error it failed with.
+    result: eyre::Result<HeapGraph>,
+}
+
+impl Repl {
+    fn new() -> Self {
+        let db = dada_db::Db::default();
+        let filename = Filename::from(&db, "<repl>");
+        Self {
+            db,
+            filename,
+            items: vec![],
+            lines: vec![],
+            output_len: 0,
+            heap_graph: None,
+        }
+    }
+
+    /// Defines a function or class, replacing any existing one with the same name.
+    fn define(&mut self, name: &str, text: &str) -> eyre::Result<()> {
+        if name == SESSION_FUNCTION {
+            println!("the name `{name}` is reserved for the lines entered in the repl");
+            return Ok(());
+        }
+
+        let mut items = self.items.clone();
+        items.retain(|(n, _)| n != name);
+        items.push((name.to_string(), text.to_string()));
+
+        self.update_source(&items, &self.lines.clone());
+        if self.print_errors()? {
+            self.restore_source();
+            return Ok(());
+        }
+        self.items = items;
+        println!("defined `{name}`");
+        Ok(())
+    }
+
+    /// Executes `line` after the lines entered so far, printing what it prints and
+    /// its value (if any). The line is kept if it executes successfully.
+    async fn execute(&mut self, line: &str) -> eyre::Result<()> {
+        let outcome = match self.execute_with(line).await? {
+            Some(outcome) => outcome,
+            None => return Ok(()),
+        };
+        print!(
+            "{}",
+            &outcome.output[self.output_len.min(outcome.output.len())..]
+        );
+        match outcome.result {
+            Ok(heap_graph) => {
+                if let Some(value) = heap_graph.in_flight_text(&self.db) {
+                    println!("{value}");
+                }
+                self.lines.push(line.to_string());
+                self.output_len = outcome.output.len();
+                self.heap_graph = Some(heap_graph);
+            }
+            Err(e) => {
+                println!("{e}");
+                self.restore_source();
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints the permission and kind of the value of `expr`, without keeping it.
+    async fn print_type(&mut self, expr: &str) -> eyre::Result<()> {
+        let outcome = match self.execute_with(expr).await? {
+            Some(outcome) => outcome,
+            None => return Ok(()),
+        };
+        match outcome.result {
+            Ok(heap_graph) => match heap_graph.in_flight_type_text(&self.db) {
+                Some(text) => println!("{text}"),
+                None => println!("`{expr}` has no value"),
+            },
+            Err(e) => println!("{e}"),
+        }
+        self.restore_source();
+        Ok(())
+    }
+
+    /// Executes the lines entered so far followed by `line`, stopping at the end
+    /// to capture the state of the program. Returns `None` if `line` has compilation
+    /// errors (which are printed).
+    async fn execute_with(&mut self, line: &str) -> eyre::Result<Option<Outcome>> {
+        let mut lines = self.lines.clone();
+        lines.push(line.to_string());
+        let end = self.update_source(&self.items.clone(), &lines);
+        if self.print_errors()? {
+            self.restore_source();
+            return Ok(None);
+        }
+
+        let function = self
+            .db
+            .function_named(self.filename, SESSION_FUNCTION)
+            .unwrap();
+        self.db.set_breakpoints(self.filename, vec![end]);
+        let mut kernel = BufferKernel::new();
+        let mut execution = Execution::new(&self.db, &kernel, function, vec![]);
+        let result = loop {
+            match execution.resume().await {
+                Progress::Paused(pause) if pause.reason == PauseReason::BreakpointEnd => {
+                    break Ok(pause.heap_graph);
+                }
+                Progress::Paused(_) => {}
+                Progress::Finished(Err(e)) => break Err(e),
+                Progress::Finished(Ok(())) => {
+                    break Err(eyre::eyre!("returned before reaching the end of the lines"))
+                }
+            }
+        };
+
+        // We have what we need; the rest of the execution is abandoned.
+        drop(execution);
+        let output = kernel.take_buffer();
+        Ok(Some(Outcome { output, result }))
+    }
+
+    /// Sets the source of the session's file and returns the position of the end of
+    /// the session function. Stopping there captures its local variables, along with
+    /// the value of its last line as the in-flight value.
+    fn update_source(&mut self, items: &[(String, String)], lines: &[String]) -> LineColumn {
+        let mut source = String::new();
+        for (_, item) in items {
+            source.push_str(item);
+            source.push('\n');
+        }
+        source.push_str(&format!("async fn {SESSION_FUNCTION}() {{\n"));
+        for line in lines {
+            source.push_str(line);
+            source.push('\n');
+        }
+        let end_line = source.lines().count() as u32 + 1;
+        source.push_str("}\n");
+
+        self.db.update_file(self.filename, source);
+        LineColumn::new1(end_line, 1)
+    }
+
+    /// Sets the source of the session's file back to the definitions and lines kept so far.
+    fn restore_source(&mut self) {
+        self.update_source(&self.items.clone(), &self.lines.clone());
+    }
+
+    /// Prints the errors in the session's file, if any, and returns true if there were some.
+    /// Warnings are not printed; e.g., every variable would be unused.
+    fn print_errors(&self) -> eyre::Result<bool> {
+        let errors = self
+            .db
+            .diagnostics(self.filename)
+            .into_iter()
+            .filter(|d| d.severity == Severity::Error)
+            .collect::<Vec<_>>();
+        if errors.is_empty() {
+            return Ok(false);
+        }
+        print!(
+            "{}",
+            dada_error_format::format_diagnostics(&self.db, &errors)?
+        );
+        Ok(true)
+    }
+
+    fn print_bir(&self, name: &str) {
+        let name = if name.is_empty() {
+            SESSION_FUNCTION
+        } else {
+            name
+        };
+        let bir = self
+            .db
+            .items(self.filename)
+            .into_iter()
+            .find(|item| item.name(&self.db).as_str(&self.db) == name)
+            .and_then(|item| self.db.bir_text(item));
+        match bir {
+            Some(bir) => print!("{bir}"),
+            None => println!("no function named `{name}`"),
+        }
+    }
+
+    fn print_heap(&self) {
+        match &self.heap_graph {
+            Some(heap_graph) => print!("{}", heap_graph.text(&self.db)),
+            None => println!("nothing has executed yet"),
+        }
+    }
+}
@@ -1,13 +1,59 @@
-use std::path::PathBuf;
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
-use dada_execute::heap_graph::HeapGraph;
+use dada_execute::{
+    heap_graph::HeapGraph,
+    kernel::{Schedule, TaskChooser},
+};
 use dada_ir::span::FileSpan;
 use eyre::Context;
+use parking_lot::Mutex;
 use tokio::io::AsyncWriteExt;
 
 #[derive(structopt::StructOpt)]
 pub struct Options {
     path: PathBuf,
+
+    /// Warn about local variables that shadow other definitions.
+    #[structopt(long)]
+    warn_shadowing: bool,
+
+    /// Optimize the bir before executing it.
+    #[structopt(long)]
+    optimize: bool,
+
+    /// Execute `main` from the given file of textual bir (as printed by
+    /// `dada check --print-bir`) instead of from its source.
+    #[structopt(long)]
+    bir: Option<PathBuf>,
+
+    /// When several tasks could run next, choose pseudo-randomly
+    /// with the given seed instead of round-robin.
+    #[structopt(long, conflicts_with = "schedule")]
+    seed: Option<u64>,
+
+    /// Replay a schedule reported by `--model-check` (e.g., `0.1.1`).
+    #[structopt(long)]
+    schedule: Option<String>,
+
+    /// Execute `main` under many different schedules, reporting those that
+    /// produce an error or a different output than round-robin.
+    #[structopt(long, conflicts_with_all = &["bir", "seed", "schedule"])]
+    model_check: bool,
+
+    /// The maximum number of schedules to explore with `--model-check`.
+    #[structopt(long, default_value = "1000")]
+    max_schedules: usize,
+
+    /// Stop with an error if execution takes more than this many steps.
+    #[structopt(long)]
+    max_steps: Option<u64>,
+
+    /// Stop with an error if execution takes more than this many milliseconds.
+    #[structopt(long)]
+    timeout_ms: Option<u64>,
 }
 
 impl Options {
@@ -18,16 +64,49 @@ impl Options {
             .with_context(|| format!("reading `{}`", self.path.display()))?;
         let filename = dada_ir::filename::Filename::from(&db, &self.path);
         db.update_file(filename, contents);
+        db.set_warn_shadowing(filename, self.warn_shadowing);
+        db.set_optimize_bir(filename, self.optimize);
 
         for diagnostic in db.diagnostics(filename) {
             dada_error_format::print_diagnostic(&db, &diagnostic)?;
         }
 
+        let schedule = match (&self.schedule, self.seed) {
+            (Some(schedule), _) => Schedule::parse_replay(schedule)?,
+            (None, Some(seed)) => Schedule::Seeded(seed),
+            (None, None) => Schedule::RoundRobin,
+        };
+        let deadline = self
+            .timeout_ms
+            .map(|timeout_ms| Instant::now() + Duration::from_millis(timeout_ms));
+        let kernel = Kernel::new(schedule, self.max_steps, deadline);
+
         // Find the "main" function
         match db.function_named(filename, "main") {
-            Some(function) => {
-                dada_execute::interpret(function, &db, &Kernel::new(), vec![]).await?;
+            Some(function) if self.model_check => {
+                let report =
+                    dada_execute::model_check::model_check(&db, function, self.max_schedules).await;
+                print!("{report}");
+                if !report.is_ok() {
+                    eyre::bail!(
+                        "{} schedules produced an error or a different output",
+                        report.failures.len()
+                    );
+                }
             }
+            Some(function) => match &self.bir {
+                Some(bir_path) => {
+                    let text = std::fs::read_to_string(bir_path)
+                        .with_context(|| format!("reading `{}`", bir_path.display()))?;
+                    let bir = db
+                        .parse_bir(function, &text)
+                        .with_context(|| format!("parsing `{}`", bir_path.display()))?;
+                    dada_execute::interpret_bir(function, bir, &db, &kernel, vec![]).await?;
+                }
+                None => {
+                    dada_execute::interpret(function, &db, &kernel, vec![]).await?;
+                }
+            },
             None => {
                 return Err(eyre::eyre!(
                     "could not find a function named `main` in `{}`",
@@ -40,11 +119,19 @@ impl Options {
     }
 }
 
-struct Kernel {}
+struct Kernel {
+    task_chooser: Mutex<TaskChooser>,
+    max_steps: Option<u64>,
+    deadline: Option<Instant>,
+}
 
 impl Kernel {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(schedule: Schedule, max_steps: Option<u64>, deadline: Option<Instant>) -> Self {
+        Self {
+            task_chooser: Mutex::new(TaskChooser::new(schedule)),
+            max_steps,
+            deadline,
+        }
     }
 }
 
@@ -80,4 +167,17 @@ impl dada_execute::kernel::Kernel for Kernel {
     ) -> eyre::Result<()> {
         panic!("no breakpoints set")
     }
+
+    fn choose_task(&self, num_choices: usize) -> usize {
+        self.task_chooser.lock().choose(num_choices)
+    }
+
+    fn max_steps(&self) -> Option<u64> {
+        self.max_steps
+    }
+
+    fn deadline_passed(&self) -> bool {
+        self.deadline
+            .map_or(false, |deadline| Instant::now() >= deadline)
+    }
 }
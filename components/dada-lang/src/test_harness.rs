@@ -7,9 +7,17 @@ use eyre::Context;
 use lsp_types::Diagnostic;
 use regex::Regex;
 
+mod dap_client;
 mod heap_graph_query;
 mod lsp_client;
 
+/// Tests that execute for longer than this many steps fail, rather than hang...
+const MAX_STEPS: u64 = 1_000_000;
+
+/// ...and tests that have more than this many objects live at once fail,
+/// rather than exhaust memory.
+const MAX_OBJECTS: usize = 10_000;
+
 #[derive(structopt::StructOpt)]
 pub struct Options {
     #[structopt(parse(from_os_str), default_value = "dada_tests")]
@@ -105,15 +113,20 @@ impl Options {
     async fn test_dada_file(&self, path: &Path) -> eyre::Result<()> {
         let expected_queries = &expected_queries(path)?;
         let expected_diagnostics = &expected_diagnostics(path)?;
+        let test_options = &test_options(path)?;
         let path_without_extention = path.with_extension("");
         fs::create_dir_all(&path_without_extention)?;
         self.test_dada_file_normal(
             &path_without_extention,
+            test_options,
             expected_diagnostics,
             expected_queries,
         )
         .await?;
-        self.test_dada_file_in_ide(&path_without_extention, expected_diagnostics)?;
+        self.test_dada_file_in_ide(&path_without_extention, test_options, expected_diagnostics)?;
+        if test_options.dap {
+            self.test_dada_file_in_debugger(&path_without_extention, expected_queries)?;
+        }
         Ok(())
     }
 
@@ -121,6 +134,7 @@ impl Options {
     async fn test_dada_file_normal(
         &self,
         path: &Path,
+        test_options: &TestOptions,
         expected_diagnostics: &[ExpectedDiagnostic],
         expected_queries: &[Query],
     ) -> eyre::Result<()> {
@@ -130,6 +144,9 @@ impl Options {
             .with_context(|| format!("reading `{}`", &source_path.display()))?;
         let filename = dada_ir::filename::Filename::from(&db, &source_path);
         db.update_file(filename, contents);
+        db.set_warn_shadowing(filename, test_options.warn_shadowing);
+        db.set_warn_unused(filename, test_options.warn_unused);
+        db.set_verify_bir(filename, true);
         let diagnostics = db.diagnostics(filename);
 
         let mut errors = Errors::default();
@@ -165,14 +182,67 @@ impl Options {
             &path.join("bir.ref"),
             &mut errors,
         )?;
-        self.check_interpreted(&db, filename, &path.join("stdout.ref"), &mut errors)
-            .await?;
+        self.check_bir_text_round_trip(&db, filename, &path.join("bir.ref"), &mut errors);
+        let output = self.interpret(&db, filename, test_options).await;
+        self.check_output_against_ref_file(output.clone(), &path.join("stdout.ref"), &mut errors)?;
+
+        // A hand-written `main.bir` in the test directory replaces the body of `main`.
+        let main_bir_path = path.join("main.bir");
+        if main_bir_path.exists() {
+            let output = self
+                .interpret_bir_file(&db, filename, &main_bir_path)
+                .await?;
+            self.check_output_against_ref_file(output, &path.join("bir-stdout.ref"), &mut errors)?;
+        }
 
         for (query, query_index) in expected_queries.iter().zip(0..) {
             self.perform_query_on_db(&mut db, path, filename, query, query_index, &mut errors)
                 .await?;
         }
 
+        if test_options.model_check {
+            if let Some(function) = db.function_named(filename, "main") {
+                let report = dada_execute::model_check::model_check(&db, function, 100).await;
+                self.check_output_against_ref_file(
+                    report.to_string(),
+                    &path.join("model-check.ref"),
+                    &mut errors,
+                )?;
+            }
+        }
+
+        if test_options.history {
+            let history = self.interpret_with_history(&db, filename).await;
+            self.check_output_against_ref_file(history, &path.join("history.ref"), &mut errors)?;
+        }
+
+        if test_options.optimize_bir {
+            self.check_compiled(
+                &db,
+                &[filename],
+                |item| db.debug_optimized_bir(item),
+                &path.join("bir-optimized.ref"),
+                &mut errors,
+            )?;
+
+            // Optimizing the bir must not change what the program does.
+            db.set_optimize_bir(filename, true);
+            let optimized_output = self.interpret(&db, filename, test_options).await;
+            if optimized_output != output {
+                errors.push(RefOutputDoesNotMatch {
+                    ref_path: path.join("stdout.ref"),
+                    expected: output,
+                    actual: optimized_output,
+                });
+            }
+
+            // Breakpoints must survive optimization.
+            for (query, query_index) in expected_queries.iter().zip(0..) {
+                self.perform_query_on_db(&mut db, path, filename, query, query_index, &mut errors)
+                    .await?;
+            }
+        }
+
         errors.into_result()
     }
 
@@ -180,10 +250,14 @@ impl Options {
     fn test_dada_file_in_ide(
         &self,
         path: &Path,
+        test_options: &TestOptions,
         expected_diagnostics: &[ExpectedDiagnostic],
     ) -> eyre::Result<()> {
         let mut c = lsp_client::ChildSession::spawn();
-        c.send_init()?;
+        c.send_init(serde_json::json!({
+            "warnShadowing": test_options.warn_shadowing,
+            "warnUnused": test_options.warn_unused,
+        }))?;
         c.send_open(&path.with_extension("dada"))?;
         let diagnostics = c.receive_errors()?;
 
@@ -202,6 +276,23 @@ impl Options {
         errors.into_result()
     }
 
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn test_dada_file_in_debugger(
+        &self,
+        path: &Path,
+        expected_queries: &[Query],
+    ) -> eyre::Result<()> {
+        let mut lines: Vec<u32> = expected_queries.iter().map(|q| q.line).collect();
+        lines.dedup();
+
+        let mut c = dap_client::ChildSession::spawn();
+        let transcript = c.debug(&path.with_extension("dada"), &lines)?;
+
+        let mut errors = Errors::default();
+        self.check_output_against_ref_file(transcript, &path.join("dap.ref"), &mut errors)?;
+        errors.into_result()
+    }
+
     async fn perform_query_on_db(
         &self,
         db: &mut dada_db::Db,
@@ -335,25 +426,129 @@ impl Options {
         Ok(())
     }
 
-    async fn check_interpreted(
+    /// Checks that printing the bir of each function as text and parsing
+    /// it back yields the same text.
+    fn check_bir_text_round_trip(
         &self,
         db: &dada_db::Db,
         filename: Filename,
-        ref_path: &Path,
+        bir_path: &Path,
         errors: &mut Errors,
-    ) -> eyre::Result<()> {
-        let actual_output = match db.function_named(filename, "main") {
+    ) {
+        for item in db.items(filename) {
+            if let Item::Function(function) = item {
+                let original = db.bir(item).unwrap();
+                let text = dada_ir::code::bir::text::print(db, original);
+                match db.parse_bir(function, &text) {
+                    Ok(bir) => {
+                        let reprinted = dada_ir::code::bir::text::print(db, bir);
+                        if reprinted != text {
+                            errors.push(RefOutputDoesNotMatch {
+                                ref_path: bir_path.to_path_buf(),
+                                expected: text,
+                                actual: reprinted,
+                            });
+                        }
+
+                        // The text format does not record origins; check that
+                        // nothing else was lost along the way.
+                        if let Err(message) =
+                            dada_ir::code::bir::text::check_round_trip(db, original, bir)
+                        {
+                            errors.push_result(Err(eyre::eyre!(
+                                "round-tripping the textual bir for `{}`: {}",
+                                function.name(db).as_str(db),
+                                message
+                            )));
+                        }
+                    }
+                    Err(e) => errors.push_result(Err(eyre::Report::new(e).wrap_err(format!(
+                        "parsing the textual bir for `{}`",
+                        function.name(db).as_str(db)
+                    )))),
+                }
+            }
+        }
+    }
+
+    /// Runs the `main` function with the textual bir in `bir_path` as its body
+    /// and returns everything that it printed.
+    async fn interpret_bir_file(
+        &self,
+        db: &dada_db::Db,
+        filename: Filename,
+        bir_path: &Path,
+    ) -> eyre::Result<String> {
+        let function = db
+            .function_named(filename, "main")
+            .ok_or_else(|| eyre::eyre!("no `main` function in `{}`", filename.as_str(db)))?;
+        let text = std::fs::read_to_string(bir_path)
+            .with_context(|| format!("reading `{}`", bir_path.display()))?;
+        let bir = db
+            .parse_bir(function, &text)
+            .with_context(|| format!("parsing `{}`", bir_path.display()))?;
+        let mut kernel = BufferKernel::new()
+            .max_steps(MAX_STEPS)
+            .max_objects(MAX_OBJECTS);
+        kernel
+            .interpret_bir_and_buffer(db, function, bir, vec![])
+            .await;
+        Ok(kernel.take_buffer())
+    }
+
+    /// Interprets `main`, recording the history of execution, and describes the
+    /// local variables at each step.
+    async fn interpret_with_history(&self, db: &dada_db::Db, filename: Filename) -> String {
+        let function = match db.function_named(filename, "main") {
+            Some(function) => function,
+            None => return format!("no `main` function in `{}`", filename.as_str(db)),
+        };
+        let mut kernel = BufferKernel::new()
+            .max_steps(MAX_STEPS)
+            .max_objects(MAX_OBJECTS)
+            .record_history(usize::MAX);
+        kernel.interpret_and_buffer(db, function, vec![]).await;
+
+        let mut output = String::new();
+        for snapshot in kernel.take_history().unwrap().iter() {
+            let span = snapshot.span;
+            let start = dada_ir::lines::line_column(db, span.filename, span.start);
+            output.push_str(&format!(
+                "step {} at {}:{}: {}\n",
+                snapshot.clock,
+                start.line1(),
+                start.column1(),
+                span.snippet(db).lines().next().unwrap_or(""),
+            ));
+            for line in snapshot.heap_graph.locals_text(db).lines() {
+                output.push_str(&format!("    {line}\n"));
+            }
+        }
+        output
+    }
+
+    /// Runs the `main` function and returns everything that it printed.
+    async fn interpret(
+        &self,
+        db: &dada_db::Db,
+        filename: Filename,
+        test_options: &TestOptions,
+    ) -> String {
+        match db.function_named(filename, "main") {
             Some(function) => {
-                let mut kernel = BufferKernel::new();
+                let mut kernel = BufferKernel::new()
+                    .max_steps(MAX_STEPS)
+                    .max_objects(MAX_OBJECTS);
+                if test_options.deadline_passed {
+                    kernel = kernel.deadline(|| true);
+                }
                 kernel.interpret_and_buffer(db, function, vec![]).await;
                 kernel.take_buffer()
             }
             None => {
                 format!("no `main` function in `{}`", filename.as_str(db))
             }
-        };
-        self.check_output_against_ref_file(actual_output, ref_path, errors)?;
-        Ok(())
+        }
     }
 }
 
@@ -512,6 +707,63 @@ enum QueryKind {
     HeapGraph,
 }
 
+/// Options that a test enables with a `#: option` line, such as
+/// `#: warn_shadowing`.
+#[derive(Clone, Debug, Default)]
+struct TestOptions {
+    /// Warn about local variables that shadow other definitions.
+    warn_shadowing: bool,
+
+    /// Warn about unused variables, parameters, functions, and classes. Unlike the
+    /// compiler, tests leave this off unless they ask for it, so that tests of other
+    /// features don't need to annotate every unused name.
+    warn_unused: bool,
+
+    /// Also optimize the bir, dumping it into `bir-optimized.ref`,
+    /// and check that the optimized program prints the same output.
+    optimize_bir: bool,
+
+    /// Also execute `main` under other schedules of its tasks,
+    /// dumping the model checking report into `model-check.ref`.
+    model_check: bool,
+
+    /// Also record the history of executing `main`, dumping the local
+    /// variables at each step into `history.ref`.
+    history: bool,
+
+    /// Also debug `main` with `dada dap`, stopping on the lines of the queries,
+    /// and dump a transcript of the session into `dap.ref`.
+    dap: bool,
+
+    /// Execute `main` as if its deadline has already passed, so that it stops
+    /// the first time the deadline is checked. This tests the time limit
+    /// without depending on how fast the machine is.
+    deadline_passed: bool,
+}
+
+fn test_options(path: &Path) -> eyre::Result<TestOptions> {
+    let file_contents = std::fs::read_to_string(path)?;
+
+    let re = regex::Regex::new(r"^\s*#:\s*(?P<option>[^\s]+)\s*$").unwrap();
+
+    let mut result = TestOptions::default();
+    for (line, line_number) in file_contents.lines().zip(1..) {
+        if let Some(c) = re.captures(line) {
+            match &c["option"] {
+                "warn_shadowing" => result.warn_shadowing = true,
+                "warn_unused" => result.warn_unused = true,
+                "optimize_bir" => result.optimize_bir = true,
+                "model_check" => result.model_check = true,
+                "dap" => result.dap = true,
+                "history" => result.history = true,
+                "deadline_passed" => result.deadline_passed = true,
+                o => eyre::bail!("unexpected test option `{}` on line {}", o, line_number),
+            }
+        }
+    }
+    Ok(result)
+}
+
 /// Returns the diagnostics that we expect to see in the file, sorted by line number.
 fn expected_diagnostics(path: &Path) -> eyre::Result<Vec<ExpectedDiagnostic>> {
     let file_contents = std::fs::read_to_string(path)?;
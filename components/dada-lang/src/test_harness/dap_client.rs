@@ -0,0 +1,237 @@
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{ChildStdout, Command, Stdio};
+
+use serde_json::{json, Value};
+
+/// A scripted debugger session with `dada dap`.
+pub(crate) struct ChildSession {
+    child: std::process::Child,
+    stdout: BufReader<ChildStdout>,
+
+    /// Sequence number of the last request sent.
+    seq: i64,
+
+    /// Events received while waiting for a response.
+    events: VecDeque<Value>,
+}
+
+impl Drop for ChildSession {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+    }
+}
+
+impl ChildSession {
+    pub fn spawn() -> ChildSession {
+        let mut child = Command::new("cargo")
+            .arg("dada")
+            .arg("dap")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("Failed to spawn child process");
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+
+        ChildSession {
+            child,
+            stdout,
+            seq: 0,
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Debugs `program`, stopping at each of `breakpoint_lines`. At each stop, records the
+    /// stack, the local variables of the topmost frame (and the fields of the objects they
+    /// refer to), and the heap graph, then continues. Returns a transcript of the session.
+    pub fn debug(&mut self, program: &Path, breakpoint_lines: &[u32]) -> eyre::Result<String> {
+        let mut transcript = String::new();
+
+        self.send_request("initialize", json!({ "adapterID": "dada" }))?;
+        self.send_request(
+            "launch",
+            json!({ "program": program, "stopOnEntry": false }),
+        )?;
+        let response = self.send_request(
+            "setBreakpoints",
+            json!({
+                "source": { "path": program },
+                "breakpoints": breakpoint_lines
+                    .iter()
+                    .map(|line| json!({ "line": line }))
+                    .collect::<Vec<_>>(),
+            }),
+        )?;
+        for breakpoint in response["breakpoints"].as_array().into_iter().flatten() {
+            writeln!(
+                transcript,
+                "breakpoint at line {}: verified = {}",
+                breakpoint["line"], breakpoint["verified"]
+            )?;
+        }
+        self.send_request("configurationDone", json!({}))?;
+
+        loop {
+            let event = self.receive_event()?;
+            match event["event"].as_str() {
+                Some("output") => write!(
+                    transcript,
+                    "{}: {}",
+                    event["body"]["category"].as_str().unwrap_or("output"),
+                    event["body"]["output"].as_str().unwrap_or("")
+                )?,
+                Some("stopped") => {
+                    writeln!(
+                        transcript,
+                        "stopped ({})",
+                        event["body"]["reason"].as_str().unwrap_or("")
+                    )?;
+                    self.record_stop(&mut transcript)?;
+                    self.send_request("continue", json!({ "threadId": 1 }))?;
+                }
+                Some("exited") => {
+                    writeln!(transcript, "exited with code {}", event["body"]["exitCode"])?
+                }
+                Some("terminated") => break,
+                _ => {}
+            }
+        }
+
+        self.send_request("disconnect", json!({}))?;
+        Ok(transcript)
+    }
+
+    fn record_stop(&mut self, transcript: &mut String) -> eyre::Result<()> {
+        let stack_trace = self.send_request("stackTrace", json!({ "threadId": 1 }))?;
+        let stack_frames = stack_trace["stackFrames"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        for frame in &stack_frames {
+            writeln!(
+                transcript,
+                "  {} at {}:{}:{}",
+                frame["name"].as_str().unwrap_or(""),
+                frame["source"]["path"].as_str().unwrap_or(""),
+                frame["line"],
+                frame["column"]
+            )?;
+        }
+
+        if let Some(top) = stack_frames.first() {
+            let scopes = self.send_request("scopes", json!({ "frameId": top["id"] }))?;
+            for scope in scopes["scopes"].as_array().cloned().unwrap_or_default() {
+                writeln!(transcript, "  {}:", scope["name"].as_str().unwrap_or(""))?;
+                for variable in self.variables(&scope["variablesReference"])? {
+                    writeln!(
+                        transcript,
+                        "    {} = {}",
+                        variable["name"].as_str().unwrap_or(""),
+                        variable["value"].as_str().unwrap_or("")
+                    )?;
+                    if variable["variablesReference"] != 0 {
+                        for field in self.variables(&variable["variablesReference"])? {
+                            writeln!(
+                                transcript,
+                                "      {} = {}",
+                                field["name"].as_str().unwrap_or(""),
+                                field["value"].as_str().unwrap_or("")
+                            )?;
+                        }
+                    }
+                }
+            }
+        }
+
+        let heap_graph = self.send_request("heapGraph", json!({}))?;
+        writeln!(transcript, "  heap graph:")?;
+        write!(
+            transcript,
+            "{}",
+            heap_graph["graphviz"].as_str().unwrap_or("")
+        )?;
+        Ok(())
+    }
+
+    fn variables(&mut self, reference: &Value) -> eyre::Result<Vec<Value>> {
+        let response =
+            self.send_request("variables", json!({ "variablesReference": reference }))?;
+        Ok(response["variables"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    /// Sends a request and returns the body of its response, queueing any events
+    /// that arrive in the meantime.
+    fn send_request(&mut self, command: &str, arguments: Value) -> eyre::Result<Value> {
+        self.seq += 1;
+        let seq = self.seq;
+        let msg_raw = json!({
+            "seq": seq,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        })
+        .to_string();
+
+        let child_stdin = self.child.stdin.as_mut().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::BrokenPipe, "can connect to child stdin")
+        })?;
+        child_stdin.write_all(format!("Content-Length: {}\r\n\r\n", msg_raw.len()).as_bytes())?;
+        child_stdin.write_all(msg_raw.as_bytes())?;
+        child_stdin.flush()?;
+
+        loop {
+            let message = self.receive()?;
+            if message["type"] == "response" && message["request_seq"] == seq {
+                if message["success"] != true {
+                    eyre::bail!("`{}` failed: {}", command, message["message"]);
+                }
+                return Ok(message["body"].clone());
+            }
+            if message["type"] == "event" {
+                self.events.push_back(message);
+            }
+        }
+    }
+
+    fn receive_event(&mut self) -> eyre::Result<Value> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(event);
+            }
+            let message = self.receive()?;
+            if message["type"] == "event" {
+                return Ok(message);
+            }
+        }
+    }
+
+    fn receive(&mut self) -> eyre::Result<Value> {
+        let mut num_bytes = None;
+        loop {
+            let mut line = String::new();
+            if self.stdout.read_line(&mut line)? == 0 {
+                eyre::bail!("the debug adapter exited");
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(digits) = line.strip_prefix("Content-Length:") {
+                num_bytes = Some(digits.trim().parse::<usize>()?);
+            }
+        }
+
+        let num_bytes = match num_bytes {
+            Some(num_bytes) => num_bytes,
+            None => eyre::bail!("message without a `Content-Length` header"),
+        };
+        let mut buffer = vec![0u8; num_bytes];
+        self.stdout.read_exact(&mut buffer)?;
+        Ok(serde_json::from_slice(&buffer)?)
+    }
+}
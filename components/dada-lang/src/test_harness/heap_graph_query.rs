@@ -25,6 +25,8 @@ impl super::Options {
         let db = &mut dada_db::Db::default();
         db.update_file(filename, in_db.file_source(filename).clone());
         db.set_breakpoints(filename, vec![LineColumn::new1(query.line, query.column)]);
+        let optimize_bir = in_db.optimize_bir(filename);
+        db.set_optimize_bir(filename, optimize_bir);
 
         let breakpoint = dada_breakpoint::breakpoint::find(
             db,
@@ -60,7 +62,11 @@ impl super::Options {
 
         let output_matched = query.message.is_match(&actual_output);
 
-        let ref_path = path.join(format!("HeapGraph-{query_index}.ref"));
+        let ref_path = if optimize_bir {
+            path.join(format!("HeapGraph-{query_index}-optimized.ref"))
+        } else {
+            path.join(format!("HeapGraph-{query_index}.ref"))
+        };
         self.check_output_against_ref_file(actual_output, &ref_path, errors)?;
 
         if !output_matched {
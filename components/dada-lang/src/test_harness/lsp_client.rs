@@ -122,14 +122,14 @@ impl ChildSession {
     }
 
     #[allow(deprecated)]
-    pub fn send_init(&mut self) -> eyre::Result<()> {
+    pub fn send_init(&mut self, initialization_options: serde_json::Value) -> eyre::Result<()> {
         self.send_request::<Initialize>(
             serde_json::from_str("22")?,
             lsp_types::InitializeParams {
                 process_id: None,
                 root_path: None,
                 root_uri: None,
-                initialization_options: None,
+                initialization_options: Some(initialization_options),
                 capabilities: ClientCapabilities {
                     workspace: None,
                     text_document: None,
@@ -12,14 +12,18 @@ pub struct LspServerDatabase {
     db: dada_db::Db,
     threads: threadpool::ThreadPool,
     sender: Sender<Message>,
+    warn_shadowing: bool,
+    warn_unused: bool,
 }
 
 impl LspServerDatabase {
-    pub fn new(sender: Sender<Message>) -> Self {
+    pub fn new(sender: Sender<Message>, warn_shadowing: bool, warn_unused: bool) -> Self {
         Self {
             db: Default::default(),
             threads: Default::default(),
             sender,
+            warn_shadowing,
+            warn_unused,
         }
     }
 
@@ -32,6 +36,8 @@ impl LspServerDatabase {
         let filename = self.filename_from_uri(&params.text_document.uri);
         let source_text = params.text_document.text;
         self.db.update_file(filename, source_text);
+        self.db.set_warn_shadowing(filename, self.warn_shadowing);
+        self.db.set_warn_unused(filename, self.warn_unused);
         self.spawn_check(
             params.text_document.uri,
             params.text_document.version,
@@ -45,6 +51,8 @@ impl LspServerDatabase {
         let change = params.content_changes.into_iter().next().unwrap();
         let source_text = change.text;
         self.db.update_file(filename, source_text);
+        self.db.set_warn_shadowing(filename, self.warn_shadowing);
+        self.db.set_warn_unused(filename, self.warn_unused);
         self.spawn_check(
             params.text_document.uri,
             params.text_document.version,
@@ -123,6 +131,21 @@ impl DadaLspMethods for dada_db::Db {
                     location: self.lsp_location(label.span),
                     message: label.message,
                 })
+                .chain(dada_diagnostic.children.into_iter().map(|child| {
+                    DiagnosticRelatedInformation {
+                        location: self.lsp_location(child.span),
+                        message: format!(
+                            "{}: {}",
+                            match child.severity {
+                                dada_ir::diagnostic::Severity::Help => "help",
+                                dada_ir::diagnostic::Severity::Note => "note",
+                                dada_ir::diagnostic::Severity::Warning => "warning",
+                                dada_ir::diagnostic::Severity::Error => "error",
+                            },
+                            child.message
+                        ),
+                    }
+                }))
                 .collect(),
         );
         let tags = None;
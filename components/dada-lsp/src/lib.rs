@@ -23,7 +23,7 @@ impl LspServer {
         let (connection, io_threads) = Connection::stdio();
 
         // Run the server
-        let (id, _params) = connection.initialize_start()?;
+        let (id, params) = connection.initialize_start()?;
 
         // let init_params: InitializeParams = serde_json::from_value(params).unwrap();
         // let client_capabilities: ClientCapabilities = init_params.capabilities;
@@ -39,7 +39,19 @@ impl LspServer {
 
         connection.initialize_finish(id, initialize_data)?;
 
-        let db = LspServerDatabase::new(connection.sender.clone());
+        // Clients can opt into (or out of) warnings via `initializationOptions`.
+        let warn_shadowing = params
+            .get("initializationOptions")
+            .and_then(|options| options.get("warnShadowing"))
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false);
+        let warn_unused = params
+            .get("initializationOptions")
+            .and_then(|options| options.get("warnUnused"))
+            .and_then(|value| value.as_bool())
+            .unwrap_or(true);
+
+        let db = LspServerDatabase::new(connection.sender.clone(), warn_shadowing, warn_unused);
 
         Ok(Self {
             connection,
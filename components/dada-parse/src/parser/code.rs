@@ -167,6 +167,7 @@ impl CodeParser<'_, '_> {
     ///       | Expr BinaryOp Expr
     ///       | Expr ( args )
     ///       | SharingMode? Id = Expr
+    ///       | Expr := Expr
     /// ```
     #[tracing::instrument(level = "debug", skip(self))]
     pub(crate) fn parse_expr(&mut self) -> Option<Expr> {
@@ -189,6 +190,7 @@ impl CodeParser<'_, '_> {
                     Op::MinusEqual,
                     Op::DividedByEqual,
                     Op::TimesEqual,
+                    Op::ColonEqual,
                 ],
                 Self::parse_expr_4,
             ) {
@@ -447,6 +449,7 @@ impl CodeParser<'_, '_> {
                     Op::PlusEqual | Op::MinusEqual | Op::DividedByEqual | Op::TimesEqual => {
                         return Some(self.add(ExprData::OpEq(base, op, rhs), span))
                     }
+                    Op::ColonEqual => return Some(self.add(ExprData::Assign(base, rhs), span)),
                     _ => return Some(self.add(ExprData::Op(base, op, rhs), span)),
                 }
             }
@@ -5,9 +5,14 @@
 #![allow(incomplete_features)]
 
 mod validate;
+pub mod warnings;
 
 #[salsa::jar(Db)]
-pub struct Jar(validate::root_definitions, validate::validate_function);
+pub struct Jar(
+    validate::root_definitions,
+    validate::validate_function,
+    warnings::warn_shadowing,
+);
 
 pub trait Db: salsa::DbWithJar<Jar> + dada_ir::Db + dada_parse::Db {}
 
@@ -73,6 +73,11 @@ impl<'me> Scope<'me> {
     pub(crate) fn lookup(&self, name: Word) -> Option<Definition> {
         self.names.get(&name).copied()
     }
+
+    /// All names in scope (locals, items, and intrinsics).
+    pub(crate) fn names(&self) -> impl Iterator<Item = Word> + '_ {
+        self.names.keys().copied()
+    }
 }
 
 impl RootDefinitions {
@@ -115,6 +115,97 @@ impl<'me> Validator<'me> {
         self.code.syntax_tree(self.db).spans(self.db)[e].in_file(self.code.filename(self.db))
     }
 
+    fn name_span(&self, decl: LocalVariableDecl) -> FileSpan {
+        self.code.syntax_tree(self.db).spans(self.db)[decl]
+            .name_span
+            .in_file(self.code.filename(self.db))
+    }
+
+    /// Brings `local_variable` (declared by `decl`) into scope. If the user
+    /// has asked for it, warns when this shadows another local variable,
+    /// a function, or a class.
+    fn insert_local_variable(
+        &mut self,
+        decl: LocalVariableDecl,
+        local_variable: validated::LocalVariable,
+    ) {
+        let name = decl.data(self.syntax_tables()).name;
+        let shadowed = match self.scope.insert(name, local_variable) {
+            Some(definition) => definition,
+            None => return,
+        };
+
+        if !crate::warnings::warn_shadowing(self.db, self.code.filename(self.db)) {
+            return;
+        }
+
+        let (kind, shadowed_span) = match shadowed {
+            Definition::LocalVariable(lv) => match self.origins[lv] {
+                validated::LocalVariableOrigin::LocalVariable(decl) => {
+                    ("variable", self.name_span(decl))
+                }
+                validated::LocalVariableOrigin::Parameter(decl) => {
+                    ("parameter", self.name_span(decl))
+                }
+                validated::LocalVariableOrigin::Temporary(_) => return,
+            },
+            Definition::Function(f) => ("function", f.name_span(self.db)),
+            Definition::Class(c) => ("class", c.name_span(self.db)),
+
+            // Intrinsics are meant to be shadowable.
+            Definition::Intrinsic(_) => return,
+        };
+
+        dada_ir::warning!(
+            self.name_span(decl),
+            "`{}` shadows a {} with the same name",
+            name.as_str(self.db),
+            kind,
+        )
+        .secondary_label(shadowed_span, format!("the {} is here", kind))
+        .emit(self.db);
+    }
+
+    /// Warns when `place` is a `shared` local variable, since those are not
+    /// meant to be assigned after they are declared. The assignment itself
+    /// succeeds at runtime (a local is only reachable from its own stack frame),
+    /// so this is the only place the mismatch gets reported.
+    fn check_assignment_target(&self, place: validated::Place, assign_expr: syntax::Expr) {
+        let lv = match place.data(self.tables) {
+            validated::PlaceData::LocalVariable(lv) => *lv,
+            _ => return,
+        };
+
+        let lv_data = lv.data(self.tables);
+        if lv_data.storage_mode != StorageMode::Shared {
+            return;
+        }
+
+        let decl = match self.origins[lv] {
+            validated::LocalVariableOrigin::LocalVariable(decl)
+            | validated::LocalVariableOrigin::Parameter(decl) => decl,
+            validated::LocalVariableOrigin::Temporary(_) => return,
+        };
+        let name = decl.data(self.syntax_tables()).name;
+
+        dada_ir::warning!(
+            self.span(assign_expr),
+            "assignment to shared variable `{}`",
+            name.as_str(self.db),
+        )
+        .secondary_label(self.name_span(decl), "variable declared here")
+        .child(
+            dada_ir::help!(
+                self.name_span(decl),
+                "declare the variable with `var` to allow assignment: `var {}`",
+                name.as_str(self.db),
+            )
+            .skip_primary_label()
+            .finish(),
+        )
+        .emit(self.db);
+    }
+
     fn empty_tuple(&mut self, origin: syntax::Expr) -> validated::Expr {
         self.add(validated::ExprData::Tuple(vec![]), origin)
     }
@@ -129,7 +220,7 @@ impl<'me> Validator<'me> {
             },
             validated::LocalVariableOrigin::Parameter(decl),
         );
-        self.scope.insert(decl_data.name, local_variable);
+        self.insert_local_variable(decl, local_variable);
     }
 
     #[tracing::instrument(level = "debug", skip_all)]
@@ -260,7 +351,7 @@ impl<'me> Validator<'me> {
                 );
 
                 let validated_initializer_expr = self.validate_expr_to_value(*initializer_expr);
-                self.scope.insert(decl_data.name, local_variable);
+                self.insert_local_variable(*decl, local_variable);
                 self.add(
                     validated::ExprData::Assign(place, validated_initializer_expr),
                     expr,
@@ -380,6 +471,7 @@ impl<'me> Validator<'me> {
                 let result = try {
                     let (validated_opt_temp_expr, validated_lhs_place) =
                         self.validate_expr_as_place(*lhs_expr)?;
+                    self.check_assignment_target(validated_lhs_place, expr);
                     let validated_lhs_expr =
                         self.add(validated::ExprData::Give(validated_lhs_place), expr);
                     let validated_rhs_expr = self.validate_expr(*rhs_expr);
@@ -405,6 +497,7 @@ impl<'me> Validator<'me> {
                 let place = try {
                     let (validated_opt_temp_expr, validated_lhs_place) =
                         self.validate_expr_as_place(*lhs_expr)?;
+                    self.check_assignment_target(validated_lhs_place, expr);
                     let validated_rhs_expr = self.validate_expr(*rhs_expr);
                     let assign_expr = self.add(
                         validated::ExprData::Assign(validated_lhs_place, validated_rhs_expr),
@@ -484,12 +577,23 @@ impl<'me> Validator<'me> {
                         self.add(validated::PlaceData::Intrinsic(i), expr)
                     }
                     None => {
+                        let span = self.span(expr);
+                        let suggestions = dada_ir::edit_distance::similar_words(
+                            self.db,
+                            *name,
+                            self.scope.names(),
+                        );
                         return Err(dada_ir::error!(
-                            self.span(expr),
+                            span,
                             "can't find anything named `{}`",
                             name.as_str(self.db)
                         )
-                        .emit(self.db))
+                        .maybe_child(dada_ir::edit_distance::did_you_mean(
+                            self.db,
+                            span,
+                            &suggestions,
+                        ))
+                        .emit(self.db));
                     }
                 },
             )),
@@ -0,0 +1,10 @@
+use dada_ir::filename::Filename;
+
+/// Salsa input: whether to warn when a local variable shadows another
+/// local variable, a function, or a class.
+///
+/// Defaults to false if not explicitly set.
+#[salsa::memoized(in crate::Jar)]
+pub fn warn_shadowing(_db: &dyn crate::Db, _filename: Filename) -> bool {
+    false // default: off
+}
@@ -4,6 +4,9 @@ const workerURL = 'ace/viz.render.js';
 let viz = new Viz({ workerURL });
 let Range = ace.require("ace/range").Range;
 
+// When recording the execution history, keep this many of the most recent steps.
+const HISTORY_STEPS = 1000;
+
 class Queue {
     constructor() {
         this.active = 0;
@@ -53,6 +56,14 @@ class DadaCompiler {
         }
     }
 
+    setHistory(enabled) {
+        if (enabled) {
+            this.dada = this.dada.with_history(HISTORY_STEPS);
+        } else {
+            this.dada = this.dada.without_history();
+        }
+    }
+
     async execute() {
         this.dada = await this.dada.execute();
     }
@@ -84,6 +95,26 @@ class DadaCompiler {
     get heaps() {
         return [this.dada.heap_before, this.dada.heap_after];
     }
+
+    get num_history_steps() {
+        return this.dada.num_history_steps;
+    }
+
+    history_clock(i) {
+        return this.dada.history_clock(i);
+    }
+
+    history_range(i) {
+        return this.dada.history_range(i);
+    }
+
+    history_heap(i) {
+        return this.dada.history_heap(i);
+    }
+
+    history_locals(i) {
+        return this.dada.history_locals(i);
+    }
 }
 
 init()
@@ -121,6 +152,22 @@ init()
             await copyClipboardUrl(editor);
         }
 
+        var historyCheckbox = document.getElementById("historyCheckbox");
+        historyCheckbox.onchange = function (event) {
+            let text = editor.getValue();
+            queue.submit(async function () {
+                dada.setHistory(historyCheckbox.checked);
+                await updateOutput(dada, editor, text, null);
+            });
+        }
+
+        var historySlider = document.getElementById("historySlider");
+        historySlider.oninput = function (event) {
+            queue.submit(async function () {
+                await showHistoryStep(dada, editor, Number(historySlider.value));
+            });
+        }
+
         let text = editor.getValue();
         queue.submit(async function () {
             await updateOutput(dada, editor, text, null);
@@ -205,10 +252,15 @@ async function updateOutput(dada, editor, text, cursor) {
         let severity = diagnostic.severity == "error" ? "error"
             : diagnostic.severity == "warning" ? "warning" :
                 "information";
+        let text = primary_label.message;
+        for (let j = 0; j < diagnostic.num_children; j++) {
+            let child = diagnostic.child(j);
+            text += "\n" + child.severity + ": " + child.primary_label.message;
+        }
         annotations.push({
             row: primary_label.start.line0,
             column: primary_label.start.column0,
-            text: primary_label.message,
+            text: text,
             type: severity,
         });
     }
@@ -249,6 +301,49 @@ async function updateOutput(dada, editor, text, cursor) {
     let [heap_before, heap_after] = dada.heaps;
     await render(heap_before, "heap-before");
     await render(heap_after, "heap-after");
+
+    // If the execution history was recorded, let the user go back through it,
+    // starting from the last step.
+    let historySlider = document.getElementById("historySlider");
+    let num_steps = dada.num_history_steps;
+    historySlider.disabled = (num_steps == 0);
+    historySlider.max = Math.max(num_steps - 1, 0);
+    historySlider.value = historySlider.max;
+    await showHistoryStep(dada, editor, num_steps - 1);
+}
+
+// Show the state of the program just before the recorded step with the given index
+// (or nothing, if the index is out of range), and highlight the code that the step executes.
+async function showHistoryStep(dada, editor, index) {
+    for (let [id, marker] of Object.entries(editor.session.getMarkers(false))) {
+        if (marker.clazz == "history-step") {
+            editor.session.removeMarker(id);
+        }
+    }
+
+    if (index < 0 || index >= dada.num_history_steps) {
+        document.getElementById("historySpan").innerText = "";
+        document.getElementById("historyLocals").innerText = "";
+        await render("", "history-heap");
+        return;
+    }
+
+    let range = dada.history_range(index);
+    editor.session.addMarker(
+        new Range(
+            range.start.line0,
+            range.start.column0 - 1,
+            range.end.line0,
+            range.end.column0 - 1,
+        ),
+        "history-step",
+        "text",
+        false
+    );
+    document.getElementById("historySpan").innerText =
+        "step " + dada.history_clock(index) + " (line " + (range.start.line0 + 1) + ")";
+    document.getElementById("historyLocals").innerText = dada.history_locals(index);
+    await render(dada.history_heap(index), "history-heap");
 }
 
 async function render(heap_string, id) {
@@ -9,7 +9,6 @@ pub struct DadaDiagnostic {
     primary_label: DadaLabel,
     #[allow(dead_code)]
     secondary_labels: Vec<DadaLabel>,
-    #[allow(dead_code)]
     children: Vec<DadaDiagnostic>,
 }
 
@@ -58,6 +57,16 @@ impl DadaDiagnostic {
     pub fn primary_label(&self) -> DadaLabel {
         self.primary_label.clone()
     }
+
+    #[wasm_bindgen(getter)]
+    pub fn num_children(&self) -> usize {
+        self.children.len()
+    }
+
+    #[wasm_bindgen]
+    pub fn child(&self, index: usize) -> DadaDiagnostic {
+        self.children[index].clone()
+    }
 }
 
 #[wasm_bindgen]
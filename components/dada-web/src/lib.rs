@@ -1,7 +1,7 @@
 #![allow(clippy::unused_unit)] // wasm-bindgen seems to trigger this
 
 use dada_error_format::format_diagnostics;
-use dada_execute::kernel::BufferKernel;
+use dada_execute::{history::History, kernel::BufferKernel, DiagnosticError, HeapStats};
 use dada_ir::{filename::Filename, span::LineColumn};
 use diagnostics::DadaDiagnostic;
 use range::DadaRange;
@@ -11,6 +11,25 @@ use wasm_bindgen::prelude::*;
 mod diagnostics;
 mod range;
 
+/// Unless configured otherwise, execution stops after this many steps...
+const DEFAULT_MAX_STEPS: u64 = 10_000_000;
+
+/// ...or after this many milliseconds, so that a program that loops
+/// forever does not hang the browser tab.
+const DEFAULT_TIMEOUT_MS: f64 = 5_000.0;
+
+/// Unless configured otherwise, execution stops once the live objects take
+/// up more than this many bytes, so that runaway allocation does not exhaust
+/// the memory of the browser tab.
+const DEFAULT_MAX_HEAP_BYTES: usize = 64 * 1024 * 1024;
+
+#[wasm_bindgen]
+extern "C" {
+    /// `Date.now()`: milliseconds since the epoch.
+    #[wasm_bindgen(js_namespace = Date, js_name = now)]
+    fn date_now() -> f64;
+}
+
 #[wasm_bindgen(start)]
 pub fn start() -> Result<(), JsValue> {
     console_error_panic_hook::set_once();
@@ -40,6 +59,28 @@ pub struct DadaCompiler {
     heap_capture: Vec<(String, String)>,
 
     breakpoint_ranges: Vec<DadaRange>,
+
+    /// Maximum number of steps to execute (if None, `DEFAULT_MAX_STEPS`).
+    max_steps: Option<u64>,
+
+    /// Maximum time to execute, in milliseconds (if None, `DEFAULT_TIMEOUT_MS`).
+    timeout_ms: Option<f64>,
+
+    /// Maximum number of live objects (if None, unlimited).
+    max_objects: Option<usize>,
+
+    /// Maximum bytes taken by live objects (if None, `DEFAULT_MAX_HEAP_BYTES`).
+    max_heap_bytes: Option<usize>,
+
+    /// Statistics about the heap when the program finished.
+    heap_stats: HeapStats,
+
+    /// If set, record the state of the program at every step,
+    /// keeping this many of the most recent steps.
+    history_limit: Option<usize>,
+
+    /// The recorded state of the program at every step, if `history_limit` is set.
+    history: Option<History>,
 }
 
 #[wasm_bindgen]
@@ -75,17 +116,73 @@ impl DadaCompiler {
         self
     }
 
+    #[wasm_bindgen]
+    pub fn with_max_steps(mut self, max_steps: u64) -> Self {
+        self.max_steps = Some(max_steps);
+        self
+    }
+
+    #[wasm_bindgen]
+    pub fn with_timeout_ms(mut self, timeout_ms: f64) -> Self {
+        self.timeout_ms = Some(timeout_ms);
+        self
+    }
+
+    #[wasm_bindgen]
+    pub fn with_max_objects(mut self, max_objects: usize) -> Self {
+        self.max_objects = Some(max_objects);
+        self
+    }
+
+    #[wasm_bindgen]
+    pub fn with_max_heap_bytes(mut self, max_heap_bytes: usize) -> Self {
+        self.max_heap_bytes = Some(max_heap_bytes);
+        self
+    }
+
+    /// Record the state of the program at every step (keeping the most recent
+    /// `max_steps`), so that the user can go back to earlier moments.
+    #[wasm_bindgen]
+    pub fn with_history(mut self, max_steps: usize) -> Self {
+        self.history_limit = Some(max_steps);
+        self
+    }
+
+    #[wasm_bindgen]
+    pub fn without_history(mut self) -> Self {
+        self.history_limit = None;
+        self
+    }
+
     #[wasm_bindgen]
     pub async fn execute(mut self) -> Self {
         let filename = self.filename();
         let diagnostics = self.db.diagnostics(filename);
 
-        let mut kernel = BufferKernel::new().stop_at_breakpoint(true);
+        let deadline = date_now() + self.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS);
+        let mut kernel = BufferKernel::new()
+            .stop_at_breakpoint(true)
+            .max_steps(self.max_steps.unwrap_or(DEFAULT_MAX_STEPS))
+            .deadline(move || date_now() >= deadline)
+            .max_heap_bytes(self.max_heap_bytes.unwrap_or(DEFAULT_MAX_HEAP_BYTES));
+        if let Some(max_objects) = self.max_objects {
+            kernel = kernel.max_objects(max_objects);
+        }
+        if let Some(history_limit) = self.history_limit {
+            kernel = kernel.record_history(history_limit);
+        }
+
+        let mut runtime_diagnostic = None;
         match self.db.function_named(filename, "main") {
             Some(function) => {
-                kernel
-                    .interpret_and_buffer(&self.db, function, vec![])
-                    .await;
+                if let Err(error) = kernel.interpret(&self.db, function, vec![]).await {
+                    kernel.append(&error.to_string());
+
+                    // Show errors from the program itself in the editor too.
+                    if let Some(diagnostic_error) = error.downcast_ref::<DiagnosticError>() {
+                        runtime_diagnostic = Some(diagnostic_error.diagnostic().clone());
+                    }
+                }
             }
             None => {
                 kernel.append(&format!(
@@ -96,6 +193,8 @@ impl DadaCompiler {
         };
 
         self.output = kernel.take_buffer();
+        self.heap_stats = kernel.heap_stats().unwrap_or_default();
+        self.history = kernel.take_history();
         let heap_graphs = kernel.take_recorded_breakpoints();
 
         tracing::info!(
@@ -109,6 +208,7 @@ impl DadaCompiler {
         );
 
         self.diagnostics = diagnostics.to_owned();
+        self.diagnostics.extend(runtime_diagnostic);
 
         self.breakpoint_ranges = heap_graphs
             .iter()
@@ -160,6 +260,26 @@ impl DadaCompiler {
         self.output.clone()
     }
 
+    #[wasm_bindgen(getter)]
+    pub fn live_objects(&self) -> usize {
+        self.heap_stats.live_objects
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn live_bytes(&self) -> usize {
+        self.heap_stats.live_bytes
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn peak_objects(&self) -> usize {
+        self.heap_stats.peak_objects
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn peak_bytes(&self) -> usize {
+        self.heap_stats.peak_bytes
+    }
+
     #[wasm_bindgen(getter)]
     pub fn heap_before(&self) -> String {
         if self.heap_capture.is_empty() {
@@ -177,4 +297,48 @@ impl DadaCompiler {
 
         self.heap_capture[0].1.clone()
     }
+
+    /// Number of steps recorded (see [`Self::with_history`]).
+    #[wasm_bindgen(getter)]
+    pub fn num_history_steps(&self) -> usize {
+        self.history.as_ref().map_or(0, |history| history.len())
+    }
+
+    /// The clock tick of the recorded step with the given index (0 being the oldest).
+    #[wasm_bindgen]
+    pub fn history_clock(&self, index: usize) -> u64 {
+        self.history_step(index).clock
+    }
+
+    /// The index of the recorded step that shows the state of the program
+    /// at the given clock tick, if it is still recorded.
+    #[wasm_bindgen]
+    pub fn history_index_at(&self, clock: u64) -> Option<usize> {
+        self.history.as_ref()?.index_at(clock)
+    }
+
+    /// The code that the recorded step with the given index executes.
+    #[wasm_bindgen]
+    pub fn history_range(&self, index: usize) -> DadaRange {
+        DadaRange::from(&self.db, self.history_step(index).span)
+    }
+
+    /// Graphviz source for the heap just before the recorded step with the given index.
+    #[wasm_bindgen]
+    pub fn history_heap(&self, index: usize) -> String {
+        self.history_step(index)
+            .heap_graph
+            .graphviz_alone(&self.db, false)
+    }
+
+    /// The local variables just before the recorded step with the given index,
+    /// one per line.
+    #[wasm_bindgen]
+    pub fn history_locals(&self, index: usize) -> String {
+        self.history_step(index).heap_graph.locals_text(&self.db)
+    }
+
+    fn history_step(&self, index: usize) -> &dada_execute::history::Snapshot {
+        self.history.as_ref().unwrap().get(index).unwrap()
+    }
 }